@@ -0,0 +1,151 @@
+//! Recording a search as an animation: the board after each `step()`, drawn
+//! offscreen like the image exports and written as an animated GIF or APNG.
+
+use crate::render::{BoardView, Scene};
+use crate::solver::{Board, Solver};
+
+/// Longest recording, so a runaway range can't exhaust memory.
+pub const MAX_FRAMES: usize = 2000;
+
+/// The boards of a search from step `from` on (step 0 is the board before
+/// the first step), at most `frames` of them. Stops early when the search
+/// is finished.
+pub fn record(solver: &mut dyn Solver, from: u64, frames: usize) -> Vec<Board> {
+    let frames = frames.min(MAX_FRAMES);
+    let mut boards = Vec::new();
+    let mut step = 0;
+    while boards.len() < frames {
+        if step >= from {
+            boards.push(solver.board().clone());
+        }
+        if solver.is_finished() {
+            break;
+        }
+        solver.step();
+        step += 1;
+    }
+    boards
+}
+
+/// Each board as (width, height, RGB), all the same size.
+fn rasterise(
+    boards: &[Board],
+    view: &BoardView,
+    cell: f32,
+) -> Result<Vec<(u32, u32, Vec<u8>)>, String> {
+    if boards.is_empty() {
+        return Err("there are no steps in that range".to_owned());
+    }
+    boards
+        .iter()
+        .map(|board| Scene::single(board, view, cell).to_rgb())
+        .collect()
+}
+
+/// A looping GIF. Its delays count hundredths of a second, so `delay_ms` is
+/// rounded to those.
+pub fn to_gif(
+    boards: &[Board],
+    view: &BoardView,
+    cell: f32,
+    delay_ms: u32,
+) -> Result<Vec<u8>, String> {
+    let frames = rasterise(boards, view, cell)?;
+    let (width, height) = (frames[0].0, frames[0].1);
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(format!("a {}x{} image is too large for GIF", width, height));
+    }
+    let error = |e: gif::EncodingError| format!("could not encode GIF: {}", e);
+    let mut bytes = Vec::new();
+    {
+        let mut encoder =
+            gif::Encoder::new(&mut bytes, width as u16, height as u16, &[]).map_err(error)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(error)?;
+        for (_, _, rgb) in &frames {
+            let mut frame = gif::Frame::from_rgb_speed(width as u16, height as u16, rgb, 10);
+            frame.delay = (delay_ms / 10).clamp(1, u16::MAX as u32) as u16;
+            encoder.write_frame(&frame).map_err(error)?;
+        }
+    }
+    Ok(bytes)
+}
+
+/// A looping animated PNG, in full colour.
+pub fn to_apng(
+    boards: &[Board],
+    view: &BoardView,
+    cell: f32,
+    delay_ms: u32,
+) -> Result<Vec<u8>, String> {
+    let frames = rasterise(boards, view, cell)?;
+    let error = |e: png::EncodingError| format!("could not encode APNG: {}", e);
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, frames[0].0, frames[0].1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .set_animated(frames.len() as u32, 0)
+            .map_err(error)?;
+        let delay = delay_ms.clamp(1, u16::MAX as u32) as u16;
+        encoder.set_frame_delay(delay, 1000).map_err(error)?;
+        let mut writer = encoder.write_header().map_err(error)?;
+        for (_, _, rgb) in &frames {
+            writer.write_image_data(rgb).map_err(error)?;
+        }
+        writer.finish().map_err(error)?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{Constraints, Piece, SolverConfig};
+    use crate::Theme;
+
+    fn steps(from: u64, frames: usize) -> Vec<Board> {
+        let mut search = SolverConfig::default().build(6, &Constraints::default());
+        record(search.as_mut(), from, frames)
+    }
+
+    #[test]
+    fn recording_starts_at_the_step_and_stops_at_the_end() {
+        let boards = steps(0, 12);
+        assert_eq!(boards.len(), 12);
+        assert!(boards[0].iter().flatten().all(|&square| square == 0));
+        assert_eq!(steps(5, 7), boards[5..]);
+        let all = steps(0, MAX_FRAMES);
+        assert!(all.len() < MAX_FRAMES);
+        assert_eq!(steps(all.len() as u64 - 1, 10).len(), 1);
+    }
+
+    #[test]
+    fn animations_have_one_frame_per_step() {
+        let theme = Theme::default();
+        let view = BoardView {
+            theme: &theme,
+            piece: &Piece::Queen,
+            fixed: &[],
+            blocked: &[],
+            labels: false,
+        };
+        let boards = steps(0, 9);
+
+        let gif = to_gif(&boards, &view, 8.0, 100).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(&gif[..]).unwrap();
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 10);
+            frames += 1;
+        }
+        assert_eq!(frames, boards.len());
+
+        let apng = to_apng(&boards, &view, 8.0, 100).unwrap();
+        let reader = png::Decoder::new(&apng[..]).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!(control.num_frames as usize, boards.len());
+
+        assert!(to_gif(&[], &view, 8.0, 100).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! Timed puzzles on top of manual play: random boards with pre-placed
+//! queens, graded by how hard the complete search finds them, and a local
+//! leaderboard.
+
+use serde::{Deserialize, Serialize};
+
+use crate::rng::Rng;
+use crate::solver::{
+    Algorithm, BitboardSolver, Constraints, Ordering, RowOrder, Solver, SolverConfig,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
+#[cfg(target_arch = "wasm32")]
+use web_time::{Duration, Instant};
+
+/// Seconds added to the time for every hint taken.
+pub const HINT_PENALTY_SECS: f64 = 30.0;
+/// Seconds added for every move beyond the number of queens to place.
+pub const MOVE_PENALTY_SECS: f64 = 2.0;
+/// Best runs kept per difficulty.
+const LEADERBOARD_SIZE: usize = 10;
+/// Random puzzles tried before settling for the closest grade.
+const MAX_ATTEMPTS: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    fn rank(self) -> usize {
+        Self::ALL.iter().position(|&d| d == self).unwrap()
+    }
+}
+
+/// What an exhaustive bitboard search over a puzzle measured.
+#[derive(Clone, Copy, Debug)]
+pub struct Metrics {
+    /// Ways to complete the pre-placed queens.
+    pub completions: usize,
+    /// Queens the search placed in free columns, dead ends included.
+    pub nodes: u64,
+    /// Columns the player has to fill.
+    pub free_columns: usize,
+}
+
+impl Metrics {
+    /// Effective branching factor `b` with `b^depth = nodes`.
+    pub fn branching(&self) -> f64 {
+        (self.nodes as f64).powf(1.0 / self.free_columns.max(1) as f64)
+    }
+
+    /// Search nodes spent per completion found.
+    pub fn effort(&self) -> f64 {
+        self.nodes as f64 / self.completions.max(1) as f64
+    }
+
+    /// Few completions, a bushy tree and much search per completion make a
+    /// puzzle hard: many plausible moves, few of which lead anywhere.
+    /// About one random puzzle in twenty grades as hard.
+    pub fn difficulty(&self) -> Difficulty {
+        if self.completions <= 2 && self.branching() >= 1.8 && self.effort() >= 25.0 {
+            Difficulty::Hard
+        } else if self.completions <= 12 && self.effort() >= 12.0 {
+            Difficulty::Medium
+        } else {
+            Difficulty::Easy
+        }
+    }
+}
+
+pub struct Puzzle {
+    pub n: usize,
+    pub constraints: Constraints,
+    pub metrics: Metrics,
+}
+
+impl Puzzle {
+    /// A random board size with some queens of a random solution pre-placed.
+    fn random(rng: &mut Rng) -> (usize, Constraints) {
+        let n = 6 + rng.below(5);
+        let config = SolverConfig {
+            algorithm: Algorithm::Backtracking,
+            ordering: Ordering {
+                rows: RowOrder::Random,
+                seed: rng.next_u64(),
+                ..Ordering::default()
+            },
+            ..SolverConfig::default()
+        };
+        let mut search = config.build(n, &Constraints::default());
+        while !search.step() {}
+        let mut rows = vec![0; n];
+        for &(r, c) in &search.solutions().placements[0] {
+            rows[c] = r;
+        }
+
+        let mut columns: Vec<usize> = (0..n).collect();
+        for i in (1..n).rev() {
+            columns.swap(i, rng.below(i + 1));
+        }
+        let given = rng.below(n / 2 + 1);
+        let constraints = Constraints {
+            fixed: columns[..given].iter().map(|&c| (rows[c], c)).collect(),
+            ..Constraints::default()
+        };
+        (n, constraints)
+    }
+}
+
+/// An exhaustive bitboard search over a puzzle, run a step at a time.
+struct Measurement {
+    n: usize,
+    constraints: Constraints,
+    search: BitboardSolver,
+    placements: u64,
+    nodes: u64,
+}
+
+impl Measurement {
+    fn new(n: usize, constraints: Constraints) -> Self {
+        Self {
+            n,
+            search: BitboardSolver::new(n, &constraints),
+            constraints,
+            placements: 0,
+            nodes: 0,
+        }
+    }
+
+    /// One search step; the metrics once the search is over.
+    fn step(&mut self) -> Option<Metrics> {
+        if !self.search.is_finished() {
+            self.search.step();
+            // The search passes through the fixed columns too, placing their
+            // queens again each time; only placements in free columns count
+            let now = self.search.stats().placements;
+            if now > self.placements {
+                self.placements = now;
+                let free = self
+                    .search
+                    .last_placed()
+                    .is_some_and(|(_, col)| self.constraints.fixed_row(col).is_none());
+                self.nodes += u64::from(free);
+            }
+        }
+        self.search.is_finished().then(|| Metrics {
+            completions: self.search.solutions().solutions.len(),
+            // Count the root as one node
+            nodes: self.nodes.max(1),
+            free_columns: self.n - self.constraints.fixed.len(),
+        })
+    }
+}
+
+/// Grades random puzzles until one matches the difficulty, settling for the
+/// closest grade after `MAX_ATTEMPTS`. One search step per `step()`, so the
+/// GUI can spread the grading over frames.
+pub struct PuzzleGenerator {
+    difficulty: Difficulty,
+    rng: Rng,
+    attempts: usize,
+    // The puzzle being graded
+    current: Option<Measurement>,
+    best: Option<Puzzle>,
+    finished: bool,
+}
+
+impl PuzzleGenerator {
+    pub fn new(difficulty: Difficulty, rng: Rng) -> Self {
+        Self {
+            difficulty,
+            rng,
+            attempts: 0,
+            current: None,
+            best: None,
+            finished: false,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Puzzles tried so far, the one being graded included.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    pub fn step(&mut self) {
+        if self.finished {
+            return;
+        }
+        let measurement = self.current.get_or_insert_with(|| {
+            self.attempts += 1;
+            let (n, constraints) = Puzzle::random(&mut self.rng);
+            Measurement::new(n, constraints)
+        });
+        let Some(metrics) = measurement.step() else {
+            return;
+        };
+        let Measurement { n, constraints, .. } = self.current.take().unwrap();
+        let puzzle = Puzzle {
+            n,
+            constraints,
+            metrics,
+        };
+        let distance = |p: &Puzzle| {
+            p.metrics
+                .difficulty()
+                .rank()
+                .abs_diff(self.difficulty.rank())
+        };
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|b| distance(&puzzle) < distance(b))
+        {
+            self.best = Some(puzzle);
+        }
+        self.finished = self.attempts == MAX_ATTEMPTS
+            || self
+                .best
+                .as_ref()
+                .is_some_and(|b| b.metrics.difficulty() == self.difficulty);
+    }
+
+    /// The puzzle found, once finished.
+    pub fn into_puzzle(self) -> Option<Puzzle> {
+        self.best.filter(|_| self.finished)
+    }
+}
+
+/// A puzzle being played against the clock.
+pub struct Challenge {
+    pub n: usize,
+    pub constraints: Constraints,
+    pub difficulty: Difficulty,
+    pub metrics: Metrics,
+    started: Instant,
+    /// The scored run once the board is solved, and its leaderboard place.
+    pub result: Option<(Entry, Option<usize>)>,
+}
+
+impl Challenge {
+    pub fn start(puzzle: &Puzzle, difficulty: Difficulty) -> Self {
+        Self {
+            n: puzzle.n,
+            constraints: puzzle.constraints.clone(),
+            difficulty,
+            metrics: puzzle.metrics,
+            started: Instant::now(),
+            result: None,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match &self.result {
+            Some((entry, _)) => Duration::from_secs_f64(entry.seconds),
+            None => self.started.elapsed(),
+        }
+    }
+
+    /// Stop the clock, score the run (lower is better) and enter it on the
+    /// leaderboard.
+    pub fn finish(&mut self, moves: u64, hints: u64, leaderboard: &mut Leaderboard) {
+        let seconds = self.started.elapsed().as_secs_f64();
+        let extra_moves = moves.saturating_sub(self.metrics.free_columns as u64);
+        let entry = Entry {
+            difficulty: self.difficulty,
+            n: self.n,
+            seconds,
+            moves,
+            hints,
+            score: seconds
+                + hints as f64 * HINT_PENALTY_SECS
+                + extra_moves as f64 * MOVE_PENALTY_SECS,
+        };
+        let place = leaderboard.add(entry.clone());
+        self.result = Some((entry, place));
+    }
+}
+
+/// One finished challenge.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub difficulty: Difficulty,
+    pub n: usize,
+    pub seconds: f64,
+    pub moves: u64,
+    pub hints: u64,
+    /// Seconds including penalties.
+    pub score: f64,
+}
+
+/// Best scores per difficulty, kept in the app's persisted storage.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub entries: Vec<Entry>,
+}
+
+impl Leaderboard {
+    pub const STORAGE_KEY: &'static str = "leaderboard";
+
+    /// Insert a run; returns its place (1-based) if it made the board.
+    pub fn add(&mut self, entry: Entry) -> Option<usize> {
+        let difficulty = entry.difficulty;
+        let score = entry.score;
+        self.entries.push(entry);
+        self.entries.sort_by(|a, b| a.score.total_cmp(&b.score));
+        let mut kept = 0;
+        self.entries.retain(|e| {
+            if e.difficulty != difficulty {
+                return true;
+            }
+            kept += 1;
+            kept <= LEADERBOARD_SIZE
+        });
+        self.best(difficulty)
+            .position(|e| e.score == score)
+            .map(|i| i + 1)
+    }
+
+    pub fn best(&self, difficulty: Difficulty) -> impl Iterator<Item = &Entry> {
+        self.entries
+            .iter()
+            .filter(move |e| e.difficulty == difficulty)
+    }
+}
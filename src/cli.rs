@@ -0,0 +1,639 @@
+//! Headless entry point: `eight_queens_rust <command> ...` runs without a window.
+
+use crate::animation;
+use crate::cnf::{self, Encoding};
+use crate::database;
+use crate::export;
+use crate::latex;
+use crate::render;
+use crate::solver::{
+    self, Algorithm, BitboardSolver, Constraints, Piece, Solver, SolverConfig, Topology,
+    TraceRecorder,
+};
+use crate::store::{self, StoreReader, StoreWriter};
+use crate::trace;
+use crate::validate::{self, Placement};
+use crate::Theme;
+
+const USAGE: &str = "\
+Usage: eight_queens_rust [command] [options]
+
+Without a command the GUI starts.
+
+Commands:
+  cnf <n> [--encoding pairwise|sequential|binary] [--mask FILE] [--toroidal]
+      [--piece queen|rook|amazon] [--out FILE]
+      Write the n-queens problem as DIMACS CNF (stdout by default).
+      A mask file (rows of . free, # blocked, Q fixed queen) adds its
+      constraints; <n> may then be left out. --toroidal wraps the diagonals.
+  decode <n> <model-file> [--mask FILE] [--toroidal] [--piece queen|rook|amazon]
+      Read a SAT solver's model for `cnf <n>` and print the solution. With
+      the mask the CNF was written for, its blocked and fixed squares are
+      checked too.
+  validate <n> <file> [--mask FILE] [--toroidal] [--piece NAME]
+      Check one placement per line of <file> and list what is wrong with
+      each: chess notation (a1, e5, ...), board rows separated by / (Q or .),
+      or the rank of the piece in each file counting from 1 (1 5 8 6 ...).
+      NAME is queen, rook, bishop, king, knight or amazon.
+  render <n> [placement] --out FILE [--mask FILE] [--toroidal] [--piece NAME]
+      [--theme NAME] [--cell PX] [--no-labels]
+      Draw a placement, written as for validate, or else the first solution
+      of the default search. FILE ending in .svg gives SVG, otherwise PNG.
+      Themes are those of the GUI, e.g. Paper; cells are 64 px by default.
+  gallery <n> --out FILE [--columns K] [--unique] [--mask FILE] [--toroidal]
+      [--piece NAME] [--theme NAME] [--cell PX]
+      Find every solution and lay them out K to a row (8 by default) with
+      their notation, as for the GUI's history panel; --unique keeps one per
+      symmetry class. FILE ending in .svg or .pdf picks the format, otherwise
+      PNG. Cells are 24 px by default.
+  latex <n> [placement] [--all] [--unique] [--chessboard] [--threats]
+      [--caption TEXT] [--mask FILE] [--toroidal] [--piece NAME] [--out FILE]
+      Write a placement (as for render), or with --all every solution, as a
+      LaTeX figure drawn with TikZ or the chessboard package. --threats
+      shades attacked squares. Output goes to stdout by default.
+  animate <n> --out FILE [--from STEP] [--frames K] [--delay MS] [--mask FILE]
+      [--toroidal] [--piece NAME] [--theme NAME] [--cell PX] [--no-labels]
+      Record the default search, one frame per step from STEP (0, the empty
+      board, by default) for K frames (200, at most 2000), MS apart (100).
+      FILE ending in .gif gives a GIF, otherwise an animated PNG. Cells are
+      32 px by default.
+  trace <n> [--steps K] [--mask FILE] [--toroidal] [--piece NAME] [--out FILE]
+      Record every event of the default search (try, reject, place,
+      backtrack, solution) until it finishes or after K steps, as a trace
+      the GUI can load and replay. Output goes to stdout by default.
+  store <n> --out FILE [--unique] [--limit K] [--toroidal] [--piece queen|rook]
+      Enumerate the solutions with the bitboard search and write each to a
+      packed store as it is found, in ceil(n log2 n) bits. --unique keeps
+      the first of each symmetry class; --limit stops after K solutions.
+  convert <store> --out FILE [--from I] [--count K]
+      Convert solutions I (1 by default) to I+K-1 of a packed store to the
+      CSV, JSON or NDJSON export, picked by the extension of FILE.
+  sqlite <n> --out FILE [--unique] [--append] [--mask FILE] [--toroidal]
+      [--piece NAME]
+      Find every solution with the default search and write them, their
+      symmetry classes and orbits and the run to a SQLite database. FILE is
+      replaced unless --append adds the run to it.
+  help
+      Show this message.";
+
+pub fn run(mut args: Vec<String>) -> Result<(), String> {
+    let command = args.remove(0);
+    match command.as_str() {
+        "cnf" => write_cnf(args),
+        "decode" => decode(args),
+        "validate" => validate(args),
+        "render" => render(args),
+        "gallery" => gallery(args),
+        "latex" => write_latex(args),
+        "animate" => animate(args),
+        "trace" => write_trace(args),
+        "store" => write_store(args),
+        "convert" => convert(args),
+        "sqlite" => write_database(args),
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(format!("unknown command \"{}\"\n\n{}", other, USAGE)),
+    }
+}
+
+/// Remove `--name value` from `args` and return the value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let Some(i) = args.iter().position(|a| a == name) else {
+        return Ok(None);
+    };
+    if i + 1 >= args.len() {
+        return Err(format!("{} needs a value", name));
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(value))
+}
+
+/// Remove `--name` from `args`, returning whether it was there.
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != name);
+    args.len() != before
+}
+
+fn topology(args: &mut Vec<String>) -> Topology {
+    if take_flag(args, "--toroidal") {
+        Topology::Toroidal
+    } else {
+        Topology::Standard
+    }
+}
+
+/// The `--piece` option; only pieces the CNF encoding can express.
+fn piece(args: &mut Vec<String>) -> Result<Piece, String> {
+    any_piece(args).and_then(|piece| {
+        Algorithm::SatCdcl
+            .supports(&piece)
+            .then_some(piece)
+            .ok_or_else(|| "unsupported piece, use queen, rook or amazon".to_owned())
+    })
+}
+
+/// The `--piece` option, any of the built-in pieces.
+fn any_piece(args: &mut Vec<String>) -> Result<Piece, String> {
+    let Some(key) = take_option(args, "--piece")? else {
+        return Ok(Piece::Queen);
+    };
+    Piece::PRESETS
+        .into_iter()
+        .find(|p| p.key() == key)
+        .ok_or_else(|| format!("unknown piece \"{}\"", key))
+}
+
+fn parse_n(arg: Option<&String>) -> Result<usize, String> {
+    let arg = arg.ok_or("missing board size <n>")?;
+    match arg.parse::<usize>() {
+        Ok(n) if solver::BOARD_SIZES.contains(&n) => Ok(n),
+        _ => Err(format!("board size must be 4-30, got \"{}\"", arg)),
+    }
+}
+
+/// Print to stdout, or write to the `--out` file when one was given.
+fn output(out: Option<String>, content: &str) -> Result<(), String> {
+    match out {
+        Some(path) => {
+            std::fs::write(&path, content).map_err(|e| format!("could not write {}: {}", path, e))
+        }
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+fn write_cnf(mut args: Vec<String>) -> Result<(), String> {
+    let encoding = match take_option(&mut args, "--encoding")? {
+        Some(key) => {
+            Encoding::from_key(&key).ok_or_else(|| format!("unknown encoding \"{}\"", key))?
+        }
+        None => Encoding::Pairwise,
+    };
+    let topology = topology(&mut args);
+    let piece = piece(&mut args)?;
+    let mask = take_option(&mut args, "--mask")?;
+    let out = take_option(&mut args, "--out")?;
+    let (n, constraints) = match mask {
+        Some(path) => {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("could not read {}: {}", path, e))?;
+            let (n, constraints) =
+                Constraints::from_mask(&text).map_err(|e| format!("{}: {}", path, e))?;
+            if !args.is_empty() && parse_n(args.first())? != n {
+                return Err(format!("{} is a {}x{} mask", path, n, constraints.cols(n)));
+            }
+            (n, constraints)
+        }
+        None => (parse_n(args.first())?, Constraints::default()),
+    };
+    let constraints = Constraints {
+        topology,
+        piece,
+        ..constraints
+    };
+    if !constraints.fills_columns(n) {
+        return Err("the CNF encoding needs a square board".to_owned());
+    }
+    output(out, &cnf::encode(n, encoding, &constraints).to_dimacs())
+}
+
+fn decode(mut args: Vec<String>) -> Result<(), String> {
+    let (n, constraints) = board_options(&mut args)?;
+    if !Algorithm::SatCdcl.supports(&constraints.piece) {
+        return Err("unsupported piece, use queen, rook or amazon".to_owned());
+    }
+    if !constraints.fills_columns(n) {
+        return Err("the CNF encoding needs a square board".to_owned());
+    }
+    let path = args.get(1).ok_or("missing <model-file>")?;
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    let board = cnf::decode_model(n, &text, &constraints)?;
+    println!("{}", solver::notation(&solver::squares(&board)));
+    Ok(())
+}
+
+/// Board size and rules from `<n>`, `--mask`, `--toroidal` and `--piece`.
+fn board_options(args: &mut Vec<String>) -> Result<(usize, Constraints), String> {
+    let topology = topology(args);
+    let piece = any_piece(args)?;
+    let mask = take_option(args, "--mask")?;
+    let n = parse_n(args.first())?;
+    let (mask_n, constraints) = match mask {
+        Some(path) => {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("could not read {}: {}", path, e))?;
+            Constraints::from_mask(&text).map_err(|e| format!("{}: {}", path, e))?
+        }
+        None => (n, Constraints::default()),
+    };
+    if mask_n != n {
+        return Err(format!("the mask is for a board with {} rows", mask_n));
+    }
+    let constraints = Constraints {
+        topology,
+        piece,
+        ..constraints
+    };
+    Ok((n, constraints))
+}
+
+/// Grade a file of placements, one per line; fails if any is wrong.
+fn validate(mut args: Vec<String>) -> Result<(), String> {
+    let (n, constraints) = board_options(&mut args)?;
+    let path = args.get(1).ok_or("missing <file>")?;
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+
+    let (mut total, mut invalid) = (0, 0);
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        total += 1;
+        let problems = match Placement::parse(line) {
+            Ok(placement) => validate::validate(n, &constraints, &placement)
+                .problems
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            Err(e) => vec![e],
+        };
+        if problems.is_empty() {
+            println!("line {}: ok", i + 1);
+        } else {
+            invalid += 1;
+            println!("line {}: invalid", i + 1);
+            for problem in problems {
+                println!("  {}", problem);
+            }
+        }
+    }
+    println!("{} of {} valid", total - invalid, total);
+    if invalid > 0 {
+        return Err(format!("{} placement(s) are invalid", invalid));
+    }
+    Ok(())
+}
+
+/// `--theme NAME`, the default theme without it.
+fn theme_option(args: &mut Vec<String>) -> Result<Theme, String> {
+    match take_option(args, "--theme")? {
+        Some(name) => Theme::presets()
+            .into_iter()
+            .find(|t| t.name.eq_ignore_ascii_case(&name))
+            .ok_or_else(|| format!("unknown theme \"{}\"", name)),
+        None => Ok(Theme::default()),
+    }
+}
+
+/// `--cell PX`, or `default` pixels.
+fn cell_option(args: &mut Vec<String>, default: f32) -> Result<f32, String> {
+    match take_option(args, "--cell")? {
+        Some(px) => px
+            .parse::<f32>()
+            .ok()
+            .filter(|px| (8.0..=512.0).contains(px))
+            .ok_or_else(|| format!("cell size must be 8-512 px, got \"{}\"", px)),
+        None => Ok(default),
+    }
+}
+
+fn render(mut args: Vec<String>) -> Result<(), String> {
+    let theme = theme_option(&mut args)?;
+    let cell = cell_option(&mut args, 64.0)?;
+    let labels = !take_flag(&mut args, "--no-labels");
+    let out = take_option(&mut args, "--out")?.ok_or("missing --out FILE")?;
+    let (n, constraints) = board_options(&mut args)?;
+
+    let board = match args.get(1) {
+        Some(text) => Placement::parse(text)?.to_board(n, &constraints),
+        None => {
+            let mut search = SolverConfig::default().build(n, &constraints);
+            while !search.is_finished() && !search.step() {}
+            search
+                .solutions()
+                .last_board
+                .clone()
+                .ok_or("the board has no solution")?
+        }
+    };
+    let view = render::BoardView {
+        theme: &theme,
+        piece: &constraints.piece,
+        fixed: &constraints.fixed,
+        blocked: &constraints.blocked,
+        labels,
+    };
+    let scene = render::Scene::single(&board, &view, cell);
+    let written = if out.ends_with(".svg") {
+        std::fs::write(&out, scene.to_svg())
+    } else {
+        std::fs::write(&out, scene.to_png()?)
+    };
+    written.map_err(|e| format!("could not write {}: {}", out, e))
+}
+
+fn gallery(mut args: Vec<String>) -> Result<(), String> {
+    let theme = theme_option(&mut args)?;
+    let cell = cell_option(&mut args, 24.0)?;
+    let columns = match take_option(&mut args, "--columns")? {
+        Some(k) => k
+            .parse::<usize>()
+            .ok()
+            .filter(|k| (1..=100).contains(k))
+            .ok_or_else(|| format!("columns must be 1-100, got \"{}\"", k))?,
+        None => 8,
+    };
+    let only_unique = take_flag(&mut args, "--unique");
+    let out = take_option(&mut args, "--out")?.ok_or("missing --out FILE")?;
+    let (n, constraints) = board_options(&mut args)?;
+
+    let mut search = SolverConfig::default().build(n, &constraints);
+    while !search.is_finished() {
+        search.step();
+    }
+    if search.solutions().solutions.is_empty() {
+        return Err("the board has no solution".to_owned());
+    }
+    let scene = render::contact_sheet(
+        n,
+        &constraints,
+        search.solutions(),
+        only_unique,
+        &theme,
+        cell,
+        columns,
+    );
+    let bytes = if out.ends_with(".svg") {
+        scene.to_svg().into_bytes()
+    } else if out.ends_with(".pdf") {
+        scene.to_pdf()?
+    } else {
+        scene.to_png()?
+    };
+    std::fs::write(&out, bytes).map_err(|e| format!("could not write {}: {}", out, e))
+}
+
+fn write_latex(mut args: Vec<String>) -> Result<(), String> {
+    let style = if take_flag(&mut args, "--chessboard") {
+        latex::Style::Chessboard
+    } else {
+        latex::Style::Tikz
+    };
+    let threats = take_flag(&mut args, "--threats");
+    let all = take_flag(&mut args, "--all");
+    let only_unique = take_flag(&mut args, "--unique");
+    let caption = take_option(&mut args, "--caption")?.unwrap_or_default();
+    let out = take_option(&mut args, "--out")?;
+    let (n, constraints) = board_options(&mut args)?;
+
+    let boards = match args.get(1) {
+        Some(text) if !all => {
+            let board = Placement::parse(text)?.to_board(n, &constraints);
+            vec![(latex::notation(&board), board)]
+        }
+        _ => {
+            let mut search = SolverConfig::default().build(n, &constraints);
+            while !search.is_finished() && (!search.step() || all) {}
+            let log = search.solutions();
+            if log.solutions.is_empty() {
+                return Err("the board has no solution".to_owned());
+            }
+            let mut boards = log.captioned(only_unique, (n, constraints.cols(n)));
+            if !all {
+                boards.truncate(1);
+            }
+            boards
+        }
+    };
+    let options = latex::Options {
+        style,
+        threats,
+        caption: &caption,
+    };
+    output(out, &latex::figure(&constraints, &boards, &options)?)
+}
+
+fn animate(mut args: Vec<String>) -> Result<(), String> {
+    let theme = theme_option(&mut args)?;
+    let cell = cell_option(&mut args, 32.0)?;
+    let labels = !take_flag(&mut args, "--no-labels");
+    let from = match take_option(&mut args, "--from")? {
+        Some(step) => step
+            .parse::<u64>()
+            .map_err(|_| format!("--from needs a step number, got \"{}\"", step))?,
+        None => 0,
+    };
+    let frames = match take_option(&mut args, "--frames")? {
+        Some(k) => k
+            .parse::<usize>()
+            .ok()
+            .filter(|k| (1..=animation::MAX_FRAMES).contains(k))
+            .ok_or_else(|| format!("frames must be 1-{}, got \"{}\"", animation::MAX_FRAMES, k))?,
+        None => 200,
+    };
+    let delay = match take_option(&mut args, "--delay")? {
+        Some(ms) => ms
+            .parse::<u32>()
+            .ok()
+            .filter(|ms| (10..=60_000).contains(ms))
+            .ok_or_else(|| format!("delay must be 10-60000 ms, got \"{}\"", ms))?,
+        None => 100,
+    };
+    let out = take_option(&mut args, "--out")?.ok_or("missing --out FILE")?;
+    let (n, constraints) = board_options(&mut args)?;
+
+    let mut search = SolverConfig::default().build(n, &constraints);
+    let boards = animation::record(search.as_mut(), from, frames);
+    let view = render::BoardView {
+        theme: &theme,
+        piece: &constraints.piece,
+        fixed: &constraints.fixed,
+        blocked: &constraints.blocked,
+        labels,
+    };
+    let bytes = if out.ends_with(".gif") {
+        animation::to_gif(&boards, &view, cell, delay)?
+    } else {
+        animation::to_apng(&boards, &view, cell, delay)?
+    };
+    std::fs::write(&out, bytes).map_err(|e| format!("could not write {}: {}", out, e))
+}
+
+fn write_trace(mut args: Vec<String>) -> Result<(), String> {
+    let steps = match take_option(&mut args, "--steps")? {
+        Some(k) => Some(
+            k.parse::<u64>()
+                .map_err(|_| format!("--steps needs a number, got \"{}\"", k))?,
+        ),
+        None => None,
+    };
+    let out = take_option(&mut args, "--out")?;
+    let (n, constraints) = board_options(&mut args)?;
+
+    let config = SolverConfig::default();
+    let mut recorder = TraceRecorder::new(config.build(n, &constraints));
+    while !recorder.is_finished() && steps.is_none_or(|k| recorder.trace().steps < k) {
+        recorder.step();
+        if recorder.is_truncated() {
+            eprintln!("stopping after {} events", solver::MAX_EVENTS);
+            break;
+        }
+    }
+    output(
+        out,
+        &trace::to_text(n, &constraints, &config, recorder.trace()),
+    )
+}
+
+fn write_store(mut args: Vec<String>) -> Result<(), String> {
+    let only_unique = take_flag(&mut args, "--unique");
+    let limit = match take_option(&mut args, "--limit")? {
+        Some(k) => Some(
+            k.parse::<u64>()
+                .map_err(|_| format!("--limit needs a number, got \"{}\"", k))?,
+        ),
+        None => None,
+    };
+    let out = take_option(&mut args, "--out")?.ok_or("missing --out FILE")?;
+    let topology = topology(&mut args);
+    let piece = any_piece(&mut args)?;
+    if !Algorithm::Bitboard.supports(&piece) {
+        return Err("store enumerates queens or rooks".to_owned());
+    }
+    let n = parse_n(args.first())?;
+    let constraints = Constraints {
+        topology,
+        piece,
+        ..Constraints::default()
+    };
+
+    let mut flags = 0;
+    if only_unique {
+        flags |= store::UNIQUE;
+    }
+    if topology == Topology::Toroidal {
+        flags |= store::TOROIDAL;
+    }
+    let file =
+        std::fs::File::create(&out).map_err(|e| format!("could not write {}: {}", out, e))?;
+    let piece = store::storable(n, &constraints)?;
+    let mut writer = StoreWriter::new(std::io::BufWriter::new(file), n, piece, flags)?;
+    let mut search = BitboardSolver::new(n, &constraints).without_log();
+    while !search.is_finished() && limit.is_none_or(|k| writer.count() < k) {
+        if search.step()
+            && (!only_unique || store::is_first_of_class(search.solution_rows(), topology))
+        {
+            writer.push(search.solution_rows())?;
+        }
+    }
+    let count = writer.count();
+    writer.finish(search.is_finished())?;
+    eprintln!(
+        "{} solution(s), {} bits each",
+        count,
+        store::bits_per_solution(n)
+    );
+    Ok(())
+}
+
+fn convert(mut args: Vec<String>) -> Result<(), String> {
+    let number = |value: Option<String>, name: &str| -> Result<Option<u64>, String> {
+        value
+            .map(|v| {
+                v.parse::<u64>()
+                    .map_err(|_| format!("{} needs a number, got \"{}\"", name, v))
+            })
+            .transpose()
+    };
+    let from = number(take_option(&mut args, "--from")?, "--from")?.unwrap_or(1);
+    let count = number(take_option(&mut args, "--count")?, "--count")?;
+    let out = take_option(&mut args, "--out")?.ok_or("missing --out FILE")?;
+    let path = args.first().ok_or("missing the store file")?;
+    let file = std::fs::File::open(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    let mut store = StoreReader::new(std::io::BufReader::new(file))?;
+    let header = store.header;
+    let first = from.max(1) - 1;
+    let end = count.map_or(store.len(), |k| (first + k).min(store.len()));
+    let topology = header.topology();
+
+    if out.ends_with(".csv") {
+        let mut writer =
+            csv::Writer::from_path(&out).map_err(|e| format!("could not write {}: {}", out, e))?;
+        let error = |e: csv::Error| format!("could not write {}: {}", out, e);
+        writer
+            .write_record(["Solution #", "Configuration"])
+            .map_err(error)?;
+        for i in first..end {
+            let rows = store.get(i)?;
+            let squares: Vec<(usize, usize)> =
+                rows.iter().enumerate().map(|(c, &r)| (r, c)).collect();
+            let mut shown = solver::notation(&squares);
+            if !store::is_first_of_class(&rows, topology) {
+                shown = format!("(Sym) {}", shown);
+            }
+            writer
+                .write_record([(i + 1).to_string(), shown])
+                .map_err(error)?;
+        }
+        return writer
+            .flush()
+            .map_err(|e| format!("could not write {}: {}", out, e));
+    }
+
+    let run = export::RunInfo::stored(header.n, &header.constraints());
+    let records = (first..end)
+        .map(|i| {
+            let rows = store.get(i)?;
+            let unique = store::is_first_of_class(&rows, topology);
+            Ok(export::stored_record(
+                i as usize + 1,
+                topology,
+                &rows,
+                unique,
+            ))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let text = if out.ends_with(".ndjson") {
+        export::to_ndjson(&run, &records)?
+    } else if out.ends_with(".json") {
+        export::to_json(&run, &records)?
+    } else {
+        return Err(format!("{}: pick a .csv, .json or .ndjson file", out));
+    };
+    output(Some(out), &text)
+}
+
+fn write_database(mut args: Vec<String>) -> Result<(), String> {
+    let only_unique = take_flag(&mut args, "--unique");
+    let append = take_flag(&mut args, "--append");
+    let out = take_option(&mut args, "--out")?.ok_or("missing --out FILE")?;
+    let (n, constraints) = board_options(&mut args)?;
+
+    let config = SolverConfig::default();
+    let mut search = config.build(n, &constraints);
+    while !search.is_finished() {
+        search.step();
+    }
+    let run = export::RunInfo::new(n, &constraints, search.as_ref());
+    let records = export::records(n, &constraints, search.solutions(), only_unique);
+    let path = std::path::Path::new(&out);
+    if !append {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("could not replace {}: {}", out, e))
+            }
+            _ => {}
+        }
+    }
+    database::write(path, &run, &records, constraints.topology)?;
+    eprintln!("{} solution(s)", records.len());
+    Ok(())
+}
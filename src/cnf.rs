@@ -0,0 +1,366 @@
+//! N-Queens as a SAT instance: DIMACS CNF export and model decoding.
+//!
+//! Variable `row * n + col + 1` is true when a queen stands on (row, col).
+//! Rows and columns get exactly one queen, every diagonal at most one.
+//! On the torus the diagonals wrap around, so there are n of each kind.
+//! Rooks drop the diagonals; the knight moves of an amazon become one
+//! binary clause per attacking pair.
+
+use crate::solver::{self, Board, Constraints, Piece, Topology};
+
+/// How an "at most one of these literals" constraint is written as clauses.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    /// One binary clause per pair, no auxiliary variables.
+    Pairwise,
+    /// Sinz's sequential counter, linear clauses and k-1 auxiliaries.
+    Sequential,
+    /// Each literal forces a distinct bit pattern on log2(k) auxiliaries.
+    Binary,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [Encoding::Pairwise, Encoding::Sequential, Encoding::Binary];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Encoding::Pairwise => "Pairwise",
+            Encoding::Sequential => "Sequential counter",
+            Encoding::Binary => "Binary",
+        }
+    }
+
+    /// Short name used on the command line and in the DIMACS header.
+    pub fn key(self) -> &'static str {
+        match self {
+            Encoding::Pairwise => "pairwise",
+            Encoding::Sequential => "sequential",
+            Encoding::Binary => "binary",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.key() == key)
+    }
+}
+
+pub struct Cnf {
+    pub n: usize,
+    pub encoding: Encoding,
+    pub topology: Topology,
+    pub piece: Piece,
+    pub num_vars: usize,
+    pub clauses: Vec<Vec<i32>>,
+}
+
+pub fn var(n: usize, row: usize, col: usize) -> i32 {
+    (row * n + col + 1) as i32
+}
+
+impl Cnf {
+    fn new(n: usize, encoding: Encoding, topology: Topology, piece: Piece) -> Self {
+        Self {
+            n,
+            encoding,
+            topology,
+            piece,
+            num_vars: n * n,
+            clauses: Vec::new(),
+        }
+    }
+
+    fn fresh(&mut self) -> i32 {
+        self.num_vars += 1;
+        self.num_vars as i32
+    }
+
+    fn at_most_one(&mut self, lits: &[i32]) {
+        let k = lits.len();
+        if k < 2 {
+            return;
+        }
+        match self.encoding {
+            Encoding::Pairwise => {
+                for i in 0..k {
+                    for j in i + 1..k {
+                        self.clauses.push(vec![-lits[i], -lits[j]]);
+                    }
+                }
+            }
+            Encoding::Sequential => {
+                // s[i] is true once one of lits[0..=i] is true
+                let s: Vec<i32> = (0..k - 1).map(|_| self.fresh()).collect();
+                self.clauses.push(vec![-lits[0], s[0]]);
+                for i in 1..k - 1 {
+                    self.clauses.push(vec![-lits[i], s[i]]);
+                    self.clauses.push(vec![-s[i - 1], s[i]]);
+                    self.clauses.push(vec![-lits[i], -s[i - 1]]);
+                }
+                self.clauses.push(vec![-lits[k - 1], -s[k - 2]]);
+            }
+            Encoding::Binary => {
+                let bits = usize::BITS - (k - 1).leading_zeros();
+                let b: Vec<i32> = (0..bits).map(|_| self.fresh()).collect();
+                for (i, &lit) in lits.iter().enumerate() {
+                    for (j, &bit) in b.iter().enumerate() {
+                        let sign = if (i >> j) & 1 == 1 { 1 } else { -1 };
+                        self.clauses.push(vec![-lit, sign * bit]);
+                    }
+                }
+            }
+        }
+    }
+
+    fn exactly_one(&mut self, lits: &[i32]) {
+        self.clauses.push(lits.to_vec());
+        self.at_most_one(lits);
+    }
+
+    /// Pin fixed queens and forbid blocked squares with unit clauses.
+    fn constrain(&mut self, constraints: &Constraints) {
+        for &(row, col) in &constraints.fixed {
+            self.clauses.push(vec![var(self.n, row, col)]);
+        }
+        for &(row, col) in &constraints.blocked {
+            self.clauses.push(vec![-var(self.n, row, col)]);
+        }
+    }
+
+    pub fn to_dimacs(&self) -> String {
+        let topology = match self.topology {
+            Topology::Standard => "",
+            Topology::Toroidal => " topology=toroidal",
+        };
+        let piece = match self.piece {
+            Piece::Queen => String::new(),
+            _ => format!(" piece={}", self.piece.key()),
+        };
+        let mut out = format!(
+            "c N-Queens n={} encoding={}{}{}\nc var row*n+col+1 is a queen on (row, col)\np cnf {} {}\n",
+            self.n,
+            self.encoding.key(),
+            topology,
+            piece,
+            self.num_vars,
+            self.clauses.len()
+        );
+        for clause in &self.clauses {
+            for lit in clause {
+                out.push_str(&lit.to_string());
+                out.push(' ');
+            }
+            out.push_str("0\n");
+        }
+        out
+    }
+}
+
+/// The board under `constraints`: their topology decides the diagonals,
+/// fixed queens and blocked squares become unit clauses. The piece has to
+/// attack along ranks and files (see `Algorithm::supports`).
+pub fn encode(n: usize, encoding: Encoding, constraints: &Constraints) -> Cnf {
+    let mut cnf = Cnf::new(n, encoding, constraints.topology, constraints.piece.clone());
+    for i in 0..n {
+        let row: Vec<i32> = (0..n).map(|c| var(n, i, c)).collect();
+        let col: Vec<i32> = (0..n).map(|r| var(n, r, i)).collect();
+        cnf.exactly_one(&row);
+        cnf.exactly_one(&col);
+    }
+    if constraints.piece.moves_like_bishop() {
+        diagonals(&mut cnf, constraints.topology);
+    }
+    leaps(&mut cnf, constraints);
+    cnf.constrain(constraints);
+    cnf
+}
+
+/// At most one queen per diagonal.
+fn diagonals(cnf: &mut Cnf, topology: Topology) {
+    let n = cnf.n;
+    if topology == Topology::Toroidal {
+        // Wrapped diagonals by (col - row) mod n and (row + col) mod n
+        for d in 0..n {
+            let down: Vec<i32> = (0..n).map(|r| var(n, r, (r + d) % n)).collect();
+            let up: Vec<i32> = (0..n).map(|r| var(n, r, (d + n - r) % n)).collect();
+            cnf.at_most_one(&down);
+            cnf.at_most_one(&up);
+        }
+        return;
+    }
+    // Diagonals by row - col (down) and row + col (up)
+    for d in 0..2 * n - 1 {
+        let down: Vec<i32> = (0..n)
+            .filter_map(|r| Some((r, (r + n - 1).checked_sub(d)?)))
+            .filter(|&(_, c)| c < n)
+            .map(|(r, c)| var(n, r, c))
+            .collect();
+        let up: Vec<i32> = (0..n)
+            .filter_map(|r| Some((r, d.checked_sub(r)?)))
+            .filter(|&(_, c)| c < n)
+            .map(|(r, c)| var(n, r, c))
+            .collect();
+        cnf.at_most_one(&down);
+        cnf.at_most_one(&up);
+    }
+}
+
+/// Forbid every attacking pair the rank, file and diagonal constraints
+/// don't already cover.
+fn leaps(cnf: &mut Cnf, constraints: &Constraints) {
+    let n = cnf.n;
+    let squares: Vec<(usize, usize)> = (0..n).flat_map(|r| (0..n).map(move |c| (r, c))).collect();
+    let diagonal = |a, b| {
+        constraints.piece.moves_like_bishop()
+            && Piece::Bishop.attacks((n, n), constraints.topology, a, b)
+    };
+    for (i, &a) in squares.iter().enumerate() {
+        for &b in &squares[i + 1..] {
+            if a.0 != b.0 && a.1 != b.1 && !diagonal(a, b) && constraints.attacks(n, a, b) {
+                cnf.clauses.push(vec![-var(n, a.0, a.1), -var(n, b.0, b.1)]);
+            }
+        }
+    }
+}
+
+/// Read a model as printed by common SAT solvers ("s SATISFIABLE" / "v ..." lines,
+/// or MiniSat's "SAT" followed by the literals) and check it is a solution
+/// that keeps the fixed queens and blocked squares of `constraints`.
+pub fn decode_model(n: usize, text: &str, constraints: &Constraints) -> Result<Board, String> {
+    let mut board = vec![vec![0; n]; n];
+    for line in text.lines() {
+        let line = line.trim();
+        if line == "UNSAT" || line.starts_with("s UNSAT") {
+            return Err("the solver reported the instance unsatisfiable".to_owned());
+        }
+        if line.is_empty() || line == "SAT" || line.starts_with('c') || line.starts_with('s') {
+            continue;
+        }
+        for token in line.trim_start_matches('v').split_whitespace() {
+            let lit: i64 = token
+                .parse()
+                .map_err(|_| format!("unexpected token \"{}\" in model", token))?;
+            // Auxiliary variables of the encoding come after the board
+            if lit > 0 && (lit as usize) <= n * n {
+                let v = lit as usize - 1;
+                board[v / n][v % n] = 1;
+            }
+        }
+    }
+
+    for col in 0..n {
+        let count = board.iter().filter(|cells| cells[col] == 1).count();
+        if count != 1 {
+            return Err(format!(
+                "column {} holds {} queens, expected 1",
+                (b'a' + col as u8) as char,
+                count
+            ));
+        }
+    }
+    let square = |(row, col): (usize, usize)| solver::notation(&[(row, col)]);
+    let cell = |(row, col): (usize, usize)| board.get(row).and_then(|cells| cells.get(col));
+    if let Some(&sq) = constraints.blocked.iter().find(|&&sq| cell(sq) == Some(&1)) {
+        return Err(format!(
+            "queen on {} stands on a blocked square",
+            square(sq)
+        ));
+    }
+    if let Some(&sq) = constraints.fixed.iter().find(|&&sq| cell(sq) != Some(&1)) {
+        return Err(format!("the fixed queen on {} is missing", square(sq)));
+    }
+    for (row, cells) in board.iter().enumerate() {
+        for (col, &cell) in cells.iter().enumerate() {
+            if cell == 1 && solver::is_threatened(&board, row, col, constraints) {
+                return Err(format!("queen on {} is attacked", square((row, col))));
+            }
+        }
+    }
+    Ok(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sat::{Cdcl, Learning, Outcome};
+
+    /// Clauses read back from `to_dimacs`, checking the header counts.
+    fn parse_dimacs(text: &str) -> (usize, Vec<Vec<i32>>) {
+        let mut header = None;
+        let mut clauses = Vec::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("p cnf ") {
+                let counts: Vec<usize> = rest
+                    .split_whitespace()
+                    .map(|t| t.parse().unwrap())
+                    .collect();
+                header = Some((counts[0], counts[1]));
+            } else if !line.starts_with('c') {
+                let lits: Vec<i32> = line
+                    .split_whitespace()
+                    .map(|t| t.parse().unwrap())
+                    .collect();
+                assert_eq!(lits.last(), Some(&0));
+                clauses.push(lits[..lits.len() - 1].to_vec());
+            }
+        }
+        let (vars, count) = header.expect("no p line");
+        assert_eq!(count, clauses.len());
+        (vars, clauses)
+    }
+
+    #[test]
+    fn written_cnf_solves_to_a_decodable_model() {
+        let constraints = Constraints::default();
+        for encoding in Encoding::ALL {
+            for n in [1, 4, 5, 8] {
+                let text = encode(n, encoding, &constraints).to_dimacs();
+                let (vars, clauses) = parse_dimacs(&text);
+                let mut sat = Cdcl::new(vars, &clauses, Learning::FirstUip, n * n);
+                while sat.step() != Outcome::Sat {}
+                let literals: Vec<String> = (1..=vars)
+                    .map(|v| {
+                        let lit = v as i32;
+                        if sat.value(v) == Some(true) {
+                            lit
+                        } else {
+                            -lit
+                        }
+                        .to_string()
+                    })
+                    .collect();
+                let model = format!("s SATISFIABLE\nv {} 0\n", literals.join(" "));
+                let board = decode_model(n, &model, &constraints).unwrap();
+                for (row, cells) in board.iter().enumerate() {
+                    for (col, &cell) in cells.iter().enumerate() {
+                        assert_eq!(
+                            cell == 1,
+                            sat.value(var(n, row, col) as usize) == Some(true)
+                        );
+                    }
+                }
+                assert_eq!(board.concat().iter().filter(|&&c| c == 1).count(), n);
+            }
+        }
+    }
+
+    #[test]
+    fn decoding_rejects_attacks_and_unsat() {
+        let constraints = Constraints::default();
+        // One queen per column, all on the long diagonal
+        let model = "SAT\n1 6 11 16 0\n";
+        let error = decode_model(4, model, &constraints).unwrap_err();
+        assert_eq!(error, "queen on a1 is attacked");
+        let error = decode_model(4, "SAT\n1 6 11 0\n", &constraints).unwrap_err();
+        assert!(error.contains("column d"), "{}", error);
+        // 2 4 1 3 is a solution, but the mask blocks b4 and fixes a3
+        let model = "SAT\n5 14 3 12 0\n";
+        assert!(decode_model(4, model, &constraints).is_ok());
+        let (_, masked) = Constraints::from_mask("....\n....\n....\n.#..").unwrap();
+        let error = decode_model(4, model, &masked).unwrap_err();
+        assert!(error.contains("blocked"), "{}", error);
+        let (_, masked) = Constraints::from_mask("....\n....\nQ...\n....").unwrap();
+        let error = decode_model(4, model, &masked).unwrap_err();
+        assert!(error.contains("a3"), "{}", error);
+        assert!(decode_model(4, "s UNSATISFIABLE\n", &constraints).is_err());
+    }
+}
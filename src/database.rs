@@ -0,0 +1,317 @@
+//! Solutions as a SQLite database for ad hoc queries (desktop only). Each
+//! export adds one run; the export JSON's fields spread over these tables:
+//!
+//! ```sql
+//! runs(id, n, cols, piece, topology, goal, algorithm, timestamp, steps,
+//!      placements, backtracks, nodes_to_first, mask)
+//! run_counters(run_id, name, value)  -- the solver's extra counters
+//! classes(id, run_id, canonical, size)
+//!     -- the symmetry classes the run found; size counts their boards
+//! orbits(class_id, notation)         -- every board of each class
+//! solutions(id, run_id, idx, class_id, notation, is_unique, permutation,
+//!           half_turn, quarter_turn, mirror)
+//! pieces(solution_id, row, col, square, colour)
+//! ```
+//!
+//! `idx` numbers the solutions as the history panel does. Rows and columns
+//! count from 0 at the top left, `square` is the app's notation (a1 is row
+//! 0, column 0) and `colour` is 1 for white, 2 for a black army piece.
+//! `permutation` is the JSON array of the row in each column, when every
+//! column holds one piece. `half_turn`, `quarter_turn` and `mirror` are 1
+//! when the board is its own image under the 180° or 90° rotation about
+//! its centre, or under some reflection. `mask` holds the fixed and blocked
+//! squares as mask rows joined by `/`. For example:
+//!
+//! ```sql
+//! SELECT COUNT(DISTINCT solution_id) FROM pieces WHERE square = 'a1';
+//! SELECT notation FROM solutions WHERE half_turn = 1;
+//! SELECT canonical, size, COUNT(*) FROM classes
+//!     JOIN solutions ON solutions.class_id = classes.id GROUP BY classes.id;
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::export::{self, RunInfo, SolutionRecord};
+use crate::solver::{self, Topology};
+
+/// `PRAGMA user_version` of the schema below.
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    n INTEGER NOT NULL,
+    cols INTEGER NOT NULL,
+    piece TEXT NOT NULL,
+    topology TEXT NOT NULL,
+    goal TEXT NOT NULL,
+    algorithm TEXT,
+    timestamp INTEGER NOT NULL,
+    steps INTEGER NOT NULL,
+    placements INTEGER NOT NULL,
+    backtracks INTEGER NOT NULL,
+    nodes_to_first INTEGER,
+    mask TEXT
+);
+CREATE TABLE IF NOT EXISTS run_counters (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    name TEXT NOT NULL,
+    value INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS classes (
+    id INTEGER PRIMARY KEY,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    canonical TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    UNIQUE (run_id, canonical)
+);
+CREATE TABLE IF NOT EXISTS orbits (
+    class_id INTEGER NOT NULL REFERENCES classes(id),
+    notation TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS solutions (
+    id INTEGER PRIMARY KEY,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    idx INTEGER NOT NULL,
+    class_id INTEGER NOT NULL REFERENCES classes(id),
+    notation TEXT NOT NULL,
+    is_unique INTEGER NOT NULL,
+    permutation TEXT,
+    half_turn INTEGER NOT NULL,
+    quarter_turn INTEGER NOT NULL,
+    mirror INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS pieces (
+    solution_id INTEGER NOT NULL REFERENCES solutions(id),
+    row INTEGER NOT NULL,
+    col INTEGER NOT NULL,
+    square TEXT NOT NULL,
+    colour INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS run_counters_run ON run_counters(run_id);
+CREATE INDEX IF NOT EXISTS orbits_class ON orbits(class_id);
+CREATE INDEX IF NOT EXISTS orbits_notation ON orbits(notation);
+CREATE INDEX IF NOT EXISTS solutions_run ON solutions(run_id, idx);
+CREATE INDEX IF NOT EXISTS solutions_class ON solutions(class_id);
+CREATE INDEX IF NOT EXISTS solutions_notation ON solutions(notation);
+CREATE INDEX IF NOT EXISTS pieces_solution ON pieces(solution_id);
+CREATE INDEX IF NOT EXISTS pieces_square ON pieces(square, colour);
+CREATE INDEX IF NOT EXISTS pieces_row_col ON pieces(row, col);
+";
+
+/// Add a run and its solutions to the database at `path`, creating it if
+/// needed. Everything goes in one transaction, so a failed export leaves
+/// the file as it was.
+pub fn write(
+    path: &Path,
+    run: &RunInfo,
+    records: &[SolutionRecord],
+    topology: Topology,
+) -> Result<(), String> {
+    let error = |e: rusqlite::Error| format!("could not write {}: {}", path.display(), e);
+    let mut conn = Connection::open(path).map_err(error)?;
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(error)?;
+    if version != 0 && version != SCHEMA_VERSION {
+        return Err(format!(
+            "{} has schema version {}, not {}",
+            path.display(),
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+    let tx = conn.transaction().map_err(error)?;
+    tx.execute_batch(SCHEMA).map_err(error)?;
+    tx.pragma_update(None, "user_version", SCHEMA_VERSION)
+        .map_err(error)?;
+
+    tx.execute(
+        "INSERT INTO runs (n, cols, piece, topology, goal, algorithm, timestamp, steps,
+             placements, backtracks, nodes_to_first, mask)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            run.n,
+            run.cols,
+            run.piece,
+            run.topology,
+            run.goal,
+            run.algorithm,
+            run.timestamp,
+            run.steps,
+            run.placements,
+            run.backtracks,
+            run.nodes_to_first,
+            run.mask.as_ref().map(|rows| rows.join("/")),
+        ],
+    )
+    .map_err(error)?;
+    let run_id = tx.last_insert_rowid();
+    {
+        let mut counter = tx
+            .prepare("INSERT INTO run_counters (run_id, name, value) VALUES (?1, ?2, ?3)")
+            .map_err(error)?;
+        for (name, value) in &run.extra {
+            counter
+                .execute(params![run_id, name, value])
+                .map_err(error)?;
+        }
+
+        let mut class = tx
+            .prepare("INSERT INTO classes (run_id, canonical, size) VALUES (?1, ?2, ?3)")
+            .map_err(error)?;
+        let mut orbit = tx
+            .prepare("INSERT INTO orbits (class_id, notation) VALUES (?1, ?2)")
+            .map_err(error)?;
+        let mut solution = tx
+            .prepare(
+                "INSERT INTO solutions (run_id, idx, class_id, notation, is_unique, permutation,
+                     half_turn, quarter_turn, mirror)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )
+            .map_err(error)?;
+        let mut piece = tx
+            .prepare(
+                "INSERT INTO pieces (solution_id, row, col, square, colour)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .map_err(error)?;
+
+        let mut classes: HashMap<String, i64> = HashMap::new();
+        for record in records {
+            let size = (record.n, record.cols);
+            let colours = record
+                .colours
+                .clone()
+                .unwrap_or_else(|| vec![1; record.squares.len()]);
+            let class_id = match classes.get(&record.canonical) {
+                Some(&id) => id,
+                None => {
+                    class
+                        .execute(params![run_id, record.canonical, record.class_size])
+                        .map_err(error)?;
+                    let id = tx.last_insert_rowid();
+                    for notation in export::orbit(size, topology, &record.squares, &colours) {
+                        orbit.execute(params![id, notation]).map_err(error)?;
+                    }
+                    classes.insert(record.canonical.clone(), id);
+                    id
+                }
+            };
+
+            let symmetry = Symmetry::of(size, &record.squares, &colours);
+            let permutation = record.permutation.as_ref().map(|rows| {
+                let rows: Vec<String> = rows.iter().map(usize::to_string).collect();
+                format!("[{}]", rows.join(","))
+            });
+            solution
+                .execute(params![
+                    run_id,
+                    record.index,
+                    class_id,
+                    record.notation,
+                    record.unique,
+                    permutation,
+                    symmetry.half_turn,
+                    symmetry.quarter_turn,
+                    symmetry.mirror,
+                ])
+                .map_err(error)?;
+            let solution_id = tx.last_insert_rowid();
+            for (&(r, c), &colour) in record.squares.iter().zip(&colours) {
+                let square = solver::notation(&[(r, c)]);
+                piece
+                    .execute(params![solution_id, r, c, square, colour])
+                    .map_err(error)?;
+            }
+        }
+    }
+    tx.commit().map_err(error)
+}
+
+/// Which symmetries of the board leave a solution unchanged.
+struct Symmetry {
+    half_turn: bool,
+    quarter_turn: bool,
+    mirror: bool,
+}
+
+impl Symmetry {
+    fn of(size: (usize, usize), squares: &[(usize, usize)], colours: &[u8]) -> Self {
+        let (rows, cols) = size;
+        let mut pieces: Vec<((usize, usize), u8)> = squares
+            .iter()
+            .copied()
+            .zip(colours.iter().copied())
+            .collect();
+        pieces.sort();
+        let fixed_by = |map: &dyn Fn(usize, usize) -> (usize, usize)| {
+            let mut image: Vec<((usize, usize), u8)> = pieces
+                .iter()
+                .map(|&((r, c), colour)| (map(r, c), colour))
+                .collect();
+            image.sort();
+            image == pieces
+        };
+        let square = rows == cols;
+        let n = rows;
+        Self {
+            half_turn: fixed_by(&|r, c| (rows - 1 - r, cols - 1 - c)),
+            quarter_turn: square && fixed_by(&|r, c| (c, n - 1 - r)),
+            mirror: fixed_by(&|r, c| (r, cols - 1 - c))
+                || fixed_by(&|r, c| (rows - 1 - r, c))
+                || (square
+                    && (fixed_by(&|r, c| (c, r)) || fixed_by(&|r, c| (n - 1 - c, n - 1 - r)))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{Constraints, SolverConfig};
+
+    #[test]
+    fn exports_a_run_with_its_classes_and_pieces() {
+        let constraints = Constraints::default();
+        let mut search = SolverConfig::default().build(6, &constraints);
+        while !search.is_finished() {
+            search.step();
+        }
+        let run = RunInfo::new(6, &constraints, search.as_ref());
+        let records = export::records(6, &constraints, search.solutions(), false);
+        let path = std::env::temp_dir().join(format!("nqueens_test_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        write(&path, &run, &records, Topology::Standard).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        // The 4 solutions of 6 queens are one symmetry class
+        assert_eq!(count("runs"), 1);
+        assert_eq!(count("solutions"), 4);
+        assert_eq!(count("classes"), 1);
+        assert_eq!(count("orbits"), 4);
+        assert_eq!(count("pieces"), 24);
+        let (algorithm, topology): (String, String) = conn
+            .query_row("SELECT algorithm, topology FROM runs", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(algorithm, search.name());
+        assert_eq!(topology, "standard");
+        let size: i64 = conn
+            .query_row("SELECT size FROM classes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(size, 4);
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
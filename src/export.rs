@@ -0,0 +1,281 @@
+//! Machine-readable exports of a run: one JSON document, or NDJSON with a
+//! run line followed by one line per solution. Squares are (row, col) from
+//! the top left, the same as on the board. The CSV export keeps only the
+//! notation of each solution, and the mask if there is one.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(target_arch = "wasm32")]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::solver::{
+    self, get_variants, ClassKey, Constraints, SolutionLog, Solver, SolverStats, Topology,
+};
+
+/// How the solutions were found.
+#[derive(Serialize)]
+pub struct RunInfo {
+    pub n: usize,
+    pub cols: usize,
+    pub piece: &'static str,
+    pub topology: &'static str,
+    pub goal: &'static str,
+    /// The search that ran, which may not be the selected algorithm. None
+    /// for solutions converted from a packed store, which keeps no record
+    /// of the search; its counters are then zero.
+    pub algorithm: Option<&'static str>,
+    /// Seconds since the Unix epoch when the file was written.
+    pub timestamp: u64,
+    pub steps: u64,
+    pub placements: u64,
+    pub backtracks: u64,
+    pub nodes_to_first: Option<u64>,
+    pub extra: Vec<(&'static str, u64)>,
+    /// Fixed and blocked squares as the mask rows, if there are any.
+    pub mask: Option<Vec<String>>,
+}
+
+impl RunInfo {
+    pub fn new(n: usize, constraints: &Constraints, solver: &dyn Solver) -> Self {
+        Self {
+            algorithm: Some(solver.name()),
+            ..Self::stats(n, constraints, solver.stats())
+        }
+    }
+
+    /// The board of a packed store.
+    pub fn stored(n: usize, constraints: &Constraints) -> Self {
+        Self::stats(n, constraints, SolverStats::default())
+    }
+
+    fn stats(n: usize, constraints: &Constraints, stats: SolverStats) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            n,
+            cols: constraints.cols(n),
+            piece: constraints.piece.key(),
+            topology: constraints.topology.key(),
+            goal: constraints.goal.label(),
+            algorithm: None,
+            timestamp,
+            steps: stats.steps,
+            placements: stats.placements,
+            backtracks: stats.backtracks,
+            nodes_to_first: stats.nodes_to_first,
+            extra: stats.extra,
+            mask: (!constraints.is_empty()).then(|| constraints.to_mask(n)),
+        }
+    }
+}
+
+/// One solution, numbered as in the history panel.
+#[derive(Serialize)]
+pub struct SolutionRecord {
+    pub index: usize,
+    pub n: usize,
+    pub cols: usize,
+    /// Occupied (row, col) squares, column by column.
+    pub squares: Vec<(usize, usize)>,
+    /// Army of each square (1 white, 2 black), for peaceable armies only.
+    pub colours: Option<Vec<u8>>,
+    /// Row of the piece in each column, when every column holds exactly one.
+    pub permutation: Option<Vec<usize>>,
+    pub notation: String,
+    /// Notation of the smallest symmetric variant, the same for the whole
+    /// symmetry class.
+    pub canonical: String,
+    /// Number of distinct boards in the symmetry class.
+    pub class_size: usize,
+    /// First solution of its class in this run.
+    pub unique: bool,
+}
+
+/// Records of the solutions the history panel shows.
+pub fn records(
+    n: usize,
+    constraints: &Constraints,
+    log: &SolutionLog,
+    only_unique: bool,
+) -> Vec<SolutionRecord> {
+    let size = (n, constraints.cols(n));
+    log.placements
+        .iter()
+        .zip(&log.colours)
+        .zip(&log.solutions)
+        .filter(|(_, shown)| !(only_unique && shown.starts_with("(Sym)")))
+        .enumerate()
+        .map(|(i, ((squares, colours), shown))| {
+            let notation = shown.trim_start_matches("(Sym) ").to_owned();
+            let unique = !shown.starts_with("(Sym)");
+            record(
+                i + 1,
+                size,
+                constraints.topology,
+                squares,
+                colours,
+                notation,
+                unique,
+            )
+        })
+        .collect()
+}
+
+/// Record of a permutation read from a packed store; `unique` as for
+/// `store::is_first_of_class`.
+pub fn stored_record(
+    index: usize,
+    topology: Topology,
+    rows: &[usize],
+    unique: bool,
+) -> SolutionRecord {
+    let n = rows.len();
+    let squares: Vec<(usize, usize)> = rows.iter().enumerate().map(|(c, &r)| (r, c)).collect();
+    let notation = solver::notation(&squares);
+    record(
+        index,
+        (n, n),
+        topology,
+        &squares,
+        &vec![1; n],
+        notation,
+        unique,
+    )
+}
+
+fn record(
+    index: usize,
+    size: (usize, usize),
+    topology: Topology,
+    squares: &[(usize, usize)],
+    colours: &[u8],
+    notation: String,
+    unique: bool,
+) -> SolutionRecord {
+    let peaceable = colours.contains(&2);
+    let distinct = orbit(size, topology, squares, colours);
+    let columns: Vec<usize> = squares.iter().map(|&(_, c)| c).collect();
+    SolutionRecord {
+        index,
+        n: size.0,
+        cols: size.1,
+        squares: squares.to_vec(),
+        colours: peaceable.then(|| colours.to_vec()),
+        permutation: (columns == (0..size.1).collect::<Vec<_>>())
+            .then(|| squares.iter().map(|&(r, _)| r).collect()),
+        notation,
+        canonical: distinct[0].clone(),
+        class_size: distinct.len(),
+        unique,
+    }
+}
+
+/// Notation of every distinct board in the symmetry class of a solution,
+/// the canonical one (see `solver::class_key`) first. Peaceable armies may
+/// also swap colours.
+pub fn orbit(
+    size: (usize, usize),
+    topology: Topology,
+    squares: &[(usize, usize)],
+    colours: &[u8],
+) -> Vec<String> {
+    let mut distinct: Vec<(ClassKey, String)> = if colours.contains(&2) {
+        let (white, black) = solver::armies(squares, colours);
+        let whites = get_variants(&white, size, topology);
+        let blacks = get_variants(&black, size, topology);
+        whites
+            .iter()
+            .zip(&blacks)
+            .flat_map(|(w, b)| [army(w, b), army(b, w)])
+            .map(|(squares, colours)| {
+                (
+                    solver::class_key(&squares, &colours),
+                    solver::army_notation(&squares, &colours),
+                )
+            })
+            .collect()
+    } else {
+        get_variants(squares, size, topology)
+            .iter()
+            .map(|v| (solver::class_key(v, colours), solver::notation(v)))
+            .collect()
+    };
+    distinct.sort();
+    distinct.dedup();
+    distinct.into_iter().map(|(_, notation)| notation).collect()
+}
+
+/// White and black squares as one board, column by column.
+fn army(white: &[(usize, usize)], black: &[(usize, usize)]) -> (Vec<(usize, usize)>, Vec<u8>) {
+    let mut pieces: Vec<((usize, usize), u8)> = white
+        .iter()
+        .map(|&sq| (sq, 1))
+        .chain(black.iter().map(|&sq| (sq, 2)))
+        .collect();
+    pieces.sort_by_key(|&((r, c), _)| (c, r));
+    pieces.into_iter().unzip()
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    run: &'a RunInfo,
+    solutions: &'a [SolutionRecord],
+}
+
+/// A line of the NDJSON export, told apart by its "type" field.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Line<'a> {
+    Run(&'a RunInfo),
+    Solution(&'a SolutionRecord),
+}
+
+/// The listed solutions, numbered as in the history panel. A last "Mask"
+/// row holds the mask rows joined by `/`, so the board can be rebuilt.
+pub fn to_csv(
+    log: &SolutionLog,
+    only_unique: bool,
+    mask: Option<&[String]>,
+) -> Result<String, String> {
+    let error = |e: csv::Error| format!("could not write CSV: {}", e);
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(["Solution #", "Configuration"])
+        .map_err(error)?;
+    for (i, shown) in log.display(only_unique).into_iter().enumerate() {
+        writer
+            .write_record([(i + 1).to_string(), shown])
+            .map_err(error)?;
+    }
+    if let Some(rows) = mask {
+        writer
+            .write_record(["Mask".to_owned(), rows.join("/")])
+            .map_err(error)?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| format!("could not write CSV: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("could not write CSV: {}", e))
+}
+
+pub fn to_json(run: &RunInfo, solutions: &[SolutionRecord]) -> Result<String, String> {
+    serde_json::to_string_pretty(&Document { run, solutions })
+        .map_err(|e| format!("could not write JSON: {}", e))
+}
+
+/// The run first, then one solution per line.
+pub fn to_ndjson(run: &RunInfo, solutions: &[SolutionRecord]) -> Result<String, String> {
+    let mut out = String::new();
+    let lines = std::iter::once(Line::Run(run)).chain(solutions.iter().map(Line::Solution));
+    for line in lines {
+        let text =
+            serde_json::to_string(&line).map_err(|e| format!("could not write NDJSON: {}", e))?;
+        out.push_str(&text);
+        out.push('\n');
+    }
+    Ok(out)
+}
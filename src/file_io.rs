@@ -0,0 +1,241 @@
+//! Saving and opening user files: rfd dialogs on desktop, downloads and the
+//! browser's file picker on the web.
+
+use std::sync::mpsc::{channel, Receiver};
+
+/// Dialog filter and browser MIME type for one kind of file.
+pub struct FileKind {
+    pub name: &'static str,
+    pub extension: &'static str,
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub mime: &'static str,
+}
+
+pub const CSV: FileKind = FileKind {
+    name: "CSV",
+    extension: "csv",
+    mime: "text/csv",
+};
+
+pub const JSON: FileKind = FileKind {
+    name: "JSON",
+    extension: "json",
+    mime: "application/json",
+};
+
+pub const NDJSON: FileKind = FileKind {
+    name: "NDJSON",
+    extension: "ndjson",
+    mime: "application/x-ndjson",
+};
+
+pub const SVG: FileKind = FileKind {
+    name: "SVG image",
+    extension: "svg",
+    mime: "image/svg+xml",
+};
+
+pub const PNG: FileKind = FileKind {
+    name: "PNG image",
+    extension: "png",
+    mime: "image/png",
+};
+
+pub const PDF: FileKind = FileKind {
+    name: "PDF document",
+    extension: "pdf",
+    mime: "application/pdf",
+};
+
+pub const TEX: FileKind = FileKind {
+    name: "LaTeX",
+    extension: "tex",
+    mime: "application/x-tex",
+};
+
+pub const GIF: FileKind = FileKind {
+    name: "GIF animation",
+    extension: "gif",
+    mime: "image/gif",
+};
+
+pub const APNG: FileKind = FileKind {
+    name: "Animated PNG",
+    extension: "png",
+    mime: "image/apng",
+};
+
+pub const TRACE: FileKind = FileKind {
+    name: "Search trace",
+    extension: "trace",
+    mime: "text/plain",
+};
+
+pub const STORE: FileKind = FileKind {
+    name: "Packed solutions",
+    extension: "nqps",
+    mime: "application/octet-stream",
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const SQLITE: FileKind = FileKind {
+    name: "SQLite database",
+    extension: "sqlite",
+    mime: "application/vnd.sqlite3",
+};
+
+pub const CNF: FileKind = FileKind {
+    name: "DIMACS CNF",
+    extension: "cnf",
+    mime: "text/plain",
+};
+
+pub const SAT_MODEL: FileKind = FileKind {
+    name: "SAT solver output",
+    extension: "txt",
+    mime: "text/plain",
+};
+
+pub const MASK: FileKind = FileKind {
+    name: "Board mask",
+    extension: "txt",
+    mime: "text/plain",
+};
+
+/// A file the user picked.
+pub struct OpenedFile {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+impl OpenedFile {
+    /// The content as text; invalid UTF-8 is replaced.
+    pub fn text(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.bytes)
+    }
+}
+
+/// Save `content`: a save dialog on desktop, a download in the browser.
+/// Cancelling the dialog is not an error.
+pub fn save_text(file_name: &str, kind: &FileKind, content: &str) -> Result<(), String> {
+    save_bytes(file_name, kind, content.as_bytes())
+}
+
+/// Like `save_text`, for binary files such as images.
+pub fn save_bytes(file_name: &str, kind: &FileKind, content: &[u8]) -> Result<(), String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter(kind.name, &[kind.extension])
+            .set_file_name(file_name)
+            .save_file()
+        {
+            std::fs::write(&path, content)
+                .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        download(file_name, kind.mime, content);
+        Ok(())
+    }
+}
+
+/// Where to write a file that is not built in memory, such as a database.
+/// None when the dialog is cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_path(file_name: &str, kind: &FileKind) -> Option<std::path::PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter(kind.name, &[kind.extension])
+        .set_file_name(file_name)
+        .save_file()
+}
+
+/// Let the user pick a file of one of `kinds`. The browser dialog is
+/// asynchronous, so the content arrives through the receiver, possibly a few
+/// frames later.
+pub fn open(kinds: &[&FileKind]) -> Receiver<Result<OpenedFile, String>> {
+    let (tx, rx) = channel();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = kinds
+        .iter()
+        .fold(rfd::FileDialog::new(), |dialog, kind| {
+            dialog.add_filter(kind.name, &[kind.extension])
+        })
+        .add_filter("All files", &["*"])
+        .pick_file()
+    {
+        let file = std::fs::read(&path)
+            .map(|bytes| OpenedFile {
+                name: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                bytes,
+            })
+            .map_err(|e| format!("could not read {}: {}", path.display(), e));
+        let _ = tx.send(file);
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let dialog = kinds
+            .iter()
+            .fold(rfd::AsyncFileDialog::new(), |dialog, kind| {
+                dialog.add_filter(kind.name, &[kind.extension])
+            })
+            .add_filter("All files", &["*"]);
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(handle) = dialog.pick_file().await {
+                let bytes = handle.read().await;
+                let _ = tx.send(Ok(OpenedFile {
+                    name: handle.file_name(),
+                    bytes,
+                }));
+            }
+        });
+    }
+    rx
+}
+
+/// A file dropped on the window: the browser hands over its bytes, the
+/// desktop only its path.
+pub fn dropped(file: &egui::DroppedFile) -> Result<OpenedFile, String> {
+    let bytes = match (&file.bytes, &file.path) {
+        (Some(bytes), _) => bytes.to_vec(),
+        (None, Some(path)) => {
+            std::fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?
+        }
+        (None, None) => return Err(format!("could not read {}", file.name)),
+    };
+    let name = match &file.path {
+        Some(path) => path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        None => file.name.clone(),
+    };
+    Ok(OpenedFile { name, bytes })
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn download(file_name: &str, mime: &str, content: &[u8]) {
+    use wasm_bindgen::JsCast;
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(content));
+    let mut blob_options = web_sys::BlobPropertyBag::new();
+    blob_options.set_type(mime);
+    let blob =
+        web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &blob_options).unwrap();
+    let url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
+    let a = document
+        .create_element("a")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .unwrap();
+    a.set_href(&url);
+    a.set_download(file_name);
+    a.click();
+    web_sys::Url::revoke_object_url(&url).unwrap();
+}
@@ -0,0 +1,401 @@
+//! Reading exported solutions back: the CSV, JSON and NDJSON of `export`.
+//! Every placement is checked against the board it came from; the ones
+//! that don't hold up are reported and left out.
+
+use serde::Deserialize;
+
+use crate::solver::{Board, Constraints, Goal, Piece, Topology, BOARD_SIZES};
+use crate::validate::{self, Placement};
+
+/// Most problems listed in the status line; the rest are only counted.
+const MAX_REPORTED: usize = 10;
+
+/// The valid solutions of a file, with the board they belong on.
+pub struct Imported {
+    pub n: usize,
+    pub constraints: Constraints,
+    pub boards: Vec<Board>,
+    /// "line 4: ..." for each placement that was left out.
+    pub errors: Vec<String>,
+}
+
+impl Imported {
+    /// One line for the status bar, followed by the first few problems.
+    pub fn summary(&self, file_name: &str) -> String {
+        let mut lines = vec![format!(
+            "{}: imported {} solution(s), {} invalid",
+            file_name,
+            self.boards.len(),
+            self.errors.len()
+        )];
+        lines.extend(self.errors.iter().take(MAX_REPORTED).cloned());
+        if self.errors.len() > MAX_REPORTED {
+            lines.push(format!("... and {} more", self.errors.len() - MAX_REPORTED));
+        }
+        lines.join("\n")
+    }
+}
+
+/// The `run` part of a JSON export; fields it doesn't need are ignored.
+/// Search traces describe their board the same way.
+#[derive(Deserialize)]
+pub struct RunIn {
+    pub n: usize,
+    pub cols: usize,
+    pub piece: String,
+    pub topology: String,
+    pub goal: String,
+    pub mask: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct SolutionIn {
+    index: Option<usize>,
+    squares: Vec<(usize, usize)>,
+    colours: Option<Vec<u8>>,
+}
+
+#[derive(Deserialize)]
+struct DocumentIn {
+    run: RunIn,
+    solutions: Vec<SolutionIn>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum LineIn {
+    Run(RunIn),
+    Solution(SolutionIn),
+}
+
+/// A placement as read from the file, before it is checked.
+struct Entry {
+    // Where to point the user: "line 4" or "solution 3", and the number
+    // problems are sorted by
+    place: String,
+    order: usize,
+    placement: Placement,
+}
+
+/// Read any of the exported formats. CSV files carry no metadata, so their
+/// solutions are checked against `current` (piece, board and goal), sized to
+/// fit unless the file has a "Mask" row.
+pub fn parse(content: &str, n: usize, current: &Constraints) -> Result<Imported, String> {
+    let trimmed = content.trim_start();
+    if !trimmed.starts_with('{') {
+        return parse_csv(content, n, current);
+    }
+    let first = trimmed.lines().next().unwrap_or("");
+    if serde_json::from_str::<LineIn>(first).is_ok() {
+        parse_ndjson(content, current)
+    } else {
+        parse_json(content, current)
+    }
+}
+
+fn parse_csv(content: &str, n: usize, current: &Constraints) -> Result<Imported, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    let mut mask = None;
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("not a solutions CSV: {}", e))?;
+        let line = record.position().map_or(0, |p| p.line());
+        match (record.get(0), record.get(1)) {
+            (Some("Mask"), Some(rows)) => mask = Some(rows.to_owned()),
+            (Some(_), Some(text)) => match Placement::parse(text) {
+                Ok(placement) => entries.push(Entry {
+                    place: format!("line {}", line),
+                    order: line as usize,
+                    placement,
+                }),
+                Err(e) => errors.push((line as usize, format!("line {}: {}", line, e))),
+            },
+            _ => errors.push((
+                line as usize,
+                format!("line {}: expected a number and a solution", line),
+            )),
+        }
+    }
+
+    let (n, constraints) = match mask {
+        Some(rows) => {
+            let (n, masked) = Constraints::from_mask(&rows)?;
+            (
+                n,
+                Constraints {
+                    topology: current.topology,
+                    piece: current.piece.clone(),
+                    goal: current.goal,
+                    ..masked
+                },
+            )
+        }
+        None => {
+            // The smallest square board the placements fit on, unless they
+            // fit the current one and need not fill it
+            let squares: Vec<(usize, usize)> = entries
+                .iter()
+                .flat_map(|e| e.placement.squares().0)
+                .collect();
+            let side = squares
+                .iter()
+                .map(|&(r, c)| r.max(c) + 1)
+                .max()
+                .unwrap_or(n)
+                .max(4);
+            let cols = current.cols(n);
+            let fits = squares.iter().all(|&(r, c)| r < n && c < cols);
+            if fits && current.goal != Goal::Fill {
+                (n, current.clone())
+            } else {
+                let constraints = Constraints {
+                    fixed: Vec::new(),
+                    blocked: Vec::new(),
+                    width: None,
+                    ..current.clone()
+                };
+                (side, constraints)
+            }
+        }
+    };
+    let cols = constraints.cols(n);
+    if !BOARD_SIZES.contains(&n) || !BOARD_SIZES.contains(&cols) {
+        return Err(format!(
+            "solutions need a {}x{} board, which is not supported",
+            n, cols
+        ));
+    }
+    Ok(check_all(n, constraints, entries, errors))
+}
+
+fn parse_json(content: &str, current: &Constraints) -> Result<Imported, String> {
+    let document: DocumentIn =
+        serde_json::from_str(content).map_err(|e| format!("not a solutions JSON: {}", e))?;
+    let (n, constraints) = run_constraints(&document.run, current)?;
+    let entries = document
+        .solutions
+        .into_iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let index = s.index.unwrap_or(i + 1);
+            entry(format!("solution {}", index), index, s)
+        })
+        .collect();
+    Ok(check_all(n, constraints, entries, Vec::new()))
+}
+
+fn parse_ndjson(content: &str, current: &Constraints) -> Result<Imported, String> {
+    let mut run = None;
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<LineIn>(line) {
+            Ok(LineIn::Run(r)) => run = Some(r),
+            Ok(LineIn::Solution(s)) => entries.push(entry(format!("line {}", i + 1), i + 1, s)),
+            Err(e) => errors.push((i + 1, format!("line {}: {}", i + 1, e))),
+        }
+    }
+    let run = run.ok_or("the file has no run line")?;
+    let (n, constraints) = run_constraints(&run, current)?;
+    Ok(check_all(n, constraints, entries, errors))
+}
+
+fn entry(place: String, order: usize, solution: SolutionIn) -> Entry {
+    let colours = solution
+        .colours
+        .unwrap_or_else(|| vec![1; solution.squares.len()]);
+    Entry {
+        place,
+        order,
+        placement: Placement::Squares {
+            squares: solution.squares,
+            colours,
+        },
+    }
+}
+
+/// The board and rules a JSON export was made with. Custom leapers don't
+/// export their offsets, so they are taken from the current piece.
+pub fn run_constraints(run: &RunIn, current: &Constraints) -> Result<(usize, Constraints), String> {
+    let piece = match run.piece.as_str() {
+        "leaper" => match &current.piece {
+            Piece::Leaper(_) => current.piece.clone(),
+            _ => return Err("pick the custom leaper the file was made with first".to_owned()),
+        },
+        key => Piece::PRESETS
+            .into_iter()
+            .find(|p| p.key() == key)
+            .ok_or(format!("unknown piece \"{}\"", key))?,
+    };
+    let topology = Topology::ALL
+        .into_iter()
+        .find(|t| t.key() == run.topology)
+        .ok_or(format!("unknown board \"{}\"", run.topology))?;
+    let goal = Goal::ALL
+        .into_iter()
+        .find(|g| g.label() == run.goal)
+        .ok_or(format!("unknown goal \"{}\"", run.goal))?;
+    if !BOARD_SIZES.contains(&run.n) || !BOARD_SIZES.contains(&run.cols) {
+        return Err(format!("a {}x{} board is not supported", run.n, run.cols));
+    }
+    let mut constraints = match &run.mask {
+        Some(rows) => {
+            let (n, mask) = Constraints::from_mask(&rows.join("/"))?;
+            if (n, mask.cols(n)) != (run.n, run.cols) {
+                return Err(format!(
+                    "the mask is for a {}x{} board, not {}x{}",
+                    n,
+                    mask.cols(n),
+                    run.n,
+                    run.cols
+                ));
+            }
+            mask
+        }
+        None => Constraints::default(),
+    };
+    constraints.width = (run.cols != run.n).then_some(run.cols);
+    constraints.piece = piece;
+    constraints.topology = topology;
+    constraints.goal = goal;
+    Ok((run.n, constraints))
+}
+
+/// Keep the placements that solve the board, with every problem of the
+/// others; exports of k pieces take k from the first one.
+fn check_all(
+    n: usize,
+    mut constraints: Constraints,
+    entries: Vec<Entry>,
+    mut errors: Vec<(usize, String)>,
+) -> Imported {
+    if let (Goal::Place(_), Some(first)) = (constraints.goal, entries.first()) {
+        constraints.goal = Goal::Place(first.placement.squares().0.len());
+    }
+    let mut boards = Vec::new();
+    for e in entries {
+        let report = validate::validate(n, &constraints, &e.placement);
+        if report.is_valid() {
+            boards.push(e.placement.to_board(n, &constraints));
+        } else {
+            let problems: Vec<String> = report.problems.iter().map(|p| p.to_string()).collect();
+            errors.push((e.order, format!("{}: {}", e.place, problems.join("; "))));
+        }
+    }
+    errors.sort_by_key(|&(order, _)| order);
+    Imported {
+        n,
+        constraints,
+        boards,
+        errors: errors.into_iter().map(|(_, e)| e).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{self, RunInfo};
+    use crate::solver::{Solver, SolverConfig};
+
+    fn solved(n: usize, constraints: &Constraints) -> Box<dyn Solver> {
+        let mut solver = SolverConfig::default().build(n, constraints);
+        while !solver.is_finished() {
+            solver.step();
+        }
+        solver
+    }
+
+    fn boards(n: usize, solver: &dyn Solver) -> Vec<Board> {
+        solver
+            .solutions()
+            .placements
+            .iter()
+            .map(|squares| {
+                let mut board = vec![vec![0; n]; n];
+                for &(r, c) in squares {
+                    board[r][c] = 1;
+                }
+                board
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exports_read_back() {
+        let plain = Constraints::default();
+        let masked = Constraints {
+            fixed: vec![(1, 0)],
+            blocked: vec![(5, 5)],
+            ..Constraints::default()
+        };
+        for constraints in [plain, masked] {
+            let solver = solved(6, &constraints);
+            let expected = boards(6, solver.as_ref());
+            assert!(!expected.is_empty());
+
+            let mask = (!constraints.is_empty()).then(|| constraints.to_mask(6));
+            let csv = export::to_csv(solver.solutions(), false, mask.as_deref()).unwrap();
+            let run = RunInfo::new(6, &constraints, solver.as_ref());
+            let records = export::records(6, &constraints, solver.solutions(), false);
+            let json = export::to_json(&run, &records).unwrap();
+            let ndjson = export::to_ndjson(&run, &records).unwrap();
+
+            // Read back while a different board is showing
+            for text in [csv, json, ndjson] {
+                let imported = parse(&text, 8, &Constraints::default()).unwrap();
+                assert_eq!(imported.n, 6);
+                assert_eq!(imported.constraints.fixed, constraints.fixed);
+                assert_eq!(imported.constraints.blocked, constraints.blocked);
+                assert_eq!(imported.boards, expected);
+                assert!(imported.errors.is_empty(), "{:?}", imported.errors);
+            }
+        }
+    }
+
+    #[test]
+    fn bad_lines_are_reported_in_order() {
+        let csv = "Solution #,Configuration\n\
+                   1,\"b1, d2, a3, c4\"\n\
+                   2,\"a1, b2, c3, d4\"\n\
+                   3,nonsense\n\
+                   4,\"c1, a2, d3, b4\"\n";
+        let imported = parse(csv, 4, &Constraints::default()).unwrap();
+        assert_eq!(imported.boards.len(), 2);
+        assert_eq!(imported.errors.len(), 2);
+        assert!(imported.errors[0].starts_with("line 3: "));
+        assert!(imported.errors[1].starts_with("line 4: "));
+
+        let solver = solved(4, &Constraints::default());
+        let run = RunInfo::new(4, &Constraints::default(), solver.as_ref());
+        let records = export::records(4, &Constraints::default(), solver.solutions(), false);
+        let mut lines: Vec<String> = export::to_ndjson(&run, &records)
+            .unwrap()
+            .lines()
+            .map(str::to_owned)
+            .collect();
+        lines.insert(
+            2,
+            r#"{"type":"solution","squares":[[0,0],[1,1]]}"#.to_owned(),
+        );
+        lines.insert(3, "{not json".to_owned());
+        let imported = parse(&lines.join("\n"), 4, &Constraints::default()).unwrap();
+        assert_eq!(imported.boards.len(), 2);
+        assert_eq!(imported.errors.len(), 2);
+        assert!(imported.errors[0].starts_with("line 3: "));
+        assert!(imported.errors[1].starts_with("line 4: "));
+    }
+
+    #[test]
+    fn boards_outside_the_size_range_are_rejected() {
+        let tiny = "Solution #,Configuration\n1,a1\nMask,Q../.../...\n";
+        assert!(parse(tiny, 8, &Constraints::default()).is_err());
+        let huge = "Solution #,Configuration\n1,\"a31\"\n";
+        assert!(parse(huge, 8, &Constraints::default()).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! Boards as LaTeX figures for course notes: plain TikZ, or the `chessboard`
+//! package. Squares are named as everywhere else in the app (a1 is row 0,
+//! column 0); TikZ draws row 0 at the top like the app, while `chessboard`
+//! puts rank 1 at the bottom as in print.
+
+use crate::solver::{self, Board, Constraints, Piece};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Tikz,
+    Chessboard,
+}
+
+impl Style {
+    pub fn label(self) -> &'static str {
+        match self {
+            Style::Tikz => "TikZ",
+            Style::Chessboard => "chessboard",
+        }
+    }
+}
+
+pub struct Options<'a> {
+    pub style: Style,
+    /// Shade the squares some piece attacks, as "Show threats" does.
+    pub threats: bool,
+    /// `\caption` of the figure; none when empty.
+    pub caption: &'a str,
+}
+
+/// A figure with one board per entry, each labelled with its text (usually
+/// the notation) underneath.
+pub fn figure(
+    constraints: &Constraints,
+    boards: &[(String, Board)],
+    options: &Options,
+) -> Result<String, String> {
+    if options.style == Style::Chessboard {
+        if let Some(board) = boards.iter().find(|(_, b)| b.len() > 26 || b[0].len() > 26) {
+            return Err(format!(
+                "chessboard draws at most 26 files and ranks, not {}x{}",
+                board.1.len(),
+                board.1[0].len()
+            ));
+        }
+    }
+    let mut out = String::from("\\begin{figure}[htbp]\n  \\centering\n");
+    for (i, (label, board)) in boards.iter().enumerate() {
+        // Three boards to a line
+        if i > 0 {
+            out.push_str(if i % 3 == 0 {
+                "  \\par\\medskip\n"
+            } else {
+                "  \\hfill\n"
+            });
+        }
+        out.push_str("  \\begin{minipage}[t]{0.3\\textwidth}\n    \\centering\n");
+        match options.style {
+            Style::Tikz => tikz(&mut out, constraints, board, options.threats),
+            Style::Chessboard => chessboard(&mut out, constraints, board, options.threats),
+        }
+        out.push_str(&format!(
+            "    \\\\[2pt]{{\\footnotesize {}}}\n  \\end{{minipage}}\n",
+            escape(label)
+        ));
+    }
+    if !options.caption.is_empty() {
+        out.push_str(&format!("  \\caption{{{}}}\n", escape(options.caption)));
+    }
+    out.push_str("\\end{figure}\n");
+    Ok(out)
+}
+
+/// Notation of the pieces on a board, file by file as in the history panel.
+pub fn notation(board: &Board) -> String {
+    let cols = board.first().map_or(0, Vec::len);
+    let (squares, colours): (Vec<_>, Vec<_>) = (0..cols)
+        .flat_map(|c| (0..board.len()).map(move |r| (r, c)))
+        .filter(|&(r, c)| board[r][c] != 0)
+        .map(|(r, c)| ((r, c), board[r][c]))
+        .unzip();
+    if colours.contains(&2) {
+        solver::army_notation(&squares, &colours)
+    } else {
+        solver::notation(&squares)
+    }
+}
+
+/// Letter of the piece in chess notation; knights stand in for custom
+/// leapers and queens for amazons, as in the app's glyphs.
+fn letter(piece: &Piece) -> char {
+    match piece {
+        Piece::Queen | Piece::Amazon => 'Q',
+        Piece::Rook => 'R',
+        Piece::Bishop => 'B',
+        Piece::King => 'K',
+        Piece::Knight | Piece::Leaper(_) => 'N',
+    }
+}
+
+fn square(r: usize, c: usize) -> String {
+    solver::notation(&[(r, c)])
+}
+
+fn tikz(out: &mut String, constraints: &Constraints, board: &Board, threats: bool) {
+    let (rows, cols) = (board.len(), board[0].len());
+    let scale = 4.0 / rows.max(cols) as f32;
+    out.push_str(&format!(
+        "    \\begin{{tikzpicture}}[x={:.3}cm, y=-{:.3}cm]\n",
+        scale, scale
+    ));
+    for r in 0..rows {
+        for c in 0..cols {
+            let at = format!("({},{}) rectangle +(1,1)", c, r);
+            if constraints.blocked.contains(&(r, c)) {
+                out.push_str(&format!("      \\fill[black!80] {};\n", at));
+                continue;
+            }
+            if (r + c) % 2 == 1 {
+                out.push_str(&format!("      \\fill[black!25] {};\n", at));
+            }
+            if threats && solver::is_threatened(board, r, c, constraints) {
+                out.push_str(&format!("      \\fill[red, opacity=0.3] {};\n", at));
+            }
+        }
+    }
+    out.push_str(&format!(
+        "      \\draw (0,0) rectangle ({},{});\n",
+        cols, rows
+    ));
+    let letter = letter(&constraints.piece);
+    for (r, cells) in board.iter().enumerate() {
+        for (c, &cell) in cells.iter().enumerate() {
+            let style = match cell {
+                1 => "draw, fill=white",
+                2 => "draw, fill=black, text=white",
+                _ => continue,
+            };
+            // Pre-placed pieces get a thicker ring
+            let fixed = if constraints.fixed.contains(&(r, c)) {
+                ", very thick"
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "      \\node[circle, {}{}, inner sep=1pt, font=\\scriptsize\\bfseries] at ({}.5,{}.5) {{{}}}; % {}\n",
+                style,
+                fixed,
+                c,
+                r,
+                letter,
+                square(r, c)
+            ));
+        }
+    }
+    out.push_str("    \\end{tikzpicture}\n");
+}
+
+fn chessboard(out: &mut String, constraints: &Constraints, board: &Board, threats: bool) {
+    let (rows, cols) = (board.len(), board[0].len());
+    let letter = letter(&constraints.piece);
+    let mut pieces = Vec::new();
+    let mut threatened = Vec::new();
+    for (r, cells) in board.iter().enumerate() {
+        for (c, &cell) in cells.iter().enumerate() {
+            match cell {
+                1 => pieces.push(format!("{}{}", letter, square(r, c))),
+                2 => pieces.push(format!("{}{}", letter.to_ascii_lowercase(), square(r, c))),
+                _ if threats
+                    && !constraints.blocked.contains(&(r, c))
+                    && solver::is_threatened(board, r, c, constraints) =>
+                {
+                    threatened.push(square(r, c))
+                }
+                _ => {}
+            }
+        }
+    }
+    let blocked: Vec<String> = constraints
+        .blocked
+        .iter()
+        .filter(|&&(r, c)| r < rows && c < cols)
+        .map(|&(r, c)| square(r, c))
+        .collect();
+    let mut keys = vec![
+        format!("maxfield={}", square(rows - 1, cols - 1)),
+        "showmover=false".to_owned(),
+        // Keeps the board inside its third of the line
+        format!("boardfontsize={}pt", (96 / rows.max(cols)).clamp(6, 20)),
+        format!("setpieces={{{}}}", pieces.join(",")),
+    ];
+    if !threatened.is_empty() {
+        keys.push("pgfstyle=color, color=red!30, opacity=0.6".to_owned());
+        keys.push(format!("markfields={{{}}}", threatened.join(",")));
+    }
+    if !blocked.is_empty() {
+        keys.push("pgfstyle=cross, color=black".to_owned());
+        keys.push(format!("markfields={{{}}}", blocked.join(",")));
+    }
+    out.push_str(&format!("    \\chessboard[{}]\n", keys.join(",\n      ")));
+}
+
+/// Keep notation and captions from breaking the document.
+fn escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|ch| match ch {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => vec!['\\', ch],
+            '\\' => "\\textbackslash{}".chars().collect(),
+            '~' => "\\textasciitilde{}".chars().collect(),
+            '^' => "\\textasciicircum{}".chars().collect(),
+            _ => vec![ch],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(escape("a & b"), "a \\& b");
+        assert_eq!(escape("50% of #1_x"), "50\\% of \\#1\\_x");
+        assert_eq!(escape("{a}"), "\\{a\\}");
+        assert_eq!(
+            escape("\\~^"),
+            "\\textbackslash{}\\textasciitilde{}\\textasciicircum{}"
+        );
+        assert_eq!(escape("a1, b3"), "a1, b3");
+    }
+
+    #[test]
+    fn figures_escape_labels_and_captions() {
+        let mut board = vec![vec![0; 4]; 4];
+        for (col, row) in [1, 3, 0, 2].into_iter().enumerate() {
+            board[row][col] = 1;
+        }
+        let boards = [("#1 b1, d2".to_owned(), board)];
+        for style in [Style::Tikz, Style::Chessboard] {
+            let options = Options {
+                style,
+                threats: false,
+                caption: "4 queens & 100% safe",
+            };
+            let tex = figure(&Constraints::default(), &boards, &options).unwrap();
+            assert!(tex.starts_with("\\begin{figure}") && tex.ends_with("\\end{figure}\n"));
+            assert!(tex.contains("{\\footnotesize \\#1 b1, d2}"));
+            assert!(tex.contains("\\caption{4 queens \\& 100\\% safe}"));
+        }
+    }
+}
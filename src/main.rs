@@ -1,4 +1,14 @@
+use challenge::{Challenge, Difficulty, Leaderboard, Puzzle, PuzzleGenerator};
 use eframe::egui;
+use file_io::OpenedFile;
+use play::Game;
+use rng::Rng;
+use solver::{
+    Algorithm, Axis, Board, ColumnOrder, Constraints, CubeSolver, Goal, OrderingComparison, Piece,
+    RowOrder, SolutionBrowser, Solver, SolverConfig, Topology, TraceRecorder, TraceReplay,
+    CUBE_STEP_BUDGET,
+};
+use std::sync::mpsc::{Receiver, TryRecvError};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 #[cfg(target_arch = "wasm32")]
@@ -6,8 +16,37 @@ use wasm_bindgen::JsCast;
 #[cfg(target_arch = "wasm32")]
 use web_time::{Duration, Instant};
 
+mod animation;
+mod challenge;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod cnf;
+#[cfg(not(target_arch = "wasm32"))]
+mod database;
+mod export;
+mod file_io;
+mod import;
+mod latex;
+mod play;
+mod render;
+mod rng;
+mod sat;
+mod solver;
+mod store;
+mod trace;
+mod validate;
+
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = cli::run(args) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([1100.0, 750.0]),
         ..Default::default()
@@ -18,7 +57,7 @@ fn main() -> eframe::Result<()> {
         Box::new(|cc| {
             // Apply a default style that works well with our theme
             cc.egui_ctx.set_visuals(egui::Visuals::dark());
-            Ok(Box::new(EightQueensApp::default()))
+            Ok(Box::new(EightQueensApp::new(cc)))
         }),
     )
 }
@@ -48,7 +87,7 @@ fn main() {
                     cc.egui_ctx.set_visuals(egui::Visuals::dark());
                     // Recommended for web: avoid infinite resize loops
                     cc.egui_ctx.set_pixels_per_point(1.0);
-                    Ok(Box::new(EightQueensApp::default()))
+                    Ok(Box::new(EightQueensApp::new(cc)))
                 }),
             )
             .await
@@ -66,6 +105,8 @@ struct Theme {
     board_light: egui::Color32,
     board_dark: egui::Color32,
     queen_color: egui::Color32,
+    // The black army of peaceable queens
+    second_queen_color: egui::Color32,
 }
 
 impl Theme {
@@ -80,6 +121,7 @@ impl Theme {
                 board_light: egui::Color32::from_rgb(241, 245, 249),
                 board_dark: egui::Color32::from_rgb(100, 116, 139),
                 queen_color: egui::Color32::from_rgb(15, 23, 42),
+                second_queen_color: egui::Color32::from_rgb(220, 38, 38),
             },
             Self {
                 name: "Classic Wood",
@@ -90,6 +132,7 @@ impl Theme {
                 board_light: egui::Color32::from_rgb(210, 180, 140),
                 board_dark: egui::Color32::from_rgb(139, 69, 19),
                 queen_color: egui::Color32::from_rgb(45, 25, 10),
+                second_queen_color: egui::Color32::from_rgb(150, 20, 20),
             },
             Self {
                 name: "Neon Night",
@@ -100,6 +143,7 @@ impl Theme {
                 board_light: egui::Color32::from_rgb(30, 30, 60),
                 board_dark: egui::Color32::from_rgb(15, 15, 30),
                 queen_color: egui::Color32::from_rgb(255, 255, 0),
+                second_queen_color: egui::Color32::from_rgb(0, 255, 128),
             },
             Self {
                 name: "Paper",
@@ -110,6 +154,7 @@ impl Theme {
                 board_light: egui::Color32::from_rgb(255, 255, 250),
                 board_dark: egui::Color32::from_rgb(200, 200, 190),
                 queen_color: egui::Color32::from_rgb(20, 20, 20),
+                second_queen_color: egui::Color32::from_rgb(30, 80, 200),
             },
         ]
     }
@@ -126,7 +171,8 @@ struct EightQueensApp {
     n_input: String,
     n: usize,
 
-    solver: SolverWrapper,
+    config: SolverConfig,
+    solver: Box<dyn Solver>,
 
     speed: u64, // 1-10
     paused: bool,
@@ -137,186 +183,1159 @@ struct EightQueensApp {
     theme: Theme,
     show_threats: bool,
     only_unique: bool,
+    // Boards per row of the gallery export
+    gallery_columns: usize,
+    // LaTeX export: package to draw with and the figure caption
+    latex_style: latex::Style,
+    latex_caption: String,
+    // Search recording: first step, number of frames and ms per frame
+    record_from: u64,
+    record_frames: usize,
+    record_delay: u32,
+    // Log every step of the next searches, or play a loaded trace instead
+    record_trace: bool,
+    replay: Option<trace::Loaded>,
+    // Pre-placed queens every solver has to respect, and what clicks on the board do
+    constraints: Constraints,
+    board_tool: BoardTool,
+    // Offsets typed for a custom leaper, kept while another piece is picked
+    leaper_input: String,
+    // The user's own board while the Play tool is selected
+    game: Option<Game>,
+    // Timed puzzle in progress, and the persisted best runs
+    challenge: Option<Challenge>,
+    // The next challenge's puzzle, graded a frame's worth at a time
+    puzzle_generator: Option<PuzzleGenerator>,
+    challenge_difficulty: Difficulty,
+    leaderboard: Leaderboard,
+    // The 3D problem, separate from the board: its search and the slice shown
+    cube: Option<CubeSolver>,
+    cube_n: usize,
+    cube_axis: Axis,
+    cube_layer: usize,
+    // Nodes to the first solution for every ordering, from "Compare orderings";
+    // run a frame's worth at a time until it is finished
+    ordering_report: Option<(Algorithm, usize, OrderingComparison)>,
+
+    // File dialog still open (always the case for a moment on the web)
+    pending_open: Option<(OpenPurpose, Receiver<Result<OpenedFile, String>>)>,
+    // Outcome of the last import or export
+    status: Option<String>,
 }
-struct SolverWrapper {
-    n: usize,
-    board: Vec<Vec<u8>>,
-    solutions: Vec<String>,
-
-    // DFS State
-    stack: Vec<(usize, usize)>,
-
-    // We need to know if we are "forwarding" or "backtracking"
-    col: usize,
-    row: usize,
-    backtracking: bool,
-    finished: bool,
-    last_solution_board: Option<Vec<Vec<u8>>>,
-    unique_solutions: Vec<Vec<usize>>, // Store row indices
+
+/// What clicking a board cell does.
+#[derive(Clone, Copy, PartialEq)]
+enum BoardTool {
+    View,
+    FixQueens,
+    Block,
+    Play,
 }
 
-impl SolverWrapper {
-    fn new(n: usize) -> Self {
-        Self {
-            n,
-            board: vec![vec![0; n]; n],
-            solutions: Vec::new(),
-            stack: Vec::new(),
-            col: 0,
-            row: 0,
-            backtracking: false,
-            finished: false,
-            last_solution_board: None,
-            unique_solutions: Vec::new(),
+impl BoardTool {
+    const ALL: [BoardTool; 4] = [
+        BoardTool::View,
+        BoardTool::FixQueens,
+        BoardTool::Block,
+        BoardTool::Play,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            BoardTool::View => "View",
+            BoardTool::FixQueens => "Fix queens",
+            BoardTool::Block => "Block squares",
+            BoardTool::Play => "Play",
         }
     }
+}
+
+/// What a file being opened will be used for.
+#[derive(Clone, Copy)]
+enum OpenPurpose {
+    SatModel,
+    Mask,
+    Solutions,
+    Trace,
+    Store,
+}
 
-    fn get_variants(sol: &[usize]) -> Vec<Vec<usize>> {
-        let n = sol.len();
-        let mut variants = Vec::new();
+impl EightQueensApp {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self::default();
+        if let Some(storage) = cc.storage {
+            app.leaderboard =
+                eframe::get_value(storage, Leaderboard::STORAGE_KEY).unwrap_or_default();
+        }
+        app
+    }
 
-        // 1. Convert to (x, y) coordinates
-        let coords: Vec<(usize, usize)> = sol.iter().enumerate().map(|(x, &y)| (x, y)).collect();
+    fn reset_solver(&mut self) {
+        self.constraints.fit(self.n);
+        // A loaded trace replays until the board is changed
+        if self
+            .replay
+            .as_ref()
+            .is_some_and(|r| r.n != self.n || r.constraints != self.constraints)
+        {
+            self.replay = None;
+        }
+        self.solver = match &self.replay {
+            Some(replay) => Box::new(TraceReplay::new(
+                replay.trace.clone(),
+                (self.n, self.constraints.cols(self.n)),
+                self.constraints.topology,
+            )),
+            None if self.record_trace => Box::new(TraceRecorder::new(
+                self.config.build(self.n, &self.constraints),
+            )),
+            None => self.config.build(self.n, &self.constraints),
+        };
+        // A challenge ends when its board changes, and its game is never
+        // restarted, which would reset the move and hint counts
+        if self
+            .challenge
+            .as_ref()
+            .is_some_and(|c| c.n != self.n || c.constraints != self.constraints)
+        {
+            self.challenge = None;
+        }
+        if self.game.is_some() && self.challenge.is_none() {
+            self.game = Some(Game::new(self.n, &self.constraints));
+        }
+    }
 
-        // Helper to convert back to sol vector
-        let to_sol = |pts: &[(usize, usize)]| -> Vec<usize> {
-            let mut v = vec![0; n];
-            for &(x, y) in pts {
-                v[x] = y;
-            }
-            v
+    /// Why the search ended without a solution, when that needs saying.
+    fn outcome_message(&self) -> Option<String> {
+        // Known before any search
+        if let Some(reason) = self.constraints.no_solution_reason(self.n) {
+            return Some(reason);
+        }
+        if !self.solver.is_finished() || !self.solver.solutions().solutions.is_empty() {
+            return None;
+        }
+        if let Some(k) = self
+            .constraints
+            .pieces(self.n)
+            .filter(|_| !self.constraints.fills_columns(self.n))
+        {
+            // The square by square search is always exhaustive
+            return Some(format!("No placement of {} pieces exists", k));
+        }
+        if self.constraints.is_empty() {
+            return None;
+        }
+        let message = if self.config.algorithm.is_complete() {
+            "No completion exists for the fixed and blocked squares"
+        } else {
+            "No completion found (min-conflicts is not exhaustive)"
         };
+        Some(message.to_owned())
+    }
 
-        // All 8 transformations
-        // (x, y) ->
-        // 1. (x, y)
-        // 2. (y, n-1-x) - rotate 90
-        // 3. (n-1-x, n-1-y) - rotate 180
-        // 4. (n-1-y, x) - rotate 270
-        // 5. (n-1-x, y) - flip H
-        // 6. (x, n-1-y) - flip V
-        // 7. (y, x) - flip D1
-        // 8. (n-1-y, n-1-x) - flip D2
+    fn board_tool_picker(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_id_salt("board_tool")
+            .selected_text(self.board_tool.label())
+            .show_ui(ui, |ui| {
+                for tool in BoardTool::ALL {
+                    ui.selectable_value(&mut self.board_tool, tool, tool.label());
+                }
+            });
+        match (self.board_tool, &self.game) {
+            (BoardTool::Play, None) => self.game = Some(Game::new(self.n, &self.constraints)),
+            (BoardTool::Play, Some(_)) => {}
+            _ => self.game = None,
+        }
+    }
 
-        let mut curr = coords.clone();
-        for _ in 0..4 {
-            // Rotate
-            variants.push(to_sol(&curr));
-            // Flip H
-            let flipped: Vec<(usize, usize)> = curr.iter().map(|&(x, y)| (n - 1 - x, y)).collect();
-            variants.push(to_sol(&flipped));
+    fn new_challenge(&mut self) {
+        self.puzzle_generator = Some(PuzzleGenerator::new(
+            self.challenge_difficulty,
+            Rng::from_clock(),
+        ));
+    }
+
+    fn start_challenge(&mut self, puzzle: Puzzle, difficulty: Difficulty) {
+        self.n = puzzle.n;
+        self.n_input = puzzle.n.to_string();
+        self.constraints = puzzle.constraints.clone();
+        self.board_tool = BoardTool::Play;
+        self.game = Some(Game::new(self.n, &self.constraints));
+        self.reset_solver();
+        self.paused = true;
+        self.auto_play = false;
+        self.challenge = Some(Challenge::start(&puzzle, difficulty));
+    }
 
-            // Apply 90 rotation for next iteration
-            curr = curr.iter().map(|&(x, y)| (y, n - 1 - x)).collect();
+    /// Stop the clock once the board is solved; drop the challenge when the
+    /// player leaves play mode or changes the board.
+    fn update_challenge(&mut self, ctx: &egui::Context) {
+        let Some(challenge) = &mut self.challenge else {
+            return;
+        };
+        let Some(game) = self
+            .game
+            .as_ref()
+            .filter(|_| challenge.n == self.n && challenge.constraints == self.constraints)
+        else {
+            self.challenge = None;
+            return;
+        };
+        if challenge.result.is_none() {
+            if game.is_solved() {
+                challenge.finish(game.moves, game.hints, &mut self.leaderboard);
+            } else {
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
         }
+    }
 
-        variants
+    fn challenge_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("Challenge")
+                .strong()
+                .color(self.theme.text_color),
+        );
+        ui.separator();
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("challenge_difficulty")
+                .selected_text(self.challenge_difficulty.label())
+                .show_ui(ui, |ui| {
+                    for difficulty in Difficulty::ALL {
+                        ui.selectable_value(
+                            &mut self.challenge_difficulty,
+                            difficulty,
+                            difficulty.label(),
+                        );
+                    }
+                });
+            if ui.button("New challenge").clicked() {
+                self.new_challenge();
+            }
+        });
+        if let Some(generator) = &self.puzzle_generator {
+            ui.label(format!(
+                "Grading random puzzles ({} tried)...",
+                generator.attempts()
+            ));
+        }
+        if let Some(challenge) = &self.challenge {
+            let metrics = challenge.metrics;
+            ui.label(format!(
+                "{} puzzle: {}x{}, {} given, {} completion(s), branching {:.2}",
+                challenge.difficulty.label(),
+                challenge.n,
+                challenge.n,
+                challenge.n - metrics.free_columns,
+                metrics.completions,
+                metrics.branching()
+            ));
+            ui.label(
+                egui::RichText::new(format!("Time: {:.1} s", challenge.elapsed().as_secs_f64()))
+                    .strong()
+                    .size(16.0),
+            );
+            ui.label(format!(
+                "Hints cost {} s, moves beyond {} cost {} s each",
+                challenge::HINT_PENALTY_SECS,
+                metrics.free_columns,
+                challenge::MOVE_PENALTY_SECS
+            ));
+            if let Some((entry, place)) = &challenge.result {
+                let message = match place {
+                    Some(place) => {
+                        format!("Score {:.1} s, #{} on the leaderboard", entry.score, place)
+                    }
+                    None => format!("Score {:.1} s", entry.score),
+                };
+                ui.label(egui::RichText::new(message).color(self.theme.accent_color));
+            }
+        }
+
+        ui.label(format!("Best {} runs", self.challenge_difficulty.label()));
+        egui::Grid::new("leaderboard").striped(true).show(ui, |ui| {
+            for header in ["#", "Size", "Time", "Moves", "Hints", "Score"] {
+                ui.label(header);
+            }
+            ui.end_row();
+            for (i, entry) in self.leaderboard.best(self.challenge_difficulty).enumerate() {
+                ui.label((i + 1).to_string());
+                ui.label(format!("{}x{}", entry.n, entry.n));
+                ui.label(format!("{:.1} s", entry.seconds));
+                ui.label(entry.moves.to_string());
+                ui.label(entry.hints.to_string());
+                ui.label(format!("{:.1}", entry.score));
+                ui.end_row();
+            }
+        });
     }
 
-    fn is_new_unique(&self, sol: &[usize]) -> bool {
-        let variants = Self::get_variants(sol);
-        for v in variants {
-            if self.unique_solutions.contains(&v) {
-                return false;
+    fn cube_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("3D Cube")
+                .strong()
+                .color(self.theme.text_color),
+        );
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("n² queens in an n×n×n cube, n:");
+            ui.add(egui::DragValue::new(&mut self.cube_n).range(1..=16));
+            if ui.button("Solve").clicked() {
+                self.cube = Some(CubeSolver::new(self.cube_n));
+                self.cube_layer = 0;
+            }
+            if self.cube.is_some() && ui.button("Close").clicked() {
+                self.cube = None;
+            }
+        });
+        let Some(cube) = &self.cube else {
+            return;
+        };
+        let n = cube.n();
+        let stats = cube.stats();
+        let message = match (cube.solution(), cube.is_finished()) {
+            (Some(_), _) if cube.is_constructed() => {
+                "Solved by the linear construction z = ax + by (mod n)".to_owned()
+            }
+            (Some(_), _) => format!("Solved after {} placements", stats.placements),
+            (None, true) if cube.gave_up() => format!(
+                "Gave up after {} steps: n = {} has no linear construction to fall back on",
+                CUBE_STEP_BUDGET, n
+            ),
+            (None, true) => format!("No solution for n = {}", n),
+            (None, false) => format!(
+                "Searching: {} placements, {} backtracks",
+                stats.placements, stats.backtracks
+            ),
+        };
+        ui.label(egui::RichText::new(message).color(self.theme.accent_color));
+
+        // One slice at full size, across the chosen axis
+        ui.horizontal(|ui| {
+            ui.label("Slice across");
+            for axis in Axis::ALL {
+                ui.selectable_value(&mut self.cube_axis, axis, axis.label());
             }
+        });
+        let axis = self.cube_axis;
+        ui.add(
+            egui::Slider::new(&mut self.cube_layer, 0..=n - 1)
+                .text(format!("Layer {}", axis.label())),
+        );
+        let size = (ui.available_width().min(240.0) / n as f32).floor();
+        self.draw_layer(ui, &cube.slice(axis, self.cube_layer), size, true);
+
+        // Every slice across the axis side by side; click one to show it
+        ui.label("Layers");
+        let small = (size * 0.4).max(3.0);
+        let mut picked = None;
+        ui.horizontal_wrapped(|ui| {
+            for z in 0..n {
+                let current = z == self.cube_layer;
+                if self
+                    .draw_layer(ui, &cube.slice(axis, z), small, current)
+                    .clicked()
+                {
+                    picked = Some(z);
+                }
+            }
+        });
+        if let Some(z) = picked {
+            self.cube_layer = z;
         }
-        true
     }
 
-    fn step(&mut self) -> bool {
-        if self.finished {
-            return false;
+    /// Draw a layer of the cube as a small board; `highlight` frames it.
+    fn draw_layer(
+        &self,
+        ui: &mut egui::Ui,
+        layer: &Board,
+        cell: f32,
+        highlight: bool,
+    ) -> egui::Response {
+        let n = layer.len();
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(n as f32, n as f32) * cell, egui::Sense::click());
+        let painter = ui.painter();
+        for (x, row) in layer.iter().enumerate() {
+            for (y, &queen) in row.iter().enumerate() {
+                let cell_rect = egui::Rect::from_min_size(
+                    rect.min + egui::vec2(y as f32, x as f32) * cell,
+                    egui::vec2(cell, cell),
+                );
+                let color = if (x + y) % 2 == 0 {
+                    self.theme.board_light
+                } else {
+                    self.theme.board_dark
+                };
+                painter.rect_filled(cell_rect, 0.0, color);
+                if queen != 0 {
+                    painter.circle_filled(cell_rect.center(), cell * 0.35, self.theme.queen_color);
+                }
+            }
+        }
+        if highlight {
+            painter.rect_stroke(rect, 0.0, egui::Stroke::new(2.0, self.theme.accent_color));
         }
+        response
+    }
 
-        if self.backtracking {
-            if self.col == 0 && self.row >= self.n {
-                self.finished = true;
-                return false;
+    fn play_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(game) = &mut self.game else {
+            return;
+        };
+        ui.label(
+            egui::RichText::new("Play")
+                .strong()
+                .color(self.theme.text_color),
+        );
+        ui.separator();
+        ui.label(format!(
+            "Queens: {}/{}   Moves: {}   Hints: {}",
+            game.queens(),
+            game.target().map_or("?".to_owned(), |k| k.to_string()),
+            game.moves,
+            game.hints
+        ));
+        if game.is_sizing() {
+            ui.label("Searching for the number of queens that solves the board...");
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Hint").clicked() {
+                game.request_hint();
             }
+            // Restarting would reset the move and hint counts of a challenge
+            if self.challenge.is_none() && ui.button("Restart").clicked() {
+                *game = Game::new(self.n, &self.constraints);
+            }
+        });
+        let message = match (game.verdict(), game.hint) {
+            (Some(verdict), _) => Some(verdict),
+            (None, Some(Ok((row, col)))) => Some(format!(
+                "Hint: a queen on {}{}",
+                (b'a' + col as u8) as char,
+                row + 1
+            )),
+            (None, Some(Err(reason))) => Some(reason.to_owned()),
+            (None, None) => None,
+        };
+        if let Some(message) = message {
+            ui.label(egui::RichText::new(message).color(self.theme.accent_color));
+        }
+    }
 
-            // Pop previous
-            if let Some((r, _)) = self.stack.pop() {
-                self.board[r][self.col - 1] = 0; // Remove queen
-                self.col -= 1;
-                self.row = r + 1; // Try next row
-                self.backtracking = false;
-            } else {
-                self.finished = true;
-                return false;
+    fn constraints_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("Board Constraints")
+                .strong()
+                .color(self.theme.text_color),
+        );
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Click on board:");
+            self.board_tool_picker(ui);
+        });
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Fixed: {}   Blocked: {}",
+                self.constraints.fixed.len(),
+                self.constraints.blocked.len()
+            ));
+            if ui.button("Clear").clicked() && !self.constraints.is_empty() {
+                self.constraints.fixed.clear();
+                self.constraints.blocked.clear();
+                self.reset_solver();
+                self.paused = true;
+                self.auto_play = false;
             }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Load mask").clicked() {
+                self.open_file(OpenPurpose::Mask, &[&file_io::MASK]);
+            }
+            if ui.button("Save mask").clicked() {
+                let mut mask = self.constraints.to_mask(self.n).join("\n");
+                mask.push('\n');
+                let file_name = format!("nqueens_{}_mask.txt", self.n);
+                self.save_file(&file_name, &file_io::MASK, &mask);
+            }
+        });
+        // Fixed pieces all belong to the white army
+        if self.constraints.conflicts(1, 1) && !self.constraints.fixed_consistent(self.n) {
+            ui.label(
+                egui::RichText::new("Some fixed queens attack each other")
+                    .color(self.theme.accent_color),
+            );
         }
+    }
 
-        if self.col >= self.n {
-            // Found solution
-            self.save_solution();
-            self.backtracking = true; // Trigger backtrack to find next
-            return true; // Signal solution found
+    /// Apply a click on board cell (row, col) for the current tool.
+    fn click_cell(&mut self, row: usize, col: usize) {
+        match self.board_tool {
+            BoardTool::View => return,
+            BoardTool::Play => {
+                if let Some(game) = &mut self.game {
+                    game.toggle(row, col);
+                }
+                return;
+            }
+            BoardTool::FixQueens => self.constraints.toggle_fixed(row, col),
+            BoardTool::Block => self.constraints.toggle_blocked(row, col),
         }
+        self.reset_solver();
+        self.paused = true;
+        self.auto_play = false;
+    }
 
-        // Search in current col
-        while self.row < self.n {
-            if self.is_safe(self.row, self.col) {
-                self.board[self.row][self.col] = 1;
-                self.stack.push((self.row, self.col));
-                self.col += 1;
-                self.row = 0;
-                return false; // Step complete (placed one queen)
+    /// The piece on the board; a custom leaper reads its offsets from a
+    /// text field such as "1,3" or "1,2; 2,2".
+    fn piece_picker(&mut self, ui: &mut egui::Ui) {
+        let before = self.constraints.piece.clone();
+        ui.horizontal(|ui| {
+            ui.label("Piece:");
+            egui::ComboBox::from_id_salt("piece")
+                .selected_text(self.constraints.piece.label())
+                .show_ui(ui, |ui| {
+                    for piece in Piece::PRESETS {
+                        let label = piece.label();
+                        ui.selectable_value(&mut self.constraints.piece, piece, label);
+                    }
+                    let custom = matches!(self.constraints.piece, Piece::Leaper(_));
+                    if ui.selectable_label(custom, "Custom leaper").clicked() && !custom {
+                        // Start from the last offsets typed, or a camel
+                        self.constraints.piece = Piece::parse_leaper(&self.leaper_input)
+                            .unwrap_or(Piece::Leaper(vec![(1, 3)]));
+                    }
+                });
+        });
+        if matches!(self.constraints.piece, Piece::Leaper(_)) {
+            ui.horizontal(|ui| {
+                ui.label("Offsets:");
+                if ui.text_edit_singleline(&mut self.leaper_input).changed() {
+                    if let Ok(piece) = Piece::parse_leaper(&self.leaper_input) {
+                        self.constraints.piece = piece;
+                    }
+                }
+            });
+            if let Err(e) = Piece::parse_leaper(&self.leaper_input) {
+                ui.label(egui::RichText::new(e).color(egui::Color32::RED));
             }
-            self.row += 1;
         }
+        if self.constraints.piece != before {
+            self.reset_solver();
+            self.paused = true;
+            self.auto_play = false;
+        }
+    }
 
-        // No row found in this col, trigger backtrack
-        self.backtracking = true;
-        false
+    /// How many pieces to place: fill the board, exactly k, or the maximum.
+    fn goal_picker(&mut self, ui: &mut egui::Ui) {
+        let before = self.constraints.goal;
+        let fill = self.constraints.pieces(self.n).unwrap_or(self.n);
+        ui.horizontal(|ui| {
+            ui.label("Pieces:");
+            egui::ComboBox::from_id_salt("goal")
+                .selected_text(self.constraints.goal.label())
+                .show_ui(ui, |ui| {
+                    for goal in Goal::ALL {
+                        let goal = match goal {
+                            Goal::Place(_) => Goal::Place(fill),
+                            other => other,
+                        };
+                        let selected = self.constraints.goal.same_kind(goal);
+                        if ui.selectable_label(selected, goal.label()).clicked() && !selected {
+                            self.constraints.goal = goal;
+                        }
+                    }
+                });
+            let squares = self.n * self.constraints.cols(self.n);
+            if let Goal::Place(k) = &mut self.constraints.goal {
+                ui.add(egui::DragValue::new(k).range(1..=squares).prefix("k = "));
+            }
+        });
+        if self.constraints.goal != before {
+            self.reset_solver();
+            self.paused = true;
+            self.auto_play = false;
+        }
+    }
+
+    /// Says which search runs when it isn't the selected algorithm.
+    fn fallback_note(&self, ui: &mut egui::Ui) {
+        let piece = &self.constraints.piece;
+        let note = if !self.constraints.fills_columns(self.n) {
+            Some("Columns may hold no piece or several: searching square by square".to_owned())
+        } else if !self.config.algorithm.supports(piece) {
+            Some(format!(
+                "{} only handles queens and rooks: using backtracking",
+                self.config.algorithm.label()
+            ))
+        } else {
+            None
+        };
+        if let Some(note) = note {
+            ui.label(
+                egui::RichText::new(note)
+                    .small()
+                    .color(self.theme.accent_color),
+            );
+        }
+    }
+
+    fn algorithm_picker(&mut self, ui: &mut egui::Ui) {
+        let before = self.config.clone();
+        egui::ComboBox::from_id_salt("algorithm_picker")
+            .selected_text(self.config.algorithm.label())
+            .show_ui(ui, |ui| {
+                for algorithm in Algorithm::ALL {
+                    ui.selectable_value(&mut self.config.algorithm, algorithm, algorithm.label());
+                }
+            });
+        self.apply_config(before);
+    }
+
+    /// Per-algorithm switches, shown under the picker on desktop.
+    fn solver_options(&mut self, ui: &mut egui::Ui) {
+        let before = self.config.clone();
+        if self.config.algorithm == Algorithm::ForwardChecking {
+            ui.checkbox(&mut self.config.arc_consistency, "Arc consistency (AC-3)");
+        }
+        if self.config.uses_encoding() {
+            self.encoding_picker(ui);
+        }
+        if self.config.uses_ordering() {
+            let ordering = &mut self.config.ordering;
+            ui.horizontal(|ui| {
+                ui.label("Columns:");
+                egui::ComboBox::from_id_salt("column_order")
+                    .selected_text(ordering.columns.label())
+                    .show_ui(ui, |ui| {
+                        for order in ColumnOrder::ALL {
+                            ui.selectable_value(&mut ordering.columns, order, order.label());
+                        }
+                    });
+                ui.label("Rows:");
+                egui::ComboBox::from_id_salt("row_order")
+                    .selected_text(ordering.rows.label())
+                    .show_ui(ui, |ui| {
+                        for order in RowOrder::ALL {
+                            ui.selectable_value(&mut ordering.rows, order, order.label());
+                        }
+                    });
+            });
+            if ordering.rows == RowOrder::Random {
+                ui.horizontal(|ui| {
+                    ui.label("Seed:");
+                    ui.add(egui::DragValue::new(&mut ordering.seed));
+                });
+            }
+
+            if ui.button("Compare orderings").clicked() {
+                self.ordering_report = Some((
+                    self.config.algorithm,
+                    self.n,
+                    OrderingComparison::new(&self.config, self.n, &self.constraints),
+                ));
+            }
+            if let Some((_, _, comparison)) = self
+                .ordering_report
+                .as_ref()
+                .filter(|(algorithm, n, _)| *algorithm == self.config.algorithm && *n == self.n)
+            {
+                let report = &comparison.report;
+                if !comparison.is_finished() {
+                    ui.label(format!(
+                        "Comparing ordering {} of {} ({} steps)...",
+                        report.len() + 1,
+                        OrderingComparison::TOTAL,
+                        comparison.current_steps()
+                    ));
+                }
+                egui::Grid::new("ordering_report")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Columns");
+                        ui.label("Rows");
+                        ui.label("Nodes to 1st solution");
+                        ui.end_row();
+                        for (ordering, nodes) in report {
+                            ui.label(ordering.columns.label());
+                            ui.label(ordering.rows.label());
+                            ui.label(match nodes {
+                                Some(nodes) => nodes.to_string(),
+                                None => format!("> {}", solver::COMPARE_STEP_BUDGET),
+                            });
+                            ui.end_row();
+                        }
+                    });
+            }
+        }
+        self.apply_config(before);
+    }
+
+    fn open_file(&mut self, purpose: OpenPurpose, kinds: &[&file_io::FileKind]) {
+        self.pending_open = Some((purpose, file_io::open(kinds)));
+    }
+
+    /// Pick up a file once its dialog has produced one.
+    fn poll_open_file(&mut self) {
+        let Some((purpose, rx)) = &self.pending_open else {
+            return;
+        };
+        let purpose = *purpose;
+        let file = match rx.try_recv() {
+            Ok(file) => file,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                // Dialog cancelled
+                self.pending_open = None;
+                return;
+            }
+        };
+        self.pending_open = None;
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                self.status = Some(e);
+                return;
+            }
+        };
+        match purpose {
+            OpenPurpose::SatModel => {
+                match cnf::decode_model(self.n, &file.text(), &self.constraints) {
+                    Ok(board) => {
+                        self.replay = None;
+                        self.solver =
+                            Box::new(SolutionBrowser::new(self.n, &self.constraints, vec![board]));
+                        self.solver.step();
+                        self.paused = true;
+                        self.auto_play = false;
+                        self.status = Some(format!("{}: model is a valid solution", file.name));
+                    }
+                    Err(e) => self.status = Some(format!("{}: {}", file.name, e)),
+                }
+            }
+            OpenPurpose::Mask => match Constraints::from_mask(&file.text()) {
+                Ok((n, constraints)) => {
+                    self.n = n;
+                    self.n_input = n.to_string();
+                    self.constraints = Constraints {
+                        topology: self.constraints.topology,
+                        piece: self.constraints.piece.clone(),
+                        goal: self.constraints.goal,
+                        ..constraints
+                    };
+                    self.reset_solver();
+                    self.paused = true;
+                    self.auto_play = false;
+                    self.status = Some(format!(
+                        "{}: loaded {}x{} mask",
+                        file.name,
+                        n,
+                        self.constraints.cols(n)
+                    ));
+                }
+                Err(e) => self.status = Some(format!("{}: {}", file.name, e)),
+            },
+            OpenPurpose::Solutions => self.import_solutions(file),
+            OpenPurpose::Trace => match trace::parse(&file.text(), &self.constraints) {
+                Ok(loaded) => {
+                    self.status = Some(format!(
+                        "{}: {} steps of {}",
+                        file.name, loaded.trace.steps, loaded.description
+                    ));
+                    self.n = loaded.n;
+                    self.n_input = loaded.n.to_string();
+                    self.constraints = loaded.constraints.clone();
+                    self.replay = Some(loaded);
+                    self.reset_solver();
+                    self.paused = true;
+                    self.auto_play = false;
+                }
+                Err(e) => self.status = Some(format!("{}: {}", file.name, e)),
+            },
+            OpenPurpose::Store => self.open_store(file),
+        }
+    }
+
+    /// Browse the solutions of a packed store, so the exporters can convert
+    /// them. Only the first `MAX_BROWSED` are loaded.
+    fn open_store(&mut self, file: OpenedFile) {
+        const MAX_BROWSED: u64 = 100_000;
+        let loaded =
+            store::StoreReader::new(std::io::Cursor::new(file.bytes)).and_then(|mut store| {
+                let n = store.header.n;
+                let boards = (0..store.len().min(MAX_BROWSED))
+                    .map(|i| {
+                        let rows = store.get(i)?;
+                        let mut board = vec![vec![0; n]; n];
+                        for (c, &r) in rows.iter().enumerate() {
+                            board[r][c] = 1;
+                        }
+                        Ok(board)
+                    })
+                    .collect::<Result<Vec<Board>, String>>()?;
+                Ok((store.header, boards))
+            });
+        let (header, boards) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                self.status = Some(format!("{}: {}", file.name, e));
+                return;
+            }
+        };
+        self.status = Some(if header.count > MAX_BROWSED {
+            format!(
+                "{}: showing the first {} of {} solutions",
+                file.name, MAX_BROWSED, header.count
+            )
+        } else {
+            format!("{}: {} solutions", file.name, header.count)
+        });
+        self.n = header.n;
+        self.n_input = header.n.to_string();
+        self.constraints = header.constraints();
+        self.replay = None;
+        self.reset_solver();
+        self.solver = Box::new(SolutionBrowser::new(self.n, &self.constraints, boards));
+        self.solver.step();
+        self.paused = true;
+        self.auto_play = false;
     }
 
-    fn is_safe(&self, row: usize, col: usize) -> bool {
-        for i in 0..col {
-            if self.board[row][i] == 1 {
-                return false;
+    /// The listed solutions as a packed store.
+    fn save_store(&mut self) {
+        let written = store::storable(self.n, &self.constraints).and_then(|piece| {
+            let mut flags = 0;
+            if self.only_unique {
+                flags |= store::UNIQUE;
+            }
+            if self.constraints.topology == Topology::Toroidal {
+                flags |= store::TOROIDAL;
             }
+            let log = self.solver.solutions();
+            let out = std::io::Cursor::new(Vec::new());
+            let mut writer = store::StoreWriter::new(out, self.n, piece, flags)?;
+            for i in log.listed(self.only_unique) {
+                let rows: Vec<usize> = log.placements[i].iter().map(|&(r, _)| r).collect();
+                writer.push(&rows)?;
+            }
+            let complete = self.solver.is_finished()
+                && self.config.algorithm.is_complete()
+                && self.constraints.is_empty();
+            Ok(writer.finish(complete)?.into_inner())
+        });
+        let file_name = format!("nqueens_{}.nqps", self.n);
+        if let Err(e) =
+            written.and_then(|bytes| file_io::save_bytes(&file_name, &file_io::STORE, &bytes))
+        {
+            self.status = Some(e);
         }
-        for (i, j) in (0..row).rev().zip((0..col).rev()) {
-            if self.board[i][j] == 1 {
-                return false;
+    }
+
+    /// Check the solutions of an exported file and browse the valid ones as
+    /// if the search had just found them.
+    fn import_solutions(&mut self, file: OpenedFile) {
+        let imported = match import::parse(&file.text(), self.n, &self.constraints) {
+            Ok(imported) => imported,
+            Err(e) => {
+                self.status = Some(format!("{}: {}", file.name, e));
+                return;
             }
+        };
+        self.status = Some(imported.summary(&file.name));
+        self.n = imported.n;
+        self.n_input = imported.n.to_string();
+        self.constraints = imported.constraints;
+        self.replay = None;
+        self.reset_solver();
+        self.solver = Box::new(SolutionBrowser::new(
+            self.n,
+            &self.constraints,
+            imported.boards,
+        ));
+        self.solver.step();
+        self.paused = true;
+        self.auto_play = false;
+    }
+
+    fn save_file(&mut self, file_name: &str, kind: &file_io::FileKind, content: &str) {
+        if let Err(e) = file_io::save_text(file_name, kind, content) {
+            self.status = Some(e);
         }
-        for (i, j) in (row + 1..self.n).zip((0..col).rev()) {
-            if self.board[i][j] == 1 {
-                return false;
+    }
+
+    /// The listed solutions as CSV. With constraints set, a last "Mask" row
+    /// holds the board mask so the run can be reproduced.
+    fn export_csv(&mut self) {
+        let mask = (!self.constraints.is_empty()).then(|| self.constraints.to_mask(self.n));
+        match export::to_csv(self.solver.solutions(), self.only_unique, mask.as_deref()) {
+            Ok(text) => {
+                let file_name = format!("nqueens_{}.csv", self.n);
+                self.save_file(&file_name, &file_io::CSV, &text);
             }
+            Err(e) => self.status = Some(e),
         }
-        true
     }
 
-    fn save_solution(&mut self) {
-        let mut queen_rows = vec![0; self.n];
-        let mut parts = Vec::new();
-        for c in 0..self.n {
-            if let Some(r) = (0..self.n).find(|&r| self.board[r][c] == 1) {
-                queen_rows[c] = r;
-                let file = (b'a' + c as u8) as char;
-                let rank = r + 1;
-                parts.push(format!("{}{}", file, rank));
+    /// The board as shown, in the current theme.
+    fn export_image(&mut self, png: bool) {
+        let board = match &self.game {
+            Some(game) => game.board(),
+            None => self.solver.board(),
+        };
+        let view = render::BoardView {
+            theme: &self.theme,
+            piece: &self.constraints.piece,
+            fixed: &self.constraints.fixed,
+            blocked: &self.constraints.blocked,
+            labels: true,
+        };
+        let scene = render::Scene::single(board, &view, 64.0);
+        let result = if png {
+            scene.to_png().and_then(|bytes| {
+                let file_name = format!("nqueens_{}.png", self.n);
+                file_io::save_bytes(&file_name, &file_io::PNG, &bytes)
+            })
+        } else {
+            let file_name = format!("nqueens_{}.svg", self.n);
+            file_io::save_text(&file_name, &file_io::SVG, &scene.to_svg())
+        };
+        if let Err(e) = result {
+            self.status = Some(e);
+        }
+    }
+
+    /// Every listed solution on one sheet, in the current theme.
+    fn export_gallery(&mut self, kind: &file_io::FileKind) {
+        let scene = render::contact_sheet(
+            self.n,
+            &self.constraints,
+            self.solver.solutions(),
+            self.only_unique,
+            &self.theme,
+            24.0,
+            self.gallery_columns,
+        );
+        let file_name = format!("nqueens_{}_gallery.{}", self.n, kind.extension);
+        let bytes = match kind.extension {
+            "svg" => Ok(scene.to_svg().into_bytes()),
+            "pdf" => scene.to_pdf(),
+            _ => scene.to_png(),
+        };
+        if let Err(e) = bytes.and_then(|bytes| file_io::save_bytes(&file_name, kind, &bytes)) {
+            self.status = Some(e);
+        }
+    }
+
+    /// The board as shown, or every listed solution, as a LaTeX figure with
+    /// threats shaded when they are on screen.
+    fn export_latex(&mut self, all: bool) {
+        let boards = if all {
+            let size = (self.n, self.constraints.cols(self.n));
+            self.solver.solutions().captioned(self.only_unique, size)
+        } else {
+            let board = match &self.game {
+                Some(game) => game.board(),
+                None => self.solver.board(),
+            };
+            vec![(latex::notation(board), board.clone())]
+        };
+        let options = latex::Options {
+            style: self.latex_style,
+            threats: self.show_threats,
+            caption: &self.latex_caption,
+        };
+        match latex::figure(&self.constraints, &boards, &options) {
+            Ok(text) => {
+                let file_name = format!("nqueens_{}.tex", self.n);
+                self.save_file(&file_name, &file_io::TEX, &text);
             }
+            Err(e) => self.status = Some(e),
+        }
+    }
+
+    /// The current search rerun offscreen from the start, as an animation of
+    /// the chosen steps in the current theme.
+    fn export_animation(&mut self, kind: &file_io::FileKind) {
+        let mut search = self.config.build(self.n, &self.constraints);
+        let boards = animation::record(search.as_mut(), self.record_from, self.record_frames);
+        let view = render::BoardView {
+            theme: &self.theme,
+            piece: &self.constraints.piece,
+            fixed: &self.constraints.fixed,
+            blocked: &self.constraints.blocked,
+            labels: true,
+        };
+        let bytes = if kind.extension == "gif" {
+            animation::to_gif(&boards, &view, 32.0, self.record_delay)
+        } else {
+            animation::to_apng(&boards, &view, 32.0, self.record_delay)
+        };
+        let file_name = format!("nqueens_{}_search.{}", self.n, kind.extension);
+        if let Err(e) = bytes.and_then(|bytes| file_io::save_bytes(&file_name, kind, &bytes)) {
+            self.status = Some(e);
         }
+    }
 
-        let sol_str = parts.join(", ");
-        if !self.is_new_unique(&queen_rows) {
-            // Already seen a variant of this
-            self.last_solution_board = Some(self.board.clone());
-            self.unique_solutions.push(queen_rows); // We still store it to mark as non-unique if needed, but usually we just want the list of strings
-            self.solutions.push(format!("(Sym) {}", sol_str));
+    /// Solutions with their run metadata; NDJSON puts one per line.
+    fn export_json(&mut self, lines: bool) {
+        let run = export::RunInfo::new(self.n, &self.constraints, self.solver.as_ref());
+        let records = export::records(
+            self.n,
+            &self.constraints,
+            self.solver.solutions(),
+            self.only_unique,
+        );
+        let (kind, text) = if lines {
+            (&file_io::NDJSON, export::to_ndjson(&run, &records))
         } else {
-            self.last_solution_board = Some(self.board.clone());
-            self.unique_solutions.push(queen_rows);
-            self.solutions.push(sol_str);
+            (&file_io::JSON, export::to_json(&run, &records))
+        };
+        match text {
+            Ok(text) => {
+                let file_name = format!("nqueens_{}.{}", self.n, kind.extension);
+                self.save_file(&file_name, kind, &text);
+            }
+            Err(e) => self.status = Some(e),
+        }
+    }
+
+    /// The JSON export's data as a SQLite database. The dialog has already
+    /// asked before replacing a file, so the run is not added to an old one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_database(&mut self) {
+        let file_name = format!("nqueens_{}.sqlite", self.n);
+        let Some(path) = file_io::save_path(&file_name, &file_io::SQLITE) else {
+            return;
+        };
+        let run = export::RunInfo::new(self.n, &self.constraints, self.solver.as_ref());
+        let records = export::records(
+            self.n,
+            &self.constraints,
+            self.solver.solutions(),
+            self.only_unique,
+        );
+        let result = match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("could not replace {}: {}", path.display(), e))
+            }
+            _ => database::write(&path, &run, &records, self.constraints.topology),
+        };
+        if let Err(e) = result {
+            self.status = Some(e);
+        }
+    }
+
+    fn encoding_picker(&mut self, ui: &mut egui::Ui) {
+        let before = self.config.clone();
+        ui.horizontal(|ui| {
+            ui.label("Encoding:");
+            egui::ComboBox::from_id_salt("cnf_encoding")
+                .selected_text(self.config.encoding.label())
+                .show_ui(ui, |ui| {
+                    for encoding in cnf::Encoding::ALL {
+                        ui.selectable_value(&mut self.config.encoding, encoding, encoding.label());
+                    }
+                });
+        });
+        self.apply_config(before);
+    }
+
+    fn trace_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("Search trace")
+                .strong()
+                .color(self.theme.text_color),
+        );
+        ui.separator();
+        if let Some(replay) = &self.replay {
+            ui.label(format!("Replaying {}", replay.description));
+            if ui.button("Stop replay").clicked() {
+                self.replay = None;
+                self.reset_solver();
+                self.paused = true;
+            }
+            return;
+        }
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut self.record_trace, "Record")
+                .on_hover_text("Log every step from the start of the search")
+                .changed()
+            {
+                self.reset_solver();
+                self.paused = true;
+                self.auto_play = false;
+            }
+            let save = ui.add_enabled(self.record_trace, egui::Button::new("Save trace"));
+            if save.clicked() {
+                self.save_trace();
+            }
+            if ui.button("Load trace").clicked() {
+                self.open_file(OpenPurpose::Trace, &[&file_io::TRACE]);
+            }
+        });
+    }
+
+    /// Write the steps recorded so far.
+    fn save_trace(&mut self) {
+        let Some(recorder) = self.solver.recording() else {
+            return;
+        };
+        let text = trace::to_text(self.n, &self.constraints, &self.config, recorder.trace());
+        let truncated = recorder.is_truncated();
+        let file_name = format!("nqueens_{}.trace", self.n);
+        self.save_file(&file_name, &file_io::TRACE, &text);
+        if truncated {
+            self.status = Some(format!(
+                "The trace stops after {} events",
+                solver::MAX_EVENTS
+            ));
         }
     }
 
-    fn restore_last_solution(&mut self) {
-        if let Some(board) = &self.last_solution_board {
-            self.board = board.clone();
+    fn sat_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("SAT (DIMACS)")
+                .strong()
+                .color(self.theme.text_color),
+        );
+        ui.separator();
+        if !self.config.uses_encoding() {
+            // The SAT algorithms show the picker with their other options
+            self.encoding_picker(ui);
+        }
+        ui.horizontal(|ui| {
+            let export = ui
+                .add_enabled(
+                    self.constraints.fills_columns(self.n),
+                    egui::Button::new("Export CNF"),
+                )
+                .on_disabled_hover_text(
+                    "The CNF encoding needs n rook-like pieces on a square board",
+                );
+            if export.clicked() {
+                let encoding = self.config.encoding;
+                let dimacs = cnf::encode(self.n, encoding, &self.constraints).to_dimacs();
+                let file_name = format!("nqueens_{}_{}.cnf", self.n, encoding.key());
+                self.save_file(&file_name, &file_io::CNF, &dimacs);
+            }
+            if ui.button("Load SAT model").clicked() {
+                self.open_file(OpenPurpose::SatModel, &[&file_io::SAT_MODEL]);
+            }
+        });
+        if let Some(status) = &self.status {
+            ui.label(egui::RichText::new(status).color(self.theme.accent_color));
+        }
+    }
+
+    fn apply_config(&mut self, before: SolverConfig) {
+        if self.config != before {
+            self.replay = None;
+            self.reset_solver();
+            self.paused = true;
+            self.auto_play = false;
         }
     }
 }
@@ -326,7 +1345,8 @@ impl Default for EightQueensApp {
         Self {
             n_input: "8".to_owned(),
             n: 8,
-            solver: SolverWrapper::new(8),
+            config: SolverConfig::default(),
+            solver: SolverConfig::default().build(8, &Constraints::default()),
             speed: 5,
             paused: true,
             auto_play: false,
@@ -335,13 +1355,52 @@ impl Default for EightQueensApp {
             theme: Theme::default(),
             show_threats: false,
             only_unique: false,
+            gallery_columns: 8,
+            latex_style: latex::Style::Tikz,
+            latex_caption: String::new(),
+            record_from: 0,
+            record_frames: 200,
+            record_delay: 100,
+            record_trace: false,
+            replay: None,
+            constraints: Constraints::default(),
+            board_tool: BoardTool::View,
+            game: None,
+            challenge: None,
+            puzzle_generator: None,
+            challenge_difficulty: Difficulty::Easy,
+            leaderboard: Leaderboard::default(),
+            leaper_input: "1,3".to_owned(),
+            cube: None,
+            cube_n: 11,
+            cube_axis: Axis::Z,
+            cube_layer: 0,
+            ordering_report: None,
+            pending_open: None,
+            status: None,
         }
     }
 }
 
 impl eframe::App for EightQueensApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, Leaderboard::STORAGE_KEY, &self.leaderboard);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // --- Update Logic ---
+        self.poll_open_file();
+        for file in ctx.input(|i| i.raw.dropped_files.clone()) {
+            match file_io::dropped(&file) {
+                Ok(file) if file.name.ends_with(".nqps") => self.open_store(file),
+                Ok(file) => self.import_solutions(file),
+                Err(e) => self.status = Some(e),
+            }
+        }
+        self.update_challenge(ctx);
+        if self.pending_open.is_some() {
+            ctx.request_repaint();
+        }
         let _dt = ctx.input(|i| i.stable_dt);
 
         let delay_ms = if self.speed == 10 {
@@ -350,46 +1409,84 @@ impl eframe::App for EightQueensApp {
             (10 - self.speed) * 50
         };
 
-        if self.auto_play && !self.solver.finished {
+        if self.auto_play && !self.solver.is_finished() {
             if self.speed == 10 {
                 let start = Instant::now();
-                while start.elapsed() < Duration::from_millis(16) && !self.solver.finished {
-                    if self.solver.step() {
-                        if !self.finding_all {
-                            self.paused = true;
-                            self.auto_play = false;
-                            break;
-                        }
+                while start.elapsed() < Duration::from_millis(16) && !self.solver.is_finished() {
+                    if self.solver.step() && !self.finding_all {
+                        self.paused = true;
+                        self.auto_play = false;
+                        break;
                     }
                 }
-                if self.solver.finished {
+                if self.solver.is_finished() {
                     self.solver.restore_last_solution();
                 }
                 ctx.request_repaint();
             } else {
                 if self.last_update.elapsed().as_millis() as u64 >= delay_ms {
-                    if self.solver.step() {
-                        if !self.finding_all {
-                            self.paused = true;
-                            self.auto_play = false;
-                        }
+                    if self.solver.step() && !self.finding_all {
+                        self.paused = true;
+                        self.auto_play = false;
                     }
                     self.last_update = Instant::now();
                 }
                 ctx.request_repaint();
             }
-        } else if !self.paused && !self.solver.finished {
+        } else if !self.paused && !self.solver.is_finished() {
             if self.last_update.elapsed().as_millis() as u64 >= delay_ms {
-                if self.solver.step() {
-                    if !self.finding_all {
-                        self.paused = true;
-                    }
+                if self.solver.step() && !self.finding_all {
+                    self.paused = true;
                 }
                 self.last_update = Instant::now();
             }
             ctx.request_repaint();
         }
 
+        // The cube search runs on its own, a frame's worth at a time
+        if let Some(cube) = self.cube.as_mut().filter(|cube| !cube.is_finished()) {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(8) && !cube.is_finished() {
+                cube.step();
+            }
+            ctx.request_repaint();
+        }
+
+        // So does sizing up a maximum or domination board in play mode
+        if let Some(game) = self.game.as_mut().filter(|game| game.is_sizing()) {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(8) && game.is_sizing() {
+                game.size_up();
+            }
+            ctx.request_repaint();
+        }
+
+        if let Some(generator) = &mut self.puzzle_generator {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(8) && !generator.is_finished() {
+                generator.step();
+            }
+            ctx.request_repaint();
+        }
+        if let Some(generator) = self.puzzle_generator.take_if(|g| g.is_finished()) {
+            let difficulty = generator.difficulty();
+            if let Some(puzzle) = generator.into_puzzle() {
+                self.start_challenge(puzzle, difficulty);
+            }
+        }
+
+        if let Some((_, _, comparison)) = self
+            .ordering_report
+            .as_mut()
+            .filter(|(_, _, comparison)| !comparison.is_finished())
+        {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(8) && !comparison.is_finished() {
+                comparison.step();
+            }
+            ctx.request_repaint();
+        }
+
         // --- Custom Styles ---
         let mut style = (*ctx.style()).clone();
         style.visuals.widgets.noninteractive.bg_fill = self.theme.background;
@@ -419,13 +1516,36 @@ impl eframe::App for EightQueensApp {
                                 .color(self.theme.accent_color),
                         );
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            ui.label(
-                                egui::RichText::new(format!(
-                                    "Sols: {}",
-                                    self.solver.solutions.len()
-                                ))
-                                .strong(),
-                            );
+                            if let Some(game) = &mut self.game {
+                                if ui.button("Hint").clicked() {
+                                    game.request_hint();
+                                }
+                                let mut progress = if game.is_solved() {
+                                    "Solved!".to_owned()
+                                } else {
+                                    format!(
+                                        "Queens: {}/{}",
+                                        game.queens(),
+                                        game.target().map_or("?".to_owned(), |k| k.to_string())
+                                    )
+                                };
+                                if let Some(challenge) = &self.challenge {
+                                    progress = format!(
+                                        "{}   {:.1} s",
+                                        progress,
+                                        challenge.elapsed().as_secs_f64()
+                                    );
+                                }
+                                ui.label(egui::RichText::new(progress).strong());
+                            } else {
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "Sols: {}",
+                                        self.solver.solutions().solutions.len()
+                                    ))
+                                    .strong(),
+                                );
+                            }
                         });
                     });
                 });
@@ -438,10 +1558,10 @@ impl eframe::App for EightQueensApp {
                         // Row 1: Board Size & Speed
                         ui.horizontal(|ui| {
                             ui.label("Size:");
-                            if ui.button("-").clicked() && self.n > 4 {
+                            if ui.button("-").clicked() && self.n > *solver::BOARD_SIZES.start() {
                                 self.n -= 1;
                                 self.n_input = self.n.to_string();
-                                self.solver = SolverWrapper::new(self.n);
+                                self.reset_solver();
                                 self.paused = true;
                                 self.auto_play = false;
                             }
@@ -450,10 +1570,10 @@ impl eframe::App for EightQueensApp {
                                     .strong()
                                     .color(self.theme.accent_color),
                             );
-                            if ui.button("+").clicked() && self.n < 30 {
+                            if ui.button("+").clicked() && self.n < *solver::BOARD_SIZES.end() {
                                 self.n += 1;
                                 self.n_input = self.n.to_string();
-                                self.solver = SolverWrapper::new(self.n);
+                                self.reset_solver();
                                 self.paused = true;
                                 self.auto_play = false;
                             }
@@ -467,12 +1587,17 @@ impl eframe::App for EightQueensApp {
 
                         // Row 2: Options & Settings
                         ui.horizontal(|ui| {
+                            self.algorithm_picker(ui);
                             ui.checkbox(&mut self.only_unique, "Unique Only");
                             ui.checkbox(&mut self.show_threats, "Threats");
+                            self.board_tool_picker(ui);
 
                             ui.with_layout(
                                 egui::Layout::right_to_left(egui::Align::Center),
                                 |ui| {
+                                    if ui.button("⏱ Challenge").clicked() {
+                                        self.new_challenge();
+                                    }
                                     if ui.button("🎨 Theme").clicked() {
                                         let presets = Theme::presets();
                                         if let Some(idx) =
@@ -483,48 +1608,29 @@ impl eframe::App for EightQueensApp {
                                     }
 
                                     if ui.button("� Export").clicked() {
-                                        let display_solutions: Vec<String> = if self.only_unique {
-                                            self.solver
-                                                .solutions
-                                                .iter()
-                                                .filter(|s| !s.starts_with("(Sym)"))
-                                                .cloned()
-                                                .collect()
-                                        } else {
-                                            self.solver.solutions.clone()
-                                        };
-                                        #[cfg(target_arch = "wasm32")]
-                                        web_csv_export(&display_solutions, self.n);
-                                        #[cfg(not(target_arch = "wasm32"))]
-                                        if let Some(path) = rfd::FileDialog::new()
-                                            .add_filter("CSV", &["csv"])
-                                            .set_file_name(&format!("nqueens_{}.csv", self.n))
-                                            .save_file()
-                                        {
-                                            let mut wtr = csv::Writer::from_path(path).unwrap();
-                                            let _ =
-                                                wtr.write_record(&["Solution #", "Configuration"]);
-                                            for (i, sol) in display_solutions.iter().enumerate() {
-                                                let _ = wtr.write_record(&[
-                                                    (i + 1).to_string(),
-                                                    sol.clone(),
-                                                ]);
-                                            }
-                                            let _ = wtr.flush();
-                                        }
+                                        self.export_csv();
+                                    }
+                                    if ui.button("JSON").clicked() {
+                                        self.export_json(false);
                                     }
                                 },
                             );
                         });
 
+                        // Import and export errors
+                        if let Some(status) = &self.status {
+                            ui.add_space(4.0);
+                            ui.label(egui::RichText::new(status).color(self.theme.accent_color));
+                        }
+
                         ui.add_space(8.0);
 
                         // Row 3: Playback Controls
                         ui.horizontal_centered(|ui| {
                             let b_size = egui::vec2(ui.available_width() / 5.0 - 5.0, 45.0);
                             if ui.add_sized(b_size, egui::Button::new("▶")).clicked() {
-                                if self.solver.finished {
-                                    self.solver = SolverWrapper::new(self.n);
+                                if self.solver.is_finished() {
+                                    self.reset_solver();
                                 }
                                 self.paused = false;
                                 self.auto_play = false;
@@ -535,13 +1641,12 @@ impl eframe::App for EightQueensApp {
                                 self.paused = true;
                             }
                             if ui.add_sized(b_size, egui::Button::new("⏩")).clicked() {
-                                while !self.solver.finished {
+                                while !self.solver.is_finished() {
                                     if self.solver.step() {
                                         break;
                                     }
                                 }
                                 self.paused = true;
-                                self.solver.backtracking = true;
                             }
                             if ui.add_sized(b_size, egui::Button::new("⏭")).clicked() {
                                 self.auto_play = true;
@@ -550,10 +1655,10 @@ impl eframe::App for EightQueensApp {
                                 self.paused = false;
                             }
                             if ui.add_sized(b_size, egui::Button::new("◼")).clicked() {
-                                if !self.paused && !self.solver.finished {
+                                if !self.paused && !self.solver.is_finished() {
                                     self.paused = true;
                                 } else {
-                                    self.solver = SolverWrapper::new(self.n);
+                                    self.reset_solver();
                                     self.paused = true;
                                 }
                             }
@@ -591,9 +1696,9 @@ impl eframe::App for EightQueensApp {
                             );
                             if resp.changed() {
                                 if let Ok(new_n) = self.n_input.parse::<usize>() {
-                                    if new_n >= 4 && new_n <= 30 && new_n != self.n {
+                                    if solver::BOARD_SIZES.contains(&new_n) && new_n != self.n {
                                         self.n = new_n;
-                                        self.solver = SolverWrapper::new(self.n);
+                                        self.reset_solver();
                                         self.paused = true;
                                         self.auto_play = false;
                                     }
@@ -605,7 +1710,57 @@ impl eframe::App for EightQueensApp {
                             if should_update {
                                 self.n_input = self.n.to_string();
                             }
+                            // Columns; a square board follows the rows
+                            ui.label("×");
+                            let mut cols = self.constraints.cols(self.n);
+                            if ui
+                                .add(egui::DragValue::new(&mut cols).range(solver::BOARD_SIZES))
+                                .changed()
+                            {
+                                self.constraints.width = Some(cols);
+                                self.reset_solver();
+                                self.paused = true;
+                                self.auto_play = false;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Board:");
+                            let before = self.constraints.topology;
+                            egui::ComboBox::from_id_salt("topology")
+                                .selected_text(self.constraints.topology.label())
+                                .show_ui(ui, |ui| {
+                                    for topology in Topology::ALL {
+                                        ui.selectable_value(
+                                            &mut self.constraints.topology,
+                                            topology,
+                                            topology.label(),
+                                        );
+                                    }
+                                });
+                            if self.constraints.topology != before {
+                                self.reset_solver();
+                                self.paused = true;
+                                self.auto_play = false;
+                            }
+                        });
+                        self.piece_picker(ui);
+                        self.goal_picker(ui);
+                        self.fallback_note(ui);
+                        ui.horizontal(|ui| {
+                            ui.label("Algorithm:");
+                            self.algorithm_picker(ui);
                         });
+                        self.solver_options(ui);
+                        ui.add_space(15.0);
+                        self.constraints_panel(ui);
+                        ui.add_space(15.0);
+                        if self.game.is_some() {
+                            self.play_panel(ui);
+                            ui.add_space(15.0);
+                        }
+                        self.challenge_panel(ui);
+                        ui.add_space(15.0);
+                        self.cube_panel(ui);
                         ui.add_space(15.0);
                         ui.label(
                             egui::RichText::new("Controls")
@@ -616,8 +1771,8 @@ impl eframe::App for EightQueensApp {
                         ui.horizontal_wrapped(|ui| {
                             let btn_size = egui::vec2(50.0, 40.0);
                             if ui.add_sized(btn_size, egui::Button::new("▶")).clicked() {
-                                if self.solver.finished {
-                                    self.solver = SolverWrapper::new(self.n);
+                                if self.solver.is_finished() {
+                                    self.reset_solver();
                                 }
                                 self.paused = false;
                                 self.auto_play = false;
@@ -628,13 +1783,12 @@ impl eframe::App for EightQueensApp {
                                 self.paused = true;
                             }
                             if ui.add_sized(btn_size, egui::Button::new("⏩")).clicked() {
-                                while !self.solver.finished {
+                                while !self.solver.is_finished() {
                                     if self.solver.step() {
                                         break;
                                     }
                                 }
                                 self.paused = true;
-                                self.solver.backtracking = true;
                             }
                             if ui.add_sized(btn_size, egui::Button::new("⏭")).clicked() {
                                 self.auto_play = true;
@@ -643,10 +1797,10 @@ impl eframe::App for EightQueensApp {
                                 self.paused = false;
                             }
                             if ui.add_sized(btn_size, egui::Button::new("◼")).clicked() {
-                                if !self.paused && !self.solver.finished {
+                                if !self.paused && !self.solver.is_finished() {
                                     self.paused = true;
                                 } else {
-                                    self.solver = SolverWrapper::new(self.n);
+                                    self.reset_solver();
                                     self.paused = true;
                                 }
                             }
@@ -675,16 +1829,7 @@ impl eframe::App for EightQueensApp {
                             });
 
                         ui.add_space(20.0);
-                        let display_solutions: Vec<String> = if self.only_unique {
-                            self.solver
-                                .solutions
-                                .iter()
-                                .filter(|s| !s.starts_with("(Sym)"))
-                                .cloned()
-                                .collect()
-                        } else {
-                            self.solver.solutions.clone()
-                        };
+                        let display_solutions = self.solver.solutions().display(self.only_unique);
 
                         ui.label(
                             egui::RichText::new(format!(
@@ -694,27 +1839,141 @@ impl eframe::App for EightQueensApp {
                             .strong()
                             .size(16.0),
                         );
+                        let stats = self.solver.stats();
+                        ui.label(format!(
+                            "Steps: {}   Placements: {}   Backtracks: {}",
+                            stats.steps, stats.placements, stats.backtracks
+                        ));
+                        if let Some(nodes) = stats.nodes_to_first {
+                            ui.label(format!("Nodes to first solution: {}", nodes));
+                        }
+                        for (name, value) in &stats.extra {
+                            ui.label(format!("{}: {}", name, value));
+                        }
+                        if let Some(message) = self.outcome_message() {
+                            ui.label(egui::RichText::new(message).color(self.theme.accent_color));
+                        }
 
                         ui.add_space(10.0);
-                        if ui.button("Export to CSV").clicked() {
-                            #[cfg(target_arch = "wasm32")]
-                            web_csv_export(&display_solutions, self.n);
+                        ui.horizontal(|ui| {
+                            if ui.button("Export to CSV").clicked() {
+                                self.export_csv();
+                            }
+                            if ui.button("JSON").clicked() {
+                                self.export_json(false);
+                            }
+                            if ui.button("NDJSON").clicked() {
+                                self.export_json(true);
+                            }
                             #[cfg(not(target_arch = "wasm32"))]
-                            if let Some(path) = rfd::FileDialog::new()
-                                .add_filter("CSV", &["csv"])
-                                .set_file_name(&format!("nqueens_{}.csv", self.n))
-                                .save_file()
+                            if ui
+                                .button("SQLite")
+                                .on_hover_text(
+                                    "Solutions, symmetry classes and the run, for queries",
+                                )
+                                .clicked()
                             {
-                                let mut wtr = csv::Writer::from_path(path).unwrap();
-                                wtr.write_record(&["Solution #", "Configuration"]).unwrap();
-                                for (i, sol) in display_solutions.iter().enumerate() {
-                                    wtr.write_record(&[(i + 1).to_string(), sol.clone()])
-                                        .unwrap();
+                                self.export_database();
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Export image:");
+                            if ui.button("SVG").clicked() {
+                                self.export_image(false);
+                            }
+                            if ui.button("PNG").clicked() {
+                                self.export_image(true);
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Gallery:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.gallery_columns)
+                                    .range(1..=20)
+                                    .suffix(" cols"),
+                            );
+                            for kind in [&file_io::SVG, &file_io::PNG, &file_io::PDF] {
+                                if ui.button(kind.extension.to_uppercase()).clicked() {
+                                    self.export_gallery(kind);
                                 }
-                                wtr.flush().unwrap();
                             }
+                        })
+                        .response
+                        .on_hover_text("All listed solutions on one sheet");
+                        ui.horizontal(|ui| {
+                            ui.label("LaTeX:");
+                            for style in [latex::Style::Tikz, latex::Style::Chessboard] {
+                                ui.selectable_value(&mut self.latex_style, style, style.label());
+                            }
+                            if ui.button("Board").clicked() {
+                                self.export_latex(false);
+                            }
+                            if ui.button("All").clicked() {
+                                self.export_latex(true);
+                            }
+                        });
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.latex_caption)
+                                .hint_text("Figure caption (optional)"),
+                        )
+                        .on_hover_text("Threats are shaded when they are shown on the board");
+                        ui.horizontal(|ui| {
+                            ui.label("Record:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.record_from).prefix("from step "),
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut self.record_frames)
+                                    .range(1..=animation::MAX_FRAMES)
+                                    .suffix(" frames"),
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut self.record_delay)
+                                    .range(10..=5000)
+                                    .suffix(" ms"),
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            for kind in [&file_io::GIF, &file_io::APNG] {
+                                if ui
+                                    .button(format!("Save {}", kind.name))
+                                    .on_hover_text("Reruns the search from the start offscreen")
+                                    .clicked()
+                                {
+                                    self.export_animation(kind);
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Packed store:");
+                            if ui
+                                .button("Save")
+                                .on_hover_text("Listed solutions in a few bits each")
+                                .clicked()
+                            {
+                                self.save_store();
+                            }
+                            if ui.button("Open").clicked() {
+                                self.open_file(OpenPurpose::Store, &[&file_io::STORE]);
+                            }
+                        });
+                        if ui
+                            .button("Import solutions")
+                            .on_hover_text("CSV, JSON or NDJSON; files can also be dropped here")
+                            .clicked()
+                        {
+                            self.open_file(
+                                OpenPurpose::Solutions,
+                                &[&file_io::CSV, &file_io::JSON, &file_io::NDJSON],
+                            );
                         }
 
+                        ui.add_space(10.0);
+                        self.trace_panel(ui);
+
+                        ui.add_space(10.0);
+                        self.sat_panel(ui);
+
                         ui.add_space(10.0);
                         ui.label(
                             egui::RichText::new("Solutions History")
@@ -743,12 +2002,28 @@ impl eframe::App for EightQueensApp {
             .show(ctx, |ui| {
                 let available_rect = ui.available_rect_before_wrap();
                 let margin = if is_mobile { 10.0 } else { 60.0 };
-                let size = (available_rect.height() - margin)
-                    .min(available_rect.width() - margin)
+                let cols = self.constraints.cols(self.n);
+                let cell_size = ((available_rect.height() - margin) / self.n as f32)
+                    .min((available_rect.width() - margin) / cols as f32)
                     .max(0.0);
                 let center = available_rect.center();
 
-                let board_rect = egui::Rect::from_center_size(center, egui::vec2(size, size));
+                let board_rect = egui::Rect::from_center_size(
+                    center,
+                    egui::vec2(cols as f32 * cell_size, self.n as f32 * cell_size),
+                );
+
+                let response = ui.allocate_rect(board_rect, egui::Sense::click());
+                if let Some(pos) = response
+                    .interact_pointer_pos()
+                    .filter(|_| response.clicked())
+                {
+                    let col = ((pos.x - board_rect.min.x) / cell_size) as usize;
+                    let row = ((pos.y - board_rect.min.y) / cell_size) as usize;
+                    if row < self.n && col < cols {
+                        self.click_cell(row, col);
+                    }
+                }
 
                 // Draw background shadow/border
                 ui.painter().rect_filled(
@@ -757,12 +2032,21 @@ impl eframe::App for EightQueensApp {
                     self.theme.text_color.linear_multiply(0.2), // Subtle shadow
                 );
 
-                let cell_size = size / self.n as f32;
                 let painter = ui.painter();
+                let (board, last_placed, domains) = match &self.game {
+                    Some(game) => (game.board(), None, None),
+                    None => (
+                        self.solver.board(),
+                        self.solver.last_placed(),
+                        self.solver.domains(),
+                    ),
+                };
+                let hint = self.game.as_ref().and_then(|game| game.hint?.ok());
+                let dominating = matches!(self.constraints.goal, Goal::Domination { .. });
 
                 // Draw Board
                 for row in 0..self.n {
-                    for col in 0..self.n {
+                    for col in 0..cols {
                         let x = board_rect.min.x + col as f32 * cell_size;
                         let y = board_rect.min.y + row as f32 * cell_size;
                         let cell_rect = egui::Rect::from_min_size(
@@ -778,66 +2062,121 @@ impl eframe::App for EightQueensApp {
 
                         painter.rect_filled(cell_rect, 0.0, color);
 
-                        if self.show_threats {
-                            // Logic: highlight if share row, col, or diag with ANY queen
-                            let mut threatened = false;
-                            for r in 0..self.n {
-                                for c in 0..self.n {
-                                    if self.solver.board[r][c] == 1 {
-                                        // Ignore current square being queen itself for threat?
-                                        // Usually threatened means where you can't place.
-                                        if r == row
-                                            || c == col
-                                            || (r as i32 - row as i32).abs()
-                                                == (c as i32 - col as i32).abs()
-                                        {
-                                            if r != row || c != col {
-                                                threatened = true;
-                                                break;
-                                            }
-                                        }
-                                    }
-                                }
-                                if threatened {
-                                    break;
-                                }
-                            }
-
-                            if threatened {
+                        // Grey out rows pruned from a column's domain
+                        if let Some(domains) = domains {
+                            if board[row][col] == 0 && !domains[col][row] {
                                 painter.rect_filled(
-                                    cell_rect.shrink(2.0),
-                                    2.0,
-                                    egui::Color32::from_rgba_unmultiplied(255, 0, 0, 40),
+                                    cell_rect,
+                                    0.0,
+                                    egui::Color32::from_black_alpha(110),
                                 );
                             }
                         }
 
+                        if self.show_threats
+                            && solver::is_threatened(board, row, col, &self.constraints)
+                        {
+                            painter.rect_filled(
+                                cell_rect.shrink(2.0),
+                                2.0,
+                                egui::Color32::from_rgba_unmultiplied(255, 0, 0, 40),
+                            );
+                        }
+
+                        // Squares a domination set still has to reach
+                        if dominating
+                            && board[row][col] == 0
+                            && !self.constraints.blocked.contains(&(row, col))
+                            && !solver::is_threatened(board, row, col, &self.constraints)
+                        {
+                            painter.circle_stroke(
+                                cell_rect.center(),
+                                cell_size * 0.15,
+                                egui::Stroke::new(2.0, self.theme.accent_color),
+                            );
+                        }
+
+                        if self.constraints.blocked.contains(&(row, col)) {
+                            painter.rect_filled(
+                                cell_rect,
+                                0.0,
+                                egui::Color32::from_black_alpha(170),
+                            );
+                            let stroke =
+                                egui::Stroke::new(2.0, self.theme.text_color.linear_multiply(0.5));
+                            let cross = cell_rect.shrink(cell_size * 0.25);
+                            painter.line_segment([cross.left_top(), cross.right_bottom()], stroke);
+                            painter.line_segment([cross.right_top(), cross.left_bottom()], stroke);
+                        }
+
+                        if self.constraints.fixed.contains(&(row, col)) {
+                            painter.rect_stroke(
+                                cell_rect.shrink(3.0),
+                                4.0,
+                                egui::Stroke::new(3.0, self.theme.accent_color),
+                            );
+                        }
+
+                        // In play, conflicting queens show at once
+                        if self
+                            .game
+                            .as_ref()
+                            .is_some_and(|game| game.is_conflicted(row, col))
+                        {
+                            painter.rect_filled(
+                                cell_rect,
+                                0.0,
+                                egui::Color32::from_rgba_unmultiplied(255, 0, 0, 110),
+                            );
+                        }
+                        if hint == Some((row, col)) {
+                            painter.circle_stroke(
+                                cell_rect.center(),
+                                cell_size * 0.4,
+                                egui::Stroke::new(3.0, self.theme.accent_color),
+                            );
+                        }
+
                         // Highlight placement (optional, simple check)
-                        if self.solver.board[row][col] == 1 {
+                        if board[row][col] != 0 {
                             let center = cell_rect.center();
                             let font_size = cell_size * 0.7;
-                            let alpha = if row == self.solver.row && col == self.solver.col - 1 {
+                            let alpha = if last_placed == Some((row, col)) {
                                 ctx.animate_bool(egui::Id::new((row, col)), true)
                             } else {
                                 1.0
                             };
 
+                            let piece = &self.constraints.piece;
+                            let colour = if board[row][col] == 2 {
+                                self.theme.second_queen_color
+                            } else {
+                                self.theme.queen_color
+                            };
                             painter.text(
                                 center,
                                 egui::Align2::CENTER_CENTER,
-                                "♛",
+                                piece.glyph(),
                                 egui::FontId::proportional(font_size),
-                                self.theme.queen_color.linear_multiply(alpha),
+                                colour.linear_multiply(alpha),
                             );
+                            if let Some(badge) = piece.badge() {
+                                painter.text(
+                                    cell_rect.right_top() + egui::vec2(-0.18, 0.18) * cell_size,
+                                    egui::Align2::CENTER_CENTER,
+                                    badge,
+                                    egui::FontId::proportional(cell_size * 0.25),
+                                    colour.linear_multiply(alpha),
+                                );
+                            }
                         }
                     }
                 }
 
                 // Draw Coordinates
-                for i in 0..self.n {
-                    let font_id = egui::FontId::proportional(cell_size * 0.15);
+                let font_id = egui::FontId::proportional(cell_size * 0.15);
+                for i in 0..cols {
                     let col_char = (b'a' + i as u8) as char;
-                    let row_char = (i + 1).to_string();
 
                     // Files (bottom)
                     let x = board_rect.min.x + i as f32 * cell_size + cell_size / 2.0;
@@ -849,6 +2188,9 @@ impl eframe::App for EightQueensApp {
                         font_id.clone(),
                         self.theme.text_color,
                     );
+                }
+                for i in 0..self.n {
+                    let row_char = (i + 1).to_string();
 
                     // Ranks (left)
                     let x = board_rect.min.x - 10.0;
@@ -864,29 +2206,3 @@ impl eframe::App for EightQueensApp {
             });
     }
 }
-
-#[cfg(target_arch = "wasm32")]
-fn web_csv_export(solutions: &[String], n: usize) {
-    use wasm_bindgen::JsCast;
-    use wasm_bindgen::JsValue;
-    let mut csv_content = String::from("Solution #,Configuration\n");
-    for (i, sol) in solutions.iter().enumerate() {
-        csv_content.push_str(&format!("{},\"{}\"\n", i + 1, sol));
-    }
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
-    let parts = js_sys::Array::of1(&JsValue::from_str(&csv_content));
-    let mut blob_options = web_sys::BlobPropertyBag::new();
-    blob_options.set_type("text/csv");
-    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &blob_options).unwrap();
-    let url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
-    let a = document
-        .create_element("a")
-        .unwrap()
-        .dyn_into::<web_sys::HtmlAnchorElement>()
-        .unwrap();
-    a.set_href(&url);
-    a.set_download(&format!("nqueens_{}.csv", n));
-    a.click();
-    web_sys::Url::revoke_object_url(&url).unwrap();
-}
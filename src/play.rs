@@ -0,0 +1,316 @@
+//! Manual play: the user places the queens, the solvers only give hints.
+
+use crate::solver::{
+    self, get_variants, Algorithm, Board, Constraints, Goal, Solver, SolverConfig,
+};
+
+/// Search budget for one hint; past it the position counts as undecided.
+const HINT_STEP_BUDGET: u64 = 2_000_000;
+
+pub struct Game {
+    n: usize,
+    cols: usize,
+    // Pieces a finished board holds, if known
+    target: Option<usize>,
+    // Search for the largest or smallest placement, which sets `target`
+    // when it finishes, and the steps it has taken
+    sizing: Option<(Box<dyn Solver>, u64)>,
+    board: Board,
+    constraints: Constraints,
+    blocked: Vec<Vec<bool>>,
+    pub moves: u64,
+    pub hints: u64,
+    /// Outcome of the last hint: a square to play, or why there is none.
+    /// Cleared by the next move.
+    pub hint: Option<Result<(usize, usize), &'static str>>,
+}
+
+impl Game {
+    /// Start from the pre-placed queens; those stay put, blocked squares stay empty.
+    pub fn new(n: usize, constraints: &Constraints) -> Self {
+        let cols = constraints.cols(n);
+        let mut board = vec![vec![0; cols]; n];
+        for &(r, c) in &constraints.fixed {
+            board[r][c] = 1;
+        }
+        // When maximising or dominating the size takes a search from the
+        // given pieces, run by `size_up`
+        let (target, sizing) = match constraints.pieces(n) {
+            Some(k) => (Some(k), None),
+            None => (
+                None,
+                Some((SolverConfig::default().build(n, constraints), 0)),
+            ),
+        };
+        Self {
+            n,
+            cols,
+            target,
+            sizing,
+            board,
+            constraints: constraints.clone(),
+            blocked: constraints.blocked_grid(n),
+            moves: 0,
+            hints: 0,
+            hint: None,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn target(&self) -> Option<usize> {
+        self.target
+    }
+
+    /// Whether the search for the target is still running.
+    pub fn is_sizing(&self) -> bool {
+        self.sizing.is_some()
+    }
+
+    /// One step of the search for the target. Past the budget the target
+    /// stays unknown.
+    pub fn size_up(&mut self) {
+        let Some((search, steps)) = &mut self.sizing else {
+            return;
+        };
+        if !search.is_finished() && *steps < HINT_STEP_BUDGET {
+            *steps += 1;
+            search.step();
+            return;
+        }
+        if search.is_finished() {
+            self.target = search.solutions().placements.last().map(Vec::len);
+        }
+        self.sizing = None;
+    }
+
+    pub fn queens(&self) -> usize {
+        self.board
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell != 0)
+            .count()
+    }
+
+    /// Place or remove a queen; peaceable armies go empty, white, black.
+    /// Returns false if the square can't be changed.
+    pub fn toggle(&mut self, row: usize, col: usize) -> bool {
+        if self.blocked[row][col] || self.constraints.fixed.contains(&(row, col)) {
+            return false;
+        }
+        let colours = if self.constraints.goal == Goal::Peaceable {
+            3
+        } else {
+            2
+        };
+        self.board[row][col] = (self.board[row][col] + 1) % colours;
+        self.moves += 1;
+        self.hint = None;
+        true
+    }
+
+    /// A queen stands on (row, col) and another one attacks it, where the
+    /// goal forbids that.
+    pub fn is_conflicted(&self, row: usize, col: usize) -> bool {
+        let colour = self.board[row][col];
+        colour != 0
+            && (0..self.n)
+                .flat_map(|r| (0..self.cols).map(move |c| (r, c)))
+                .any(|(r, c)| {
+                    let other = self.board[r][c];
+                    other != 0
+                        && (r, c) != (row, col)
+                        && self.constraints.conflicts(colour, other)
+                        && self.constraints.attacks(self.n, (r, c), (row, col))
+                })
+    }
+
+    /// A free square no queen attacks or occupies; only domination cares.
+    fn is_uncovered(&self, row: usize, col: usize) -> bool {
+        self.board[row][col] == 0
+            && !self.blocked[row][col]
+            && !solver::is_threatened(&self.board, row, col, &self.constraints)
+    }
+
+    fn dominates(&self) -> bool {
+        !matches!(self.constraints.goal, Goal::Domination { .. })
+            || (0..self.n).all(|r| (0..self.cols).all(|c| !self.is_uncovered(r, c)))
+    }
+
+    fn has_conflicts(&self) -> bool {
+        (0..self.n).any(|r| (0..self.cols).any(|c| self.is_conflicted(r, c)))
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.target == Some(self.queens())
+            && !self.has_conflicts()
+            && self.dominates()
+            && self.armies_match()
+    }
+
+    /// Peaceable armies are the same size.
+    fn armies_match(&self) -> bool {
+        let black = self
+            .board
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell == 2)
+            .count();
+        self.constraints.goal != Goal::Peaceable || 2 * black == self.queens()
+    }
+
+    /// Ask the bitboard search for a completion of the current position and
+    /// suggest one of its queens.
+    pub fn request_hint(&mut self) {
+        self.hints += 1;
+        self.hint = Some(self.find_hint());
+    }
+
+    fn find_hint(&self) -> Result<(usize, usize), &'static str> {
+        if self.is_sizing() {
+            return Err("Still working out how many pieces solve the board");
+        }
+        if self.has_conflicts() {
+            return Err("Remove the conflicting queens first");
+        }
+        // A dominating board may need more pieces than the smallest one
+        if self.is_solved() {
+            return Err("The board is already full");
+        }
+        if self.constraints.goal == Goal::Peaceable {
+            return Err("No hints for peaceable armies");
+        }
+        let mut position = self.constraints.clone();
+        position.fixed = (0..self.n)
+            .flat_map(|r| (0..self.cols).map(move |c| (r, c)))
+            .filter(|&(r, c)| self.board[r][c] == 1)
+            .collect();
+        // Domination keeps its goal, independent or not: a smallest
+        // dominating superset. The others need `target` non-attacking pieces
+        let dominating = matches!(self.constraints.goal, Goal::Domination { .. });
+        if let Some(k) = self.target.filter(|_| !dominating) {
+            position.goal = Goal::Place(k);
+        }
+        // Pieces the bitboards can't handle fall back to another search
+        let config = SolverConfig {
+            algorithm: Algorithm::Bitboard,
+            ..SolverConfig::default()
+        };
+        let mut search = config.build(self.n, &position);
+        let mut steps = 0;
+        while !search.is_finished() && steps < HINT_STEP_BUDGET {
+            steps += 1;
+            if search.step() {
+                // The search has already lifted its last queen again
+                let completion = search.solutions().last_board.as_ref().unwrap();
+                return (0..self.cols)
+                    .flat_map(|c| (0..self.n).map(move |r| (r, c)))
+                    .find(|&(r, c)| completion[r][c] == 1 && self.board[r][c] == 0)
+                    .ok_or("The board is already full");
+            }
+        }
+        if search.is_finished() {
+            Err("No completion exists from this position")
+        } else {
+            Err("No completion found within the search budget")
+        }
+    }
+
+    /// Once solved, how the board relates to its fundamental solution: the
+    /// variant the default search finds first, i.e. the one listed under
+    /// "Unique Only".
+    pub fn verdict(&self) -> Option<String> {
+        if !self.is_solved() {
+            return None;
+        }
+        if self.constraints.goal == Goal::Peaceable {
+            return Some(format!(
+                "Solved! Two peaceable armies of {} queens",
+                self.queens() / 2
+            ));
+        }
+        let squares = solver::squares(&self.board);
+        let fundamental = get_variants(&squares, (self.n, self.cols), self.constraints.topology)
+            .into_iter()
+            .min_by_key(|v| solver::class_key(v, &vec![1; v.len()]))
+            .unwrap();
+        Some(if fundamental == squares {
+            format!(
+                "Solved! This is fundamental solution {}",
+                solver::notation(&fundamental)
+            )
+        } else {
+            format!(
+                "Solved! A rotation or reflection of fundamental solution {}",
+                solver::notation(&fundamental)
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sized(n: usize, constraints: &Constraints) -> Game {
+        let mut game = Game::new(n, constraints);
+        while game.is_sizing() {
+            game.size_up();
+        }
+        game
+    }
+
+    #[test]
+    fn peaceable_conflicts_are_between_colours() {
+        let constraints = Constraints {
+            goal: Goal::Peaceable,
+            ..Constraints::default()
+        };
+        let mut game = sized(5, &constraints);
+        // White on a1 and b2 share a diagonal, which one army may do
+        game.toggle(0, 0);
+        game.toggle(1, 1);
+        assert!(!game.is_conflicted(0, 0) && !game.is_conflicted(1, 1));
+        // A black queen on c3 is attacked by both, and attacks them back
+        game.toggle(2, 2);
+        game.toggle(2, 2);
+        assert!(game.is_conflicted(2, 2));
+        assert!(game.is_conflicted(0, 0) && game.is_conflicted(1, 1));
+    }
+
+    #[test]
+    fn independent_domination_hints_lead_to_a_dominating_board() {
+        let constraints = Constraints {
+            goal: Goal::Domination { independent: true },
+            ..Constraints::default()
+        };
+        let n = 5;
+        assert_eq!(sized(n, &constraints).target(), Some(3));
+        let mut completed = 0;
+        for start in (0..n).flat_map(|r| (0..n).map(move |c| (r, c))) {
+            let mut game = sized(n, &constraints);
+            game.toggle(start.0, start.1);
+            while game.queens() < n * n {
+                game.request_hint();
+                match game.hint {
+                    Some(Ok((r, c))) => {
+                        game.toggle(r, c);
+                    }
+                    _ => break,
+                }
+            }
+            // Following the hints never leaves the board undominated
+            assert!(game.dominates(), "from {:?}: {:?}", start, game.board());
+            assert!(
+                !game.has_conflicts(),
+                "from {:?}: {:?}",
+                start,
+                game.board()
+            );
+            completed += usize::from(game.is_solved());
+        }
+        assert!(completed > 0);
+    }
+}
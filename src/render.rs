@@ -0,0 +1,692 @@
+//! Drawing boards without a window, for image exports. A picture is first
+//! built as a list of shapes, then written out as SVG or rasterised to PNG
+//! with the fonts egui ships, so both look like the board on screen.
+
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use eframe::egui::{Color32, FontDefinitions, FontFamily};
+
+use crate::solver::{Board, Constraints, Piece, SolutionLog};
+use crate::Theme;
+
+/// How text sits horizontally on its anchor point; vertically it is always
+/// centred.
+#[derive(Clone, Copy)]
+pub enum Align {
+    Center,
+    Right,
+}
+
+enum Shape {
+    Rect {
+        min: (f32, f32),
+        size: (f32, f32),
+        fill: Color32,
+    },
+    Outline {
+        min: (f32, f32),
+        size: (f32, f32),
+        width: f32,
+        colour: Color32,
+    },
+    Line {
+        from: (f32, f32),
+        to: (f32, f32),
+        width: f32,
+        colour: Color32,
+    },
+    Text {
+        at: (f32, f32),
+        size: f32,
+        text: String,
+        colour: Color32,
+        align: Align,
+    },
+}
+
+/// What to draw on a board besides its pieces.
+#[derive(Clone, Copy)]
+pub struct BoardView<'a> {
+    pub theme: &'a Theme,
+    pub piece: &'a Piece,
+    pub fixed: &'a [(usize, usize)],
+    pub blocked: &'a [(usize, usize)],
+    /// Files below the board and ranks to its left.
+    pub labels: bool,
+}
+
+/// Every solution the history panel lists, as a contact sheet captioned with
+/// its number and notation.
+pub fn contact_sheet(
+    n: usize,
+    constraints: &Constraints,
+    log: &SolutionLog,
+    only_unique: bool,
+    theme: &Theme,
+    cell: f32,
+    columns: usize,
+) -> Scene {
+    let size = (n, constraints.cols(n));
+    let boards = log.captioned(only_unique, size);
+    let title = format!(
+        "{} on {}×{}: {} {}solution(s)",
+        constraints.piece.label(),
+        size.0,
+        size.1,
+        boards.len(),
+        if only_unique { "unique " } else { "" }
+    );
+    let view = BoardView {
+        theme,
+        piece: &constraints.piece,
+        fixed: &constraints.fixed,
+        blocked: &constraints.blocked,
+        labels: false,
+    };
+    Scene::gallery(&title, &boards, &view, cell, columns)
+}
+
+/// A picture of `width` by `height` pixels.
+pub struct Scene {
+    pub width: f32,
+    pub height: f32,
+    background: Color32,
+    shapes: Vec<Shape>,
+}
+
+impl Scene {
+    pub fn new(width: f32, height: f32, background: Color32) -> Self {
+        Self {
+            width,
+            height,
+            background,
+            shapes: Vec::new(),
+        }
+    }
+
+    /// A picture of just one board with squares of `cell` pixels.
+    pub fn single(board: &Board, view: &BoardView, cell: f32) -> Self {
+        let margin = if view.labels { cell * 0.6 } else { cell * 0.2 };
+        let (rows, cols) = (board.len(), board.first().map_or(0, Vec::len));
+        let mut scene = Self::new(
+            cols as f32 * cell + 2.0 * margin,
+            rows as f32 * cell + 2.0 * margin,
+            view.theme.background,
+        );
+        scene.board((margin, margin), cell, board, view);
+        scene
+    }
+
+    /// A contact sheet: one small board per solution, `columns` to a row,
+    /// each captioned, under a title.
+    pub fn gallery(
+        title: &str,
+        boards: &[(String, Board)],
+        view: &BoardView,
+        cell: f32,
+        columns: usize,
+    ) -> Self {
+        let (rows, cols) = boards
+            .first()
+            .map_or((0, 0), |(_, b)| (b.len(), b.first().map_or(0, Vec::len)));
+        let columns = columns.clamp(1, boards.len().max(1));
+        let gap = cell;
+        let caption = (cell * 0.6).max(8.0);
+        let (tile_w, tile_h) = (cols as f32 * cell, rows as f32 * cell + caption * 1.8);
+        let header = caption * 3.0;
+        let lines = boards.len().div_ceil(columns);
+        let mut scene = Self::new(
+            gap + columns as f32 * (tile_w + gap),
+            header + lines as f32 * (tile_h + gap),
+            view.theme.background,
+        );
+        let title_size = caption * 1.5;
+        let title_at = (scene.width / 2.0, header / 2.0);
+        scene.text(
+            title_at,
+            title_size,
+            title,
+            view.theme.text_color,
+            Align::Center,
+        );
+
+        let view = BoardView {
+            labels: false,
+            ..*view
+        };
+        for (i, (text, board)) in boards.iter().enumerate() {
+            let x = gap + (i % columns) as f32 * (tile_w + gap);
+            let y = header + (i / columns) as f32 * (tile_h + gap);
+            scene.board((x, y), cell, board, &view);
+            // Shrink long captions to the width of the board
+            let size = caption.min(tile_w / (text.chars().count() as f32 * 0.55).max(1.0));
+            let at = (x + tile_w / 2.0, y + rows as f32 * cell + caption);
+            scene.text(at, size, text, view.theme.text_color, Align::Center);
+        }
+        scene
+    }
+
+    pub fn rect(&mut self, min: (f32, f32), size: (f32, f32), fill: Color32) {
+        self.shapes.push(Shape::Rect { min, size, fill });
+    }
+
+    pub fn text(&mut self, at: (f32, f32), size: f32, text: &str, colour: Color32, align: Align) {
+        self.shapes.push(Shape::Text {
+            at,
+            size,
+            text: text.to_owned(),
+            colour,
+            align,
+        });
+    }
+
+    /// Draw `board` with its top left corner at `min`, the way the central
+    /// panel does: row 0 at the top, pieces in the theme colours.
+    pub fn board(&mut self, min: (f32, f32), cell: f32, board: &Board, view: &BoardView) {
+        let theme = view.theme;
+        let at = |row: usize, col: usize| (min.0 + col as f32 * cell, min.1 + row as f32 * cell);
+        let centre = |row: usize, col: usize| {
+            let (x, y) = at(row, col);
+            (x + cell / 2.0, y + cell / 2.0)
+        };
+        for (row, cells) in board.iter().enumerate() {
+            for (col, &value) in cells.iter().enumerate() {
+                let colour = if (row + col) % 2 == 0 {
+                    theme.board_light
+                } else {
+                    theme.board_dark
+                };
+                self.rect(at(row, col), (cell, cell), colour);
+
+                if view.blocked.contains(&(row, col)) {
+                    self.rect(at(row, col), (cell, cell), Color32::from_black_alpha(170));
+                    let (x, y) = at(row, col);
+                    let (a, b) = (cell * 0.25, cell * 0.75);
+                    let colour = theme.text_color.linear_multiply(0.5);
+                    for (from, to) in [((a, a), (b, b)), ((b, a), (a, b))] {
+                        self.shapes.push(Shape::Line {
+                            from: (x + from.0, y + from.1),
+                            to: (x + to.0, y + to.1),
+                            width: 2.0,
+                            colour,
+                        });
+                    }
+                }
+                if view.fixed.contains(&(row, col)) {
+                    let (x, y) = at(row, col);
+                    self.shapes.push(Shape::Outline {
+                        min: (x + 3.0, y + 3.0),
+                        size: (cell - 6.0, cell - 6.0),
+                        width: 3.0,
+                        colour: theme.accent_color,
+                    });
+                }
+
+                if value != 0 {
+                    let colour = if value == 2 {
+                        theme.second_queen_color
+                    } else {
+                        theme.queen_color
+                    };
+                    let glyph = view.piece.glyph();
+                    self.text(centre(row, col), cell * 0.7, glyph, colour, Align::Center);
+                    if let Some(badge) = view.piece.badge() {
+                        let (x, y) = at(row, col);
+                        let badge_at = (x + cell * 0.82, y + cell * 0.18);
+                        self.text(badge_at, cell * 0.25, badge, colour, Align::Center);
+                    }
+                }
+            }
+        }
+
+        if view.labels {
+            let size = (cell * 0.22).max(8.0);
+            let rows = board.len();
+            for col in 0..board.first().map_or(0, Vec::len) {
+                let (x, _) = centre(0, col);
+                let y = min.1 + rows as f32 * cell + cell * 0.3;
+                let file = ((b'a' + col as u8) as char).to_string();
+                self.text((x, y), size, &file, theme.text_color, Align::Center);
+            }
+            for row in 0..rows {
+                let (_, y) = centre(row, 0);
+                let rank = (row + 1).to_string();
+                self.text(
+                    (min.0 - cell * 0.15, y),
+                    size,
+                    &rank,
+                    theme.text_color,
+                    Align::Right,
+                );
+            }
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = px(self.width),
+            h = px(self.height)
+        );
+        out.push_str(&format!(
+            "<rect width=\"100%\" height=\"100%\" {}/>\n",
+            svg_fill(self.background)
+        ));
+        for shape in &self.shapes {
+            let element = match shape {
+                Shape::Rect { min, size, fill } => format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+                    px(min.0),
+                    px(min.1),
+                    px(size.0),
+                    px(size.1),
+                    svg_fill(*fill)
+                ),
+                Shape::Outline {
+                    min,
+                    size,
+                    width,
+                    colour,
+                } => format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"none\" stroke-width=\"{}\" {}/>",
+                    px(min.0),
+                    px(min.1),
+                    px(size.0),
+                    px(size.1),
+                    px(*width),
+                    svg_stroke(*colour)
+                ),
+                Shape::Line {
+                    from,
+                    to,
+                    width,
+                    colour,
+                } => format!(
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke-width=\"{}\" {}/>",
+                    px(from.0),
+                    px(from.1),
+                    px(to.0),
+                    px(to.1),
+                    px(*width),
+                    svg_stroke(*colour)
+                ),
+                Shape::Text {
+                    at,
+                    size,
+                    text,
+                    colour,
+                    align,
+                } => {
+                    let anchor = match align {
+                        Align::Center => "middle",
+                        Align::Right => "end",
+                    };
+                    format!(
+                        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"sans-serif\" text-anchor=\"{}\" dominant-baseline=\"central\" {}>{}</text>",
+                        px(at.0),
+                        px(at.1),
+                        px(*size),
+                        anchor,
+                        svg_fill(*colour),
+                        escape(text)
+                    )
+                }
+            };
+            out.push_str(&element);
+            out.push('\n');
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        self.to_pixmap()?
+            .encode_png()
+            .map_err(|e| format!("could not encode PNG: {}", e))
+    }
+
+    /// A one-page PDF holding the rasterised scene, at 96 pixels per inch.
+    pub fn to_pdf(&self) -> Result<Vec<u8>, String> {
+        let (w, h, rgb) = self.to_rgb()?;
+        let image = miniz_oxide::deflate::compress_to_vec_zlib(&rgb, 6);
+        let (page_w, page_h) = (w as f32 * 0.75, h as f32 * 0.75);
+        let content = format!("q {} 0 0 {} 0 0 cm /Im0 Do Q\n", page_w, page_h);
+
+        let mut pdf: Vec<u8> = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        let mut object = |pdf: &mut Vec<u8>, body: &[u8]| {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n", offsets.len()).as_bytes());
+            pdf.extend(body);
+            pdf.extend(b"\nendobj\n");
+        };
+        object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+        object(&mut pdf, b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+        object(
+            &mut pdf,
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /XObject << /Im0 4 0 R >> >> /Contents 5 0 R >>",
+                page_w, page_h
+            )
+            .as_bytes(),
+        );
+        let mut stream = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>\nstream\n",
+            w,
+            h,
+            image.len()
+        )
+        .into_bytes();
+        stream.extend(&image);
+        stream.extend(b"\nendstream");
+        object(&mut pdf, &stream);
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content.as_bytes());
+        stream.extend(b"endstream");
+        object(&mut pdf, &stream);
+
+        let xref = pdf.len();
+        pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes());
+        for offset in &offsets {
+            pdf.extend(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                offsets.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        Ok(pdf)
+    }
+
+    /// Width, height and the RGB bytes of the rasterised scene, row by row.
+    pub fn to_rgb(&self) -> Result<(u32, u32, Vec<u8>), String> {
+        let pixmap = self.to_pixmap()?;
+        let rgb = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|p| {
+                let p = p.demultiply();
+                [p.red(), p.green(), p.blue()]
+            })
+            .collect();
+        Ok((pixmap.width(), pixmap.height(), rgb))
+    }
+
+    fn to_pixmap(&self) -> Result<tiny_skia::Pixmap, String> {
+        let (width, height) = (self.width.ceil() as u32, self.height.ceil() as u32);
+        let mut pixmap = tiny_skia::Pixmap::new(width, height)
+            .ok_or_else(|| format!("can't draw a {}x{} image", width, height))?;
+        pixmap.fill(skia_colour(self.background));
+        let fonts = Fonts::load();
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect { min, size, fill } => {
+                    if let Some(rect) = tiny_skia::Rect::from_xywh(min.0, min.1, size.0, size.1) {
+                        pixmap.fill_rect(
+                            rect,
+                            &paint(*fill),
+                            tiny_skia::Transform::identity(),
+                            None,
+                        );
+                    }
+                }
+                Shape::Outline {
+                    min,
+                    size,
+                    width,
+                    colour,
+                } => {
+                    if let Some(rect) = tiny_skia::Rect::from_xywh(min.0, min.1, size.0, size.1) {
+                        let path = tiny_skia::PathBuilder::from_rect(rect);
+                        stroke(&mut pixmap, &path, *width, *colour);
+                    }
+                }
+                Shape::Line {
+                    from,
+                    to,
+                    width,
+                    colour,
+                } => {
+                    let mut builder = tiny_skia::PathBuilder::new();
+                    builder.move_to(from.0, from.1);
+                    builder.line_to(to.0, to.1);
+                    if let Some(path) = builder.finish() {
+                        stroke(&mut pixmap, &path, *width, *colour);
+                    }
+                }
+                Shape::Text {
+                    at,
+                    size,
+                    text,
+                    colour,
+                    align,
+                } => fonts.draw(&mut pixmap, *at, *size, text, *colour, *align),
+            }
+        }
+        Ok(pixmap)
+    }
+}
+
+/// Coordinates rounded to hundredths of a pixel, so the SVG stays readable.
+fn px(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
+
+fn svg_colour(colour: Color32) -> (String, f32) {
+    let [r, g, b, a] = colour.to_srgba_unmultiplied();
+    (format!("#{:02x}{:02x}{:02x}", r, g, b), a as f32 / 255.0)
+}
+
+fn svg_fill(colour: Color32) -> String {
+    match svg_colour(colour) {
+        (hex, a) if a < 1.0 => format!("fill=\"{}\" fill-opacity=\"{:.3}\"", hex, a),
+        (hex, _) => format!("fill=\"{}\"", hex),
+    }
+}
+
+fn svg_stroke(colour: Color32) -> String {
+    match svg_colour(colour) {
+        (hex, a) if a < 1.0 => format!("stroke=\"{}\" stroke-opacity=\"{:.3}\"", hex, a),
+        (hex, _) => format!("stroke=\"{}\"", hex),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn skia_colour(colour: Color32) -> tiny_skia::Color {
+    let [r, g, b, a] = colour.to_srgba_unmultiplied();
+    tiny_skia::Color::from_rgba8(r, g, b, a)
+}
+
+fn paint(colour: Color32) -> tiny_skia::Paint<'static> {
+    let mut paint = tiny_skia::Paint::default();
+    paint.set_color(skia_colour(colour));
+    paint.anti_alias = true;
+    paint
+}
+
+fn stroke(pixmap: &mut tiny_skia::Pixmap, path: &tiny_skia::Path, width: f32, colour: Color32) {
+    let stroke = tiny_skia::Stroke {
+        width,
+        ..tiny_skia::Stroke::default()
+    };
+    pixmap.stroke_path(
+        path,
+        &paint(colour),
+        &stroke,
+        tiny_skia::Transform::identity(),
+        None,
+    );
+}
+
+/// egui's proportional fonts, in the order egui falls back through them.
+struct Fonts(Vec<FontArc>);
+
+impl Fonts {
+    fn load() -> Self {
+        let definitions = FontDefinitions::default();
+        let names = definitions
+            .families
+            .get(&FontFamily::Proportional)
+            .cloned()
+            .unwrap_or_default();
+        Self(
+            names
+                .iter()
+                .filter_map(|name| definitions.font_data.get(name))
+                .filter_map(|data| FontArc::try_from_vec(data.font.to_vec()).ok())
+                .collect(),
+        )
+    }
+
+    /// The first font that has `c`.
+    fn font_for(&self, c: char) -> Option<&FontArc> {
+        self.0
+            .iter()
+            .find(|font| font.glyph_id(c).0 != 0)
+            .or(self.0.first())
+    }
+
+    fn draw(
+        &self,
+        pixmap: &mut tiny_skia::Pixmap,
+        at: (f32, f32),
+        size: f32,
+        text: &str,
+        colour: Color32,
+        align: Align,
+    ) {
+        let scale = PxScale::from(size);
+        let width: f32 = text
+            .chars()
+            .filter_map(|c| {
+                let font = self.font_for(c)?.as_scaled(scale);
+                Some(font.h_advance(font.glyph_id(c)))
+            })
+            .sum();
+        let mut x = match align {
+            Align::Center => at.0 - width / 2.0,
+            Align::Right => at.0 - width,
+        };
+        let [r, g, b, a] = colour.to_srgba_unmultiplied();
+        let (w, h) = (pixmap.width(), pixmap.height());
+        let pixels = pixmap.pixels_mut();
+        for c in text.chars() {
+            let Some(font) = self.font_for(c) else {
+                continue;
+            };
+            let scaled = font.as_scaled(scale);
+            // Centre the line of text, ascent to descent, on the anchor
+            let baseline = at.1 + (scaled.ascent() + scaled.descent()) / 2.0;
+            let glyph = scaled
+                .glyph_id(c)
+                .with_scale_and_position(scale, ab_glyph::point(x, baseline));
+            x += scaled.h_advance(glyph.id);
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px as u32 >= w || py as u32 >= h {
+                    return;
+                }
+                let alpha = coverage.clamp(0.0, 1.0) * a as f32 / 255.0;
+                let pixel = &mut pixels[(py as u32 * w + px as u32) as usize];
+                // Source over, on premultiplied pixels
+                let blend =
+                    |src: u8, dst: u8| (src as f32 * alpha + dst as f32 * (1.0 - alpha)) as u8;
+                if let Some(blended) = tiny_skia::PremultipliedColorU8::from_rgba(
+                    blend(r, pixel.red()),
+                    blend(g, pixel.green()),
+                    blend(b, pixel.blue()),
+                    blend(255, pixel.alpha()),
+                ) {
+                    *pixel = blended;
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::SolverConfig;
+
+    /// The 8 queens with one per column, at these rows.
+    fn eight_queens() -> Board {
+        let mut board = vec![vec![0; 8]; 8];
+        for (col, row) in [0, 4, 7, 5, 2, 6, 1, 3].into_iter().enumerate() {
+            board[row][col] = 1;
+        }
+        board
+    }
+
+    #[test]
+    fn svg_draws_one_glyph_per_queen() {
+        let theme = Theme::default();
+        let view = BoardView {
+            theme: &theme,
+            piece: &Piece::Queen,
+            fixed: &[],
+            blocked: &[],
+            labels: true,
+        };
+        let svg = Scene::single(&eight_queens(), &view, 32.0).to_svg();
+        assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches(">♛</text>").count(), 8);
+        assert!(svg.contains(">h</text>") && svg.contains(">8</text>"));
+        assert_eq!(escape("<a & b>"), "&lt;a &amp; b&gt;");
+    }
+
+    #[test]
+    fn png_has_the_signature_and_size_of_the_scene() {
+        let theme = Theme::default();
+        let view = BoardView {
+            theme: &theme,
+            piece: &Piece::Queen,
+            fixed: &[],
+            blocked: &[],
+            labels: false,
+        };
+        let scene = Scene::single(&eight_queens(), &view, 10.0);
+        let png = scene.to_png().unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+        assert_eq!(
+            (width, height),
+            (scene.width.ceil() as u32, scene.height.ceil() as u32)
+        );
+    }
+
+    #[test]
+    fn contact_sheet_shows_every_listed_solution() {
+        let constraints = Constraints::default();
+        let mut search = SolverConfig::default().build(6, &constraints);
+        while !search.is_finished() {
+            search.step();
+        }
+        let theme = Theme::default();
+        let sheet = contact_sheet(6, &constraints, search.solutions(), false, &theme, 20.0, 3);
+        let svg = sheet.to_svg();
+        assert_eq!(svg.matches(">♛</text>").count(), 4 * 6);
+        assert!(svg.contains("6×6: 4 solution(s)"));
+        for k in 1..=4 {
+            assert!(svg.contains(&format!(">#{} ", k)));
+        }
+        // Four tiles in rows of three take two lines
+        let unique = contact_sheet(6, &constraints, search.solutions(), true, &theme, 20.0, 3);
+        assert!(unique.height < sheet.height);
+        assert!(unique.to_svg().contains("6×6: 1 unique solution(s)"));
+    }
+}
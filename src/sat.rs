@@ -0,0 +1,410 @@
+//! A small CDCL SAT solver: two watched literals, first-UIP clause learning
+//! with non-chronological backjumping and VSIDS-style variable activities.
+//!
+//! With `Learning::Decisions` every conflict learns the negation of the
+//! current decisions instead, which makes the search plain DPLL with
+//! chronological backtracking; handy for comparing the two.
+//!
+//! Literals use DIMACS numbering: `v` and `-v` for variable `v >= 1`.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Learning {
+    FirstUip,
+    Decisions,
+}
+
+/// What one call to `Cdcl::step` did.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    /// Propagation finished and a new decision was made.
+    Decided,
+    /// Propagation hit a conflict; a clause was learned and the search backjumped.
+    Conflict,
+    /// Every variable is assigned without conflict.
+    Sat,
+    /// The clauses have no (further) model.
+    Unsat,
+}
+
+pub struct Cdcl {
+    num_vars: usize,
+    clauses: Vec<Vec<i32>>,
+    // Clause indices watching each literal, see `lit_index`
+    watches: Vec<Vec<usize>>,
+    learning: Learning,
+    // Variables decided `true` first, the rest `false`
+    positive_phase: usize,
+
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<i32>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    unsat: bool,
+
+    activity: Vec<f64>,
+    var_inc: f64,
+
+    pub decisions: u64,
+    pub conflicts: u64,
+    pub propagations: u64,
+    pub learned: u64,
+}
+
+fn lit_index(lit: i32) -> usize {
+    2 * (lit.unsigned_abs() as usize - 1) + usize::from(lit < 0)
+}
+
+fn lit_var(lit: i32) -> usize {
+    lit.unsigned_abs() as usize
+}
+
+impl Cdcl {
+    /// Variables `1..=positive_phase` are tried `true` when decided.
+    pub fn new(
+        num_vars: usize,
+        clauses: &[Vec<i32>],
+        learning: Learning,
+        positive_phase: usize,
+    ) -> Self {
+        let mut solver = Self {
+            num_vars,
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * num_vars],
+            learning,
+            positive_phase,
+            values: vec![None; num_vars + 1],
+            levels: vec![0; num_vars + 1],
+            reasons: vec![None; num_vars + 1],
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            unsat: false,
+            activity: vec![0.0; num_vars + 1],
+            var_inc: 1.0,
+            decisions: 0,
+            conflicts: 0,
+            propagations: 0,
+            learned: 0,
+        };
+        for clause in clauses {
+            solver.add_clause(clause.clone());
+        }
+        solver
+    }
+
+    pub fn value(&self, var: usize) -> Option<bool> {
+        self.values[var]
+    }
+
+    fn lit_value(&self, lit: i32) -> Option<bool> {
+        self.values[lit_var(lit)].map(|v| v == (lit > 0))
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    /// The literal decided at the current level, if any.
+    pub fn last_decision(&self) -> Option<i32> {
+        self.trail_lim.last().map(|&i| self.trail[i])
+    }
+
+    /// Add a clause between searches (at level 0), e.g. to block a found model.
+    pub fn add_clause(&mut self, mut clause: Vec<i32>) {
+        self.backtrack(0);
+        clause.sort_unstable();
+        clause.dedup();
+        if clause.windows(2).any(|w| w[0] == -w[1])
+            || clause.iter().any(|&l| self.lit_value(l) == Some(true))
+        {
+            return; // Tautology or already satisfied
+        }
+        clause.retain(|&l| self.lit_value(l) != Some(false));
+        match clause.len() {
+            0 => self.unsat = true,
+            1 => self.enqueue(clause[0], None),
+            _ => {
+                self.watch(self.clauses.len(), &clause);
+                self.clauses.push(clause);
+            }
+        }
+    }
+
+    fn watch(&mut self, index: usize, clause: &[i32]) {
+        self.watches[lit_index(clause[0])].push(index);
+        self.watches[lit_index(clause[1])].push(index);
+    }
+
+    fn enqueue(&mut self, lit: i32, reason: Option<usize>) {
+        let var = lit_var(lit);
+        self.values[var] = Some(lit > 0);
+        self.levels[var] = self.decision_level();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Unit propagation; returns the index of a falsified clause.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = -self.trail[self.qhead];
+            self.qhead += 1;
+            let mut watching = std::mem::take(&mut self.watches[lit_index(false_lit)]);
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watching.len() {
+                let ci = watching[i];
+                if self.clauses[ci][0] == false_lit {
+                    self.clauses[ci].swap(0, 1);
+                }
+                let first = self.clauses[ci][0];
+                if self.lit_value(first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                // Look for a new literal to watch instead of `false_lit`
+                let replacement = (2..self.clauses[ci].len())
+                    .find(|&k| self.lit_value(self.clauses[ci][k]) != Some(false));
+                if let Some(k) = replacement {
+                    self.clauses[ci].swap(1, k);
+                    let lit = self.clauses[ci][1];
+                    self.watches[lit_index(lit)].push(ci);
+                    watching.swap_remove(i);
+                    continue;
+                }
+                if self.lit_value(first) == Some(false) {
+                    conflict = Some(ci);
+                    break;
+                }
+                self.enqueue(first, Some(ci));
+                self.propagations += 1;
+                i += 1;
+            }
+            self.watches[lit_index(false_lit)] = watching;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for &lit in &self.trail[start..] {
+            let var = lit_var(lit);
+            self.values[var] = None;
+            self.reasons[var] = None;
+        }
+        self.trail.truncate(start);
+        self.trail_lim.truncate(level);
+        self.qhead = start;
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.var_inc;
+        if self.activity[var] > 1e100 {
+            for a in &mut self.activity {
+                *a *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+    }
+
+    /// First-UIP learning. Returns the learned clause, asserting literal
+    /// first, and the level to backjump to.
+    fn analyze(&mut self, conflict: usize) -> (Vec<i32>, usize) {
+        let level = self.decision_level();
+        let mut seen = vec![false; self.num_vars + 1];
+        let mut learnt = vec![0];
+        let mut open = 0;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+        let mut resolved: Option<i32> = None;
+
+        loop {
+            for k in 0..self.clauses[clause].len() {
+                let q = self.clauses[clause][k];
+                let var = lit_var(q);
+                if Some(q) == resolved || seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                seen[var] = true;
+                self.bump(var);
+                if self.levels[var] == level {
+                    open += 1;
+                } else {
+                    learnt.push(q);
+                }
+            }
+            // Walk back the trail to the next literal of this level in the clause
+            loop {
+                index -= 1;
+                if seen[lit_var(self.trail[index])] {
+                    break;
+                }
+            }
+            let p = self.trail[index];
+            seen[lit_var(p)] = false;
+            open -= 1;
+            if open == 0 {
+                learnt[0] = -p;
+                break;
+            }
+            resolved = Some(p);
+            clause = self.reasons[lit_var(p)].expect("implied literal without reason");
+        }
+
+        // Watch the literal of the highest remaining level second
+        let mut backjump = 0;
+        if learnt.len() > 1 {
+            let (k, _) = learnt
+                .iter()
+                .enumerate()
+                .skip(1)
+                .max_by_key(|&(_, &l)| self.levels[lit_var(l)])
+                .unwrap();
+            learnt.swap(1, k);
+            backjump = self.levels[lit_var(learnt[1])];
+        }
+        (learnt, backjump)
+    }
+
+    /// DPLL-style: forbid the current combination of decisions.
+    fn decisions_clause(&self) -> (Vec<i32>, usize) {
+        let learnt = self
+            .trail_lim
+            .iter()
+            .rev()
+            .map(|&i| -self.trail[i])
+            .collect();
+        (learnt, self.decision_level() - 1)
+    }
+
+    pub fn step(&mut self) -> Outcome {
+        if self.unsat {
+            return Outcome::Unsat;
+        }
+        if let Some(conflict) = self.propagate() {
+            self.conflicts += 1;
+            if self.decision_level() == 0 {
+                self.unsat = true;
+                return Outcome::Unsat;
+            }
+            let (learnt, backjump) = match self.learning {
+                Learning::FirstUip => self.analyze(conflict),
+                Learning::Decisions => self.decisions_clause(),
+            };
+            self.backtrack(backjump);
+            self.var_inc /= 0.95;
+            self.learned += 1;
+            if learnt.len() == 1 {
+                self.enqueue(learnt[0], None);
+            } else {
+                let index = self.clauses.len();
+                self.watch(index, &learnt);
+                self.enqueue(learnt[0], Some(index));
+                self.clauses.push(learnt);
+            }
+            return Outcome::Conflict;
+        }
+
+        let next = (1..=self.num_vars)
+            .filter(|&v| self.values[v].is_none())
+            .max_by(|&a, &b| {
+                self.activity[a]
+                    .total_cmp(&self.activity[b])
+                    .then(b.cmp(&a))
+            });
+        match next {
+            Some(var) => {
+                self.decisions += 1;
+                self.trail_lim.push(self.trail.len());
+                let lit = if var <= self.positive_phase {
+                    var as i32
+                } else {
+                    -(var as i32)
+                };
+                self.enqueue(lit, None);
+                Outcome::Decided
+            }
+            None => Outcome::Sat,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cnf::{self, Encoding};
+    use crate::solver::Constraints;
+
+    /// Distinct boards the solver finds for n queens, blocking each one as
+    /// the SAT search does.
+    fn count_models(n: usize, encoding: Encoding, learning: Learning) -> usize {
+        let cnf = cnf::encode(n, encoding, &Constraints::default());
+        let mut sat = Cdcl::new(cnf.num_vars, &cnf.clauses, learning, n * n);
+        let mut models = 0;
+        loop {
+            match sat.step() {
+                Outcome::Sat => {
+                    models += 1;
+                    let blocking = (1..=n * n)
+                        .filter(|&v| sat.value(v) == Some(true))
+                        .map(|v| -(v as i32))
+                        .collect();
+                    sat.add_clause(blocking);
+                }
+                Outcome::Unsat => return models,
+                Outcome::Decided | Outcome::Conflict => {}
+            }
+        }
+    }
+
+    #[test]
+    fn counts_every_board_under_each_encoding() {
+        let expected = [1, 0, 0, 2, 10, 4, 40, 92];
+        for encoding in Encoding::ALL {
+            for learning in [Learning::FirstUip, Learning::Decisions] {
+                for (n, &count) in (1..=8).zip(&expected) {
+                    assert_eq!(
+                        count_models(n, encoding, learning),
+                        count,
+                        "n = {}, {:?}, {:?}",
+                        n,
+                        encoding,
+                        learning
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn two_and_three_are_unsatisfiable() {
+        for encoding in Encoding::ALL {
+            for n in [2, 3] {
+                let cnf = cnf::encode(n, encoding, &Constraints::default());
+                let mut sat = Cdcl::new(cnf.num_vars, &cnf.clauses, Learning::FirstUip, n * n);
+                let outcome = loop {
+                    match sat.step() {
+                        Outcome::Decided | Outcome::Conflict => {}
+                        outcome => break outcome,
+                    }
+                };
+                assert_eq!(outcome, Outcome::Unsat, "n = {}, {:?}", n, encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn contradictory_units_are_unsatisfiable() {
+        let mut sat = Cdcl::new(2, &[vec![1, 2]], Learning::FirstUip, 0);
+        sat.add_clause(vec![-1]);
+        sat.add_clause(vec![-2]);
+        assert_eq!(sat.step(), Outcome::Unsat);
+    }
+}
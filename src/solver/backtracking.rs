@@ -0,0 +1,230 @@
+use super::{
+    Algorithm, Board, ColumnOrder, Constraints, Ordering, Piece, SolutionLog, Solver, SolverStats,
+    Topology,
+};
+
+pub struct SolverWrapper {
+    n: usize,
+    board: Board,
+    log: SolutionLog,
+
+    // DFS State
+    stack: Vec<(usize, usize)>,
+    // Pre-placed queens; their columns are never searched
+    fixed: Vec<(usize, usize)>,
+    blocked: Vec<Vec<bool>>,
+    piece: Piece,
+    topology: Topology,
+
+    // Column being filled and the position in its row order to try next
+    col: usize,
+    row: usize,
+    columns: ColumnOrder,
+    column_sequence: Vec<usize>,
+    row_sequences: Vec<Vec<usize>>,
+
+    // Squares the last step found attacked before it placed a queen or gave up
+    rejected: Vec<(usize, usize)>,
+
+    // We need to know if we are "forwarding" or "backtracking"
+    backtracking: bool,
+    finished: bool,
+    stats: SolverStats,
+}
+
+impl SolverWrapper {
+    pub fn new(n: usize, ordering: Ordering, constraints: &Constraints) -> Self {
+        let fixed = constraints.fixed.clone();
+        let mut board = vec![vec![0; n]; n];
+        for &(r, c) in &fixed {
+            board[r][c] = 1;
+        }
+        let mut solver = Self {
+            n,
+            board,
+            log: SolutionLog::new(constraints.topology),
+            stack: Vec::new(),
+            col: 0,
+            row: 0,
+            columns: ordering.columns,
+            column_sequence: ordering
+                .column_sequence(n)
+                .into_iter()
+                .filter(|&c| fixed.iter().all(|&(_, fc)| fc != c))
+                .collect(),
+            row_sequences: ordering.row_sequences(n),
+            fixed,
+            blocked: constraints.blocked_grid(n),
+            piece: constraints.piece.clone(),
+            topology: constraints.topology,
+            rejected: Vec::new(),
+            backtracking: false,
+            // Nothing to search if the given queens already attack each other
+            finished: !constraints.fixed_consistent(n),
+            stats: SolverStats::default(),
+        };
+        solver.col = solver.next_column();
+        solver
+    }
+
+    fn is_free(&self, col: usize) -> bool {
+        self.stack.iter().all(|&(_, c)| c != col)
+    }
+
+    /// The column to fill after the queens already on the stack.
+    fn next_column(&self) -> usize {
+        let mut free = self
+            .column_sequence
+            .iter()
+            .copied()
+            .filter(|&c| self.is_free(c));
+        if self.columns == ColumnOrder::Mrv {
+            free.min_by_key(|&c| (0..self.n).filter(|&r| self.is_safe(r, c)).count())
+                .unwrap_or(0)
+        } else {
+            free.next().unwrap_or(0)
+        }
+    }
+
+    fn is_safe(&self, row: usize, col: usize) -> bool {
+        !self.blocked[row][col]
+            && self.stack.iter().chain(&self.fixed).all(|&queen| {
+                !self
+                    .piece
+                    .attacks((self.n, self.n), self.topology, queen, (row, col))
+            })
+    }
+}
+
+impl Solver for SolverWrapper {
+    fn name(&self) -> &'static str {
+        Algorithm::Backtracking.label()
+    }
+
+    fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.stats.steps += 1;
+        self.rejected.clear();
+
+        if self.backtracking {
+            // Pop previous
+            if let Some((r, c)) = self.stack.pop() {
+                self.board[r][c] = 0; // Remove queen
+                self.col = c;
+                // Try the row after it
+                self.row = self.row_sequences[c].iter().position(|&x| x == r).unwrap() + 1;
+                self.backtracking = false;
+                self.stats.backtracks += 1;
+            } else {
+                self.finished = true;
+                return false;
+            }
+        }
+
+        if self.stack.len() + self.fixed.len() >= self.n {
+            // Found solution
+            self.log.record(&self.board);
+            self.stats.note_solution();
+            self.backtracking = true; // Trigger backtrack to find next
+            return true; // Signal solution found
+        }
+
+        // Search in current col
+        while self.row < self.n {
+            let row = self.row_sequences[self.col][self.row];
+            if self.is_safe(row, self.col) {
+                self.board[row][self.col] = 1;
+                self.stack.push((row, self.col));
+                self.stats.placements += 1;
+                self.col = self.next_column();
+                self.row = 0;
+                return false; // Step complete (placed one queen)
+            }
+            self.rejected.push((row, self.col));
+            self.row += 1;
+        }
+
+        // No row found in this col, trigger backtrack
+        self.backtracking = true;
+        false
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        self.stack.last().copied()
+    }
+
+    fn rejected(&self) -> &[(usize, usize)] {
+        &self.rejected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::RowOrder;
+
+    #[test]
+    fn every_ordering_finds_the_same_solutions() {
+        let mut found = Vec::new();
+        for columns in ColumnOrder::ALL {
+            for rows in RowOrder::ALL {
+                let ordering = Ordering {
+                    columns,
+                    rows,
+                    seed: 42,
+                };
+                let mut solver = SolverWrapper::new(8, ordering, &Constraints::default());
+                while !solver.is_finished() {
+                    solver.step();
+                }
+                let mut placements = solver.solutions().placements.clone();
+                placements.sort();
+                assert_eq!(placements.len(), 92, "{:?}", ordering);
+                found.push(placements);
+            }
+        }
+        assert!(found.iter().all(|placements| *placements == found[0]));
+    }
+
+    #[test]
+    fn a_seed_repeats_its_search() {
+        let run = |seed| {
+            let ordering = Ordering {
+                rows: RowOrder::Random,
+                seed,
+                ..Ordering::default()
+            };
+            let mut solver = SolverWrapper::new(8, ordering, &Constraints::default());
+            while !solver.is_finished() {
+                solver.step();
+            }
+            solver.solutions().solutions.clone()
+        };
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(43));
+    }
+}
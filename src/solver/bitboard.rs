@@ -0,0 +1,230 @@
+use super::{Algorithm, Board, Constraints, Piece, SolutionLog, Solver, SolverStats, Topology};
+
+/// Same column-by-column search as `SolverWrapper`, but the attacked rows of
+/// the next column are kept as bit masks instead of being rescanned.
+pub struct BitboardSolver {
+    n: usize,
+    board: Board,
+    log: SolutionLog,
+
+    // One frame per filled column: (row placed, rows still to try, row mask, diag masks)
+    frames: Vec<Frame>,
+    // Rows still to try in the column being filled
+    candidates: u64,
+    // Rows each column may use at all: the fixed row, or unblocked rows no
+    // fixed queen attacks
+    allowed: Vec<u64>,
+    fixed_columns: Vec<bool>,
+    // Diagonal masks rotate instead of shifting out at the edges
+    toroidal: bool,
+    // Rooks ignore the diagonal masks
+    diagonals: bool,
+    finished: bool,
+    stats: SolverStats,
+    // Long enumerations only look at `solution_rows`, not the log
+    logging: bool,
+    solution_rows: Vec<usize>,
+}
+
+struct Frame {
+    row: usize,
+    remaining: u64,
+    rows: u64,
+    down: u64,
+    up: u64,
+}
+
+impl BitboardSolver {
+    pub fn new(n: usize, constraints: &Constraints) -> Self {
+        let mut board = vec![vec![0; n]; n];
+        for &(r, c) in &constraints.fixed {
+            board[r][c] = 1;
+        }
+        let allowed: Vec<u64> = (0..n)
+            .map(|col| match constraints.fixed_row(col) {
+                Some(row) => 1 << row,
+                None => (0..n)
+                    .filter(|&row| {
+                        !constraints.blocked.contains(&(row, col))
+                            && constraints
+                                .fixed
+                                .iter()
+                                .all(|&q| !constraints.attacks(n, q, (row, col)))
+                    })
+                    .fold(0, |mask, row| mask | 1 << row),
+            })
+            .collect();
+        Self {
+            n,
+            board,
+            log: SolutionLog::new(constraints.topology),
+            frames: Vec::new(),
+            candidates: allowed.first().copied().unwrap_or(0),
+            fixed_columns: (0..n).map(|c| constraints.fixed_row(c).is_some()).collect(),
+            allowed,
+            toroidal: constraints.topology == Topology::Toroidal,
+            diagonals: constraints.piece != Piece::Rook,
+            finished: !constraints.fixed_consistent(n),
+            stats: SolverStats::default(),
+            logging: true,
+            solution_rows: Vec::new(),
+        }
+    }
+
+    /// Don't keep solutions in `solutions()`, for enumerations too long to
+    /// hold in memory; read each from `solution_rows` as `step` finds it.
+    pub fn without_log(mut self) -> Self {
+        self.logging = false;
+        self
+    }
+
+    /// Row of the queen in each column of the latest solution.
+    pub fn solution_rows(&self) -> &[usize] {
+        &self.solution_rows
+    }
+
+    fn lift(&mut self, frame: &Frame, col: usize) {
+        if !self.fixed_columns[col] {
+            self.board[frame.row][col] = 0;
+        }
+        self.candidates = frame.remaining;
+        self.stats.backtracks += 1;
+    }
+
+    /// Masks of rows attacked in the column after the last frame.
+    fn attacked(&self) -> (u64, u64, u64) {
+        match self.frames.last() {
+            Some(f) => {
+                let bit = 1u64 << f.row;
+                let (down, up) = (f.down | bit, f.up | bit);
+                if self.toroidal {
+                    let full = (1u64 << self.n) - 1;
+                    (
+                        f.rows | bit,
+                        (down << 1 | down >> (self.n - 1)) & full,
+                        up >> 1 | (up & 1) << (self.n - 1),
+                    )
+                } else {
+                    (f.rows | bit, down << 1, up >> 1)
+                }
+            }
+            None => (0, 0, 0),
+        }
+    }
+}
+
+impl Solver for BitboardSolver {
+    fn name(&self) -> &'static str {
+        Algorithm::Bitboard.label()
+    }
+
+    fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.stats.steps += 1;
+
+        let col = self.frames.len();
+        if col == self.n {
+            self.solution_rows = self.frames.iter().map(|f| f.row).collect();
+            if self.logging {
+                self.log.record(&self.board);
+            }
+            self.stats.note_solution();
+            // Drop the last queen so the next step resumes its column
+            let frame = self.frames.pop().unwrap();
+            self.lift(&frame, col - 1);
+            return true;
+        }
+
+        let (rows, down, up) = self.attacked();
+        let free = if self.diagonals {
+            self.candidates & !(rows | down | up)
+        } else {
+            self.candidates & !rows
+        };
+        if free == 0 {
+            match self.frames.pop() {
+                Some(frame) => self.lift(&frame, col - 1),
+                None => self.finished = true,
+            }
+            return false;
+        }
+
+        let row = free.trailing_zeros() as usize;
+        self.board[row][col] = 1;
+        self.frames.push(Frame {
+            row,
+            remaining: free & (free - 1),
+            rows,
+            down,
+            up,
+        });
+        self.candidates = self.allowed.get(col + 1).copied().unwrap_or(0);
+        self.stats.placements += 1;
+        false
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        self.frames.last().map(|f| (f.row, self.frames.len() - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(n: usize, topology: Topology) -> usize {
+        let constraints = Constraints {
+            topology,
+            ..Constraints::default()
+        };
+        let mut solver = BitboardSolver::new(n, &constraints);
+        while !solver.is_finished() {
+            solver.step();
+        }
+        solver.solutions().solutions.len()
+    }
+
+    #[test]
+    fn torus_needs_n_coprime_to_six() {
+        let counts: Vec<usize> = (4..=11).map(|n| count(n, Topology::Toroidal)).collect();
+        assert_eq!(counts, [0, 10, 0, 28, 0, 0, 0, 88]);
+        for n in 4..=11 {
+            assert_eq!(
+                Topology::Toroidal.has_solutions(n),
+                counts[n - 4] > 0,
+                "n = {}",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn standard_counts() {
+        let counts: Vec<usize> = (4..=9).map(|n| count(n, Topology::Standard)).collect();
+        assert_eq!(counts, [2, 10, 4, 40, 92, 352]);
+    }
+}
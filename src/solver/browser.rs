@@ -0,0 +1,62 @@
+use super::{Board, Constraints, SolutionLog, Solver, SolverStats};
+
+/// Plays back boards that were solved elsewhere (a SAT model, an imported
+/// file), one solution per step, so they can be browsed like a live search.
+pub struct SolutionBrowser {
+    boards: Vec<Board>,
+    next: usize,
+    board: Board,
+    log: SolutionLog,
+    stats: SolverStats,
+}
+
+impl SolutionBrowser {
+    pub fn new(n: usize, constraints: &Constraints, boards: Vec<Board>) -> Self {
+        Self {
+            boards,
+            next: 0,
+            board: vec![vec![0; constraints.cols(n)]; n],
+            log: SolutionLog::new(constraints.topology),
+            stats: SolverStats::default(),
+        }
+    }
+}
+
+impl Solver for SolutionBrowser {
+    fn name(&self) -> &'static str {
+        "Loaded solutions"
+    }
+
+    fn step(&mut self) -> bool {
+        let Some(board) = self.boards.get(self.next) else {
+            return false;
+        };
+        self.next += 1;
+        self.stats.steps += 1;
+        self.board = board.clone();
+        self.log.record(&self.board);
+        true
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.next >= self.boards.len()
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use super::{Board, SolverStats};
+
+/// A cell (x, y, z) of the cube; z counts layers from the bottom.
+pub type Cell = (usize, usize, usize);
+
+/// Steps the search spends before giving up. n = 7 to 10 are decided well
+/// within it; the cubes from n = 12 up with no linear solution are not.
+pub const CUBE_STEP_BUDGET: u64 = 2_000_000;
+
+/// The axis a slice of the cube is taken across.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Axis::X => "x",
+            Axis::Y => "y",
+            Axis::Z => "z",
+        }
+    }
+}
+
+/// Queens on `a` and `b` share one of the 13 lines through a cell: along an
+/// axis, a face diagonal or a space diagonal. The coordinates that change
+/// all change by the same amount.
+pub fn attacks(a: Cell, b: Cell) -> bool {
+    let d = [a.0.abs_diff(b.0), a.1.abs_diff(b.1), a.2.abs_diff(b.2)];
+    let step = d.iter().copied().max().unwrap_or(0);
+    d.iter().all(|&x| x == 0 || x == step)
+}
+
+/// `heights[x * n + y]` is the layer of the queen on column (x, y). With n²
+/// queens every vertical line holds exactly one, so this covers every
+/// solution.
+pub fn is_solution(n: usize, heights: &[usize]) -> bool {
+    let cells: Vec<Cell> = heights
+        .iter()
+        .enumerate()
+        .map(|(i, &z)| (i / n, i % n, z))
+        .collect();
+    cells.len() == n * n
+        && cells.iter().all(|&(_, _, z)| z < n)
+        && cells
+            .iter()
+            .enumerate()
+            .all(|(i, &a)| cells[i + 1..].iter().all(|&b| !attacks(a, b)))
+}
+
+/// Queens on the planes z = a·x + b·y (mod n). Some pair (a, b) works
+/// exactly when n shares no factor with 2·3·5·7, so n = 11 is the smallest
+/// cube past n = 1 with this kind of solution.
+pub fn linear(n: usize) -> Option<Vec<usize>> {
+    (0..n)
+        .flat_map(|a| (0..n).map(move |b| (a, b)))
+        .map(|(a, b)| {
+            (0..n * n)
+                .map(|i| (a * (i / n) + b * (i % n)) % n)
+                .collect::<Vec<_>>()
+        })
+        .find(|heights| is_solution(n, heights))
+}
+
+/// n² non-attacking queens in the n×n×n cube. Cubes with a linear solution
+/// get it straight away; the others are searched column by column, giving
+/// each (x, y) the lowest layer no earlier queen attacks, until the first
+/// solution, until the search space is exhausted or until the step budget
+/// runs out.
+pub struct CubeSolver {
+    n: usize,
+    // DFS State: layers of the columns placed so far, in x * n + y order,
+    // and the next layer to try on the column after them
+    heights: Vec<usize>,
+    next: usize,
+    solved: bool,
+    // The solution came from the linear construction
+    constructed: bool,
+    finished: bool,
+    // Steps allowed, and whether they ran out before the search was decided
+    budget: u64,
+    gave_up: bool,
+    stats: SolverStats,
+}
+
+impl CubeSolver {
+    pub fn new(n: usize) -> Self {
+        let mut solver = Self::search(n, CUBE_STEP_BUDGET);
+        if let Some(heights) = linear(n) {
+            solver.heights = heights;
+            solver.solved = true;
+            solver.constructed = true;
+            solver.finished = true;
+        }
+        solver
+    }
+
+    /// The column by column search alone, without trying the construction,
+    /// giving up after `budget` steps.
+    pub fn search(n: usize, budget: u64) -> Self {
+        Self {
+            n,
+            heights: Vec::new(),
+            next: 0,
+            solved: false,
+            constructed: false,
+            finished: false,
+            budget,
+            gave_up: false,
+            stats: SolverStats::default(),
+        }
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    fn is_safe(&self, z: usize) -> bool {
+        let i = self.heights.len();
+        let cell = (i / self.n, i % self.n, z);
+        self.heights
+            .iter()
+            .enumerate()
+            .all(|(j, &h)| !attacks((j / self.n, j % self.n, h), cell))
+    }
+
+    /// Place or lift one queen. Returns true when this step found the solution.
+    pub fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        if self.stats.steps >= self.budget {
+            self.gave_up = true;
+            self.finished = true;
+            return false;
+        }
+        self.stats.steps += 1;
+
+        if let Some(z) = (self.next..self.n).find(|&z| self.is_safe(z)) {
+            self.heights.push(z);
+            self.next = 0;
+            self.stats.placements += 1;
+            if self.heights.len() == self.n * self.n {
+                self.solved = true;
+                self.finished = true;
+                self.stats.note_solution();
+                return true;
+            }
+            return false;
+        }
+
+        match self.heights.pop() {
+            Some(z) => {
+                self.next = z + 1;
+                self.stats.backtracks += 1;
+            }
+            // Every layer of the first column has been ruled out
+            None => self.finished = true,
+        }
+        false
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn solution(&self) -> Option<&[usize]> {
+        self.solved.then_some(&self.heights[..])
+    }
+
+    pub fn is_constructed(&self) -> bool {
+        self.constructed
+    }
+
+    /// The search stopped at the step budget without deciding the cube.
+    pub fn gave_up(&self) -> bool {
+        self.gave_up
+    }
+
+    pub fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    /// The plane `axis` = k as a board, with the queens placed so far. Its
+    /// rows and columns are the other two axes in x, y, z order: layer z = k
+    /// has row x and column y.
+    pub fn slice(&self, axis: Axis, k: usize) -> Board {
+        let mut board = vec![vec![0; self.n]; self.n];
+        for (i, &z) in self.heights.iter().enumerate() {
+            let (x, y) = (i / self.n, i % self.n);
+            let (at, row, col) = match axis {
+                Axis::X => (x, y, z),
+                Axis::Y => (y, x, z),
+                Axis::Z => (z, x, y),
+            };
+            if at == k {
+                board[row][col] = 1;
+            }
+        }
+        board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(n: usize) -> Option<Vec<usize>> {
+        let mut solver = CubeSolver::search(n, CUBE_STEP_BUDGET);
+        while !solver.is_finished() {
+            solver.step();
+        }
+        assert!(!solver.gave_up(), "n = {}", n);
+        solver.solution().map(<[usize]>::to_vec)
+    }
+
+    fn gcd(a: usize, b: usize) -> usize {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    #[test]
+    fn thirteen_directions() {
+        let centre = (1, 1, 1);
+        let lines = (0..27)
+            .map(|i| (i / 9, i / 3 % 3, i % 3))
+            .filter(|&cell| cell != centre && attacks(centre, cell))
+            .count();
+        assert_eq!(lines, 26);
+        assert!(!attacks((0, 0, 0), (1, 2, 0)));
+        assert!(!attacks((0, 0, 0), (1, 1, 2)));
+    }
+
+    #[test]
+    fn small_cubes_by_search() {
+        assert!(search(1).is_some_and(|h| is_solution(1, &h)));
+        for n in 2..=6 {
+            assert_eq!(search(n), None, "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn linear_solutions_follow_gcd_with_210() {
+        for n in 1..=30 {
+            let heights = linear(n);
+            assert_eq!(heights.is_some(), gcd(n, 210) == 1, "n = {}", n);
+            assert!(heights.is_none_or(|h| is_solution(n, &h)));
+        }
+        let solver = CubeSolver::new(11);
+        assert!(solver.is_finished() && solver.is_constructed());
+        // Every plane across every axis holds one queen per line
+        for axis in Axis::ALL {
+            let per_slice: Vec<usize> = (0..11)
+                .map(|k| {
+                    solver
+                        .slice(axis, k)
+                        .concat()
+                        .iter()
+                        .filter(|&&c| c == 1)
+                        .count()
+                })
+                .collect();
+            assert_eq!(per_slice, vec![11; 11], "{:?}", axis);
+        }
+    }
+
+    #[test]
+    fn gives_up_at_the_budget() {
+        let mut solver = CubeSolver::search(12, 1000);
+        while !solver.is_finished() {
+            solver.step();
+        }
+        assert!(solver.gave_up() && solver.solution().is_none());
+        assert_eq!(solver.stats().steps, 1000);
+        // n = 7 has no linear solution but is decided within the budget
+        assert_eq!(search(7), None);
+    }
+}
@@ -0,0 +1,199 @@
+use super::{Board, Constraints, SolutionLog, Solver, SolverStats};
+
+/// Smallest sets of pieces that attack or occupy every free square
+/// (blocked squares need no cover). Tries k = 1, 2, ... pieces in turn;
+/// for each k the first uncovered square, in column order, has to be
+/// covered by one of the squares that attack it, so those are the only
+/// branches. Every minimum set is listed once.
+pub struct DominationSolver {
+    board: Board,
+    log: SolutionLog,
+
+    // Squares by index (col * n + row)
+    squares: Vec<(usize, usize)>,
+    // Squares each square attacks, itself included
+    covers: Vec<Vec<usize>>,
+    // Pieces covering each square, and whether it needs covering at all
+    coverage: Vec<usize>,
+    free: Vec<bool>,
+    // Largest number of squares one piece covers, for pruning
+    max_cover: usize,
+    // Pieces may not attack each other
+    independent: bool,
+
+    // DFS State: per placed piece, the squares that were tried for it and
+    // the position of the one on the board
+    frames: Vec<(Vec<usize>, usize)>,
+    fixed: usize,
+    // Pieces per set in the current round
+    size: usize,
+
+    backtracking: bool,
+    finished: bool,
+    stats: SolverStats,
+}
+
+impl DominationSolver {
+    pub fn new(n: usize, constraints: &Constraints, independent: bool) -> Self {
+        let cols = constraints.cols(n);
+        let squares: Vec<(usize, usize)> = (0..cols)
+            .flat_map(|c| (0..n).map(move |r| (r, c)))
+            .collect();
+        let covers: Vec<Vec<usize>> = squares
+            .iter()
+            .map(|&a| {
+                (0..squares.len())
+                    .filter(|&j| constraints.attacks(n, a, squares[j]))
+                    .collect()
+            })
+            .collect();
+        let blocked = constraints.blocked_grid(n);
+        let mut solver = Self {
+            board: vec![vec![0; cols]; n],
+            log: SolutionLog::new(constraints.topology),
+            free: squares.iter().map(|&(r, c)| !blocked[r][c]).collect(),
+            coverage: vec![0; squares.len()],
+            max_cover: covers.iter().map(Vec::len).max().unwrap_or(0),
+            squares,
+            covers,
+            independent,
+            frames: Vec::new(),
+            fixed: constraints.fixed.len(),
+            size: constraints.fixed.len(),
+            backtracking: false,
+            finished: independent && !constraints.fixed_consistent(n),
+            stats: SolverStats::default(),
+        };
+        for &(r, c) in &constraints.fixed {
+            solver.put(c * n + r);
+        }
+        solver
+    }
+
+    fn put(&mut self, i: usize) {
+        let (r, c) = self.squares[i];
+        self.board[r][c] = 1;
+        for &j in &self.covers[i] {
+            self.coverage[j] += 1;
+        }
+    }
+
+    fn lift(&mut self, i: usize) {
+        let (r, c) = self.squares[i];
+        self.board[r][c] = 0;
+        for &j in &self.covers[i] {
+            self.coverage[j] -= 1;
+        }
+    }
+
+    fn uncovered(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.squares.len()).filter(|&i| self.free[i] && self.coverage[i] == 0)
+    }
+
+    /// Free, empty squares a new piece may take to cover square `u`.
+    fn candidates(&self, u: usize) -> Vec<usize> {
+        // Covering is symmetric, so the squares covering u are those u covers
+        self.covers[u]
+            .iter()
+            .copied()
+            .filter(|&i| {
+                let (r, c) = self.squares[i];
+                self.free[i] && self.board[r][c] == 0 && !(self.independent && self.coverage[i] > 0)
+            })
+            .collect()
+    }
+
+    /// Place the next untried candidate of the top frame, if any is left.
+    fn advance(&mut self) -> bool {
+        let Some((candidates, next)) = self.frames.last_mut() else {
+            return false;
+        };
+        let Some(&i) = candidates.get(*next) else {
+            self.frames.pop();
+            return false;
+        };
+        *next += 1;
+        self.put(i);
+        self.stats.placements += 1;
+        true
+    }
+}
+
+impl Solver for DominationSolver {
+    fn name(&self) -> &'static str {
+        "Domination search"
+    }
+
+    fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.stats.steps += 1;
+
+        if self.backtracking {
+            let Some((candidates, next)) = self.frames.last() else {
+                // Every set of this size has been tried
+                if !self.log.solutions.is_empty() || self.size >= self.squares.len() {
+                    self.finished = true;
+                } else {
+                    self.size += 1;
+                    self.backtracking = false;
+                }
+                return false;
+            };
+            let placed = candidates[next - 1];
+            self.lift(placed);
+            self.stats.backtracks += 1;
+            self.backtracking = !self.advance();
+            return false;
+        }
+
+        let Some(u) = self.uncovered().next() else {
+            // Each set can be reached in several orders
+            self.backtracking = true;
+            let squares = super::squares(&self.board);
+            if self.log.contains(&squares) {
+                return false;
+            }
+            self.log.record(&self.board);
+            self.stats.note_solution();
+            return true;
+        };
+
+        let left = self.size - self.fixed - self.frames.len();
+        if left == 0 || self.uncovered().count() > left * self.max_cover {
+            self.backtracking = true;
+            return false;
+        }
+        self.frames.push((self.candidates(u), 0));
+        self.backtracking = !self.advance();
+        false
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        let (candidates, next) = self.frames.last()?;
+        Some(self.squares[candidates[next - 1]])
+    }
+}
@@ -0,0 +1,359 @@
+use super::{
+    Algorithm, Board, ColumnOrder, Constraints, Ordering, Piece, SolutionLog, Solver, SolverStats,
+    Topology,
+};
+
+/// `domains[col][row]` is true while `row` is still a candidate for `col`.
+pub type Domains = Vec<Vec<bool>>;
+
+/// Constraint propagation search: every column keeps the set of rows not yet
+/// attacked, assignments prune the other columns (forward checking) and,
+/// optionally, arc consistency keeps pruning until every value is supported.
+pub struct ForwardCheckingSolver {
+    n: usize,
+    board: Board,
+    log: SolutionLog,
+    domains: Domains,
+    assigned: Vec<Option<usize>>,
+
+    arc_consistency: bool,
+    piece: Piece,
+    topology: Topology,
+    columns: ColumnOrder,
+    column_sequence: Vec<usize>,
+    row_sequences: Vec<Vec<usize>>,
+
+    // One frame per assignment, holding the domains as they were before it
+    stack: Vec<Frame>,
+    // Column being decided and the position in its row order to try next
+    cursor: Option<(usize, usize)>,
+    backtracking: bool,
+    finished: bool,
+    stats: SolverStats,
+    pruned: u64,
+    wipeouts: u64,
+}
+
+struct Frame {
+    col: usize,
+    row: usize,
+    index: usize,
+    saved: Domains,
+}
+
+impl ForwardCheckingSolver {
+    pub fn new(
+        n: usize,
+        arc_consistency: bool,
+        ordering: Ordering,
+        constraints: &Constraints,
+    ) -> Self {
+        let blocked = constraints.blocked_grid(n);
+        let mut solver = Self {
+            n,
+            board: vec![vec![0; n]; n],
+            log: SolutionLog::new(constraints.topology),
+            // Blocked squares are never candidates
+            domains: (0..n)
+                .map(|col| (0..n).map(|row| !blocked[row][col]).collect())
+                .collect(),
+            assigned: vec![None; n],
+            arc_consistency,
+            piece: constraints.piece.clone(),
+            topology: constraints.topology,
+            columns: ordering.columns,
+            column_sequence: ordering.column_sequence(n),
+            row_sequences: ordering.row_sequences(n),
+            stack: Vec::new(),
+            cursor: None,
+            backtracking: false,
+            finished: false,
+            stats: SolverStats::default(),
+            pruned: 0,
+            wipeouts: 0,
+        };
+
+        // Fixed queens are assignments made before the search starts
+        let mut consistent = constraints.fixed_consistent(n);
+        for &(row, col) in &constraints.fixed {
+            solver.assigned[col] = Some(row);
+            solver.board[row][col] = 1;
+            solver.domains[col] = (0..n).map(|r| r == row).collect();
+        }
+        for &(row, col) in &constraints.fixed {
+            consistent = consistent && solver.forward_check(row, col);
+        }
+        consistent = consistent && (0..n).all(|col| solver.domain_size(col) > 0);
+        if consistent && arc_consistency {
+            consistent = solver.propagate_arcs();
+        }
+        solver.finished = !consistent;
+        solver
+    }
+
+    fn domain_size(&self, col: usize) -> usize {
+        self.domains[col].iter().filter(|&&d| d).count()
+    }
+
+    /// Next column to assign: the first free one in column order, or with
+    /// MRV the free column with the fewest rows left.
+    fn choose_column(&self) -> Option<usize> {
+        let mut free = self
+            .column_sequence
+            .iter()
+            .copied()
+            .filter(|&c| self.assigned[c].is_none());
+        if self.columns == ColumnOrder::Mrv {
+            free.min_by_key(|&c| self.domain_size(c))
+        } else {
+            free.next()
+        }
+    }
+
+    fn remove(&mut self, col: usize, row: usize) {
+        if self.domains[col][row] {
+            self.domains[col][row] = false;
+            self.pruned += 1;
+        }
+    }
+
+    /// Remove the rows attacked by a queen on (row, col) from every free
+    /// column. Returns false when some column is left without candidates.
+    fn forward_check(&mut self, row: usize, col: usize) -> bool {
+        for c in 0..self.n {
+            if self.assigned[c].is_some() {
+                continue;
+            }
+            for r in 0..self.n {
+                if self
+                    .piece
+                    .attacks((self.n, self.n), self.topology, (row, col), (r, c))
+                {
+                    self.remove(c, r);
+                }
+            }
+            if self.domain_size(c) == 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// AC-3 over the free columns. Returns false on a wipeout.
+    fn propagate_arcs(&mut self) -> bool {
+        let free: Vec<usize> = (0..self.n)
+            .filter(|&c| self.assigned[c].is_none())
+            .collect();
+        let mut queue: Vec<(usize, usize)> = free
+            .iter()
+            .flat_map(|&i| free.iter().filter(move |&&j| j != i).map(move |&j| (i, j)))
+            .collect();
+
+        while let Some((i, j)) = queue.pop() {
+            if !self.revise(i, j) {
+                continue;
+            }
+            if self.domain_size(i) == 0 {
+                return false;
+            }
+            for &k in &free {
+                if k != i && k != j {
+                    queue.push((k, i));
+                }
+            }
+        }
+        true
+    }
+
+    /// Drop the rows of column `i` that no row of column `j` is compatible with.
+    fn revise(&mut self, i: usize, j: usize) -> bool {
+        let mut revised = false;
+        for a in 0..self.n {
+            if !self.domains[i][a] {
+                continue;
+            }
+            let supported = (0..self.n).any(|b| {
+                self.domains[j][b]
+                    && !self
+                        .piece
+                        .attacks((self.n, self.n), self.topology, (a, i), (b, j))
+            });
+            if !supported {
+                self.remove(i, a);
+                revised = true;
+            }
+        }
+        revised
+    }
+}
+
+impl Solver for ForwardCheckingSolver {
+    fn name(&self) -> &'static str {
+        Algorithm::ForwardChecking.label()
+    }
+
+    fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.stats.steps += 1;
+
+        if self.backtracking {
+            match self.stack.pop() {
+                Some(frame) => {
+                    self.board[frame.row][frame.col] = 0;
+                    self.assigned[frame.col] = None;
+                    self.domains = frame.saved;
+                    self.cursor = Some((frame.col, frame.index + 1));
+                    self.backtracking = false;
+                    self.stats.backtracks += 1;
+                }
+                None => {
+                    self.finished = true;
+                    return false;
+                }
+            }
+        }
+
+        let (col, start) = match self.cursor {
+            Some(cursor) => cursor,
+            None => match self.choose_column() {
+                Some(col) => (col, 0),
+                None => {
+                    // Every column holds a queen
+                    self.log.record(&self.board);
+                    self.stats.note_solution();
+                    self.backtracking = true;
+                    return true;
+                }
+            },
+        };
+
+        let rows = &self.row_sequences[col];
+        let Some(index) = (start..self.n).find(|&i| self.domains[col][rows[i]]) else {
+            // Column exhausted, undo the previous assignment
+            self.backtracking = true;
+            return false;
+        };
+
+        let row = rows[index];
+        let saved = self.domains.clone();
+        self.assigned[col] = Some(row);
+        self.domains[col] = (0..self.n).map(|r| r == row).collect();
+        let consistent =
+            self.forward_check(row, col) && (!self.arc_consistency || self.propagate_arcs());
+
+        if !consistent {
+            // Some free column lost all its rows: reject this row and try the next
+            self.assigned[col] = None;
+            self.domains = saved;
+            self.cursor = Some((col, index + 1));
+            self.wipeouts += 1;
+            return false;
+        }
+
+        self.board[row][col] = 1;
+        self.stack.push(Frame {
+            col,
+            row,
+            index,
+            saved,
+        });
+        self.cursor = None;
+        self.stats.placements += 1;
+        false
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn stats(&self) -> SolverStats {
+        let mut stats = self.stats.clone();
+        stats.extra.push(("Pruned values", self.pruned));
+        stats.extra.push(("Wipeouts", self.wipeouts));
+        stats
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        self.stack.last().map(|f| (f.row, f.col))
+    }
+
+    fn domains(&self) -> Option<&Domains> {
+        Some(&self.domains)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(n: usize, arc_consistency: bool, columns: ColumnOrder) -> usize {
+        let ordering = Ordering {
+            columns,
+            ..Ordering::default()
+        };
+        let mut solver =
+            ForwardCheckingSolver::new(n, arc_consistency, ordering, &Constraints::default());
+        while !solver.is_finished() {
+            solver.step();
+        }
+        solver.solutions().solutions.len()
+    }
+
+    #[test]
+    fn counts_with_and_without_ac3_and_mrv() {
+        let expected = [1, 0, 0, 2, 10, 4, 40, 92];
+        for arc_consistency in [false, true] {
+            for columns in [ColumnOrder::Sequential, ColumnOrder::Mrv] {
+                let counts: Vec<usize> = (1..=8)
+                    .map(|n| count(n, arc_consistency, columns))
+                    .collect();
+                assert_eq!(counts, expected, "AC-3 {}, {:?}", arc_consistency, columns);
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_queens_prune_the_free_columns() {
+        let corner = Constraints {
+            fixed: vec![(0, 0)],
+            ..Constraints::default()
+        };
+        // A queen on a1 takes row 0 and the diagonal square of every column
+        let solver = ForwardCheckingSolver::new(8, false, Ordering::default(), &corner);
+        let domains = solver.domains().unwrap();
+        assert_eq!(domains[0], (0..8).map(|r| r == 0).collect::<Vec<_>>());
+        for (c, domain) in domains.iter().enumerate().skip(1) {
+            let left: Vec<bool> = (0..8).map(|r| r != 0 && r != c).collect();
+            assert_eq!(*domain, left, "column {}", c);
+        }
+
+        // No 4x4 solution has a corner queen: AC-3 empties a column before
+        // the search starts, forward checking alone has to search
+        let solver = ForwardCheckingSolver::new(4, true, Ordering::default(), &corner);
+        assert!(solver.is_finished());
+        let domains = solver.domains().unwrap();
+        assert!(domains.iter().any(|domain| domain.iter().all(|&d| !d)));
+        let mut solver = ForwardCheckingSolver::new(4, false, Ordering::default(), &corner);
+        assert!(!solver.is_finished());
+        while !solver.is_finished() {
+            solver.step();
+        }
+        assert!(solver.solutions().solutions.is_empty());
+    }
+}
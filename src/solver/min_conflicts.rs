@@ -0,0 +1,210 @@
+use super::{Algorithm, Board, Constraints, Piece, SolutionLog, Solver, SolverStats, Topology};
+use crate::rng::Rng;
+
+/// Give up on a random start after this many moves per column.
+const MOVES_PER_COLUMN: usize = 10;
+/// Stop once this many restarts in a row only rediscovered known solutions.
+const MAX_STALE_RESTARTS: u64 = 25;
+
+/// Local search: start from a full (conflicting) board and keep moving a
+/// conflicted queen to the least attacked row of its column.
+/// It is not exhaustive; it restarts after each solution and stops when
+/// restarts keep landing on solutions it has already found.
+pub struct MinConflictsSolver {
+    n: usize,
+    rows: Vec<usize>,
+    // Row of the pre-placed queen in each column; those columns never move
+    fixed: Vec<Option<usize>>,
+    blocked: Vec<Vec<bool>>,
+    piece: Piece,
+    topology: Topology,
+    board: Board,
+    log: SolutionLog,
+    rng: Rng,
+
+    moves_since_restart: usize,
+    stale_restarts: u64,
+    restart_pending: bool,
+    last_moved: Option<(usize, usize)>,
+    finished: bool,
+    stats: SolverStats,
+    restarts: u64,
+}
+
+impl MinConflictsSolver {
+    pub fn new(n: usize, constraints: &Constraints) -> Self {
+        let mut fixed = vec![None; n];
+        for &(row, col) in &constraints.fixed {
+            fixed[col] = Some(row);
+        }
+        let mut solver = Self {
+            n,
+            rows: vec![0; n],
+            fixed,
+            blocked: constraints.blocked_grid(n),
+            piece: constraints.piece.clone(),
+            topology: constraints.topology,
+            board: vec![vec![0; n]; n],
+            log: SolutionLog::new(constraints.topology),
+            rng: Rng::from_clock(),
+            moves_since_restart: 0,
+            stale_restarts: 0,
+            restart_pending: false,
+            last_moved: None,
+            finished: false,
+            stats: SolverStats::default(),
+            restarts: 0,
+        };
+        // No board can repair queens that attack each other but never move,
+        // nor fill a column whose squares are all blocked
+        solver.finished = !constraints.fixed_consistent(n)
+            || (0..n).any(|c| (0..n).all(|r| solver.blocked[r][c]));
+        if !solver.finished {
+            solver.randomize();
+        }
+        solver
+    }
+
+    /// Greedy random start: each column takes a least conflicted row
+    /// with respect to the columns before it and the pre-placed queens.
+    fn randomize(&mut self) {
+        for c in 0..self.n {
+            self.rows[c] = self.fixed[c].unwrap_or(0);
+        }
+        for c in 0..self.n {
+            if self.fixed[c].is_none() {
+                let row = self.best_row(c, c);
+                self.rows[c] = row;
+            }
+        }
+        self.sync_board();
+        self.moves_since_restart = 0;
+        self.last_moved = None;
+    }
+
+    fn sync_board(&mut self) {
+        for (c, &r) in self.rows.iter().enumerate() {
+            for row in 0..self.n {
+                self.board[row][c] = u8::from(row == r);
+            }
+        }
+    }
+
+    /// Queens in the first `upto` columns or pre-placed (other than `col`)
+    /// attacking (row, col).
+    fn conflicts(&self, row: usize, col: usize, upto: usize) -> usize {
+        (0..self.n)
+            .filter(|&c| c != col && (c < upto || self.fixed[c].is_some()))
+            .filter(|&c| {
+                self.piece.attacks(
+                    (self.n, self.n),
+                    self.topology,
+                    (self.rows[c], c),
+                    (row, col),
+                )
+            })
+            .count()
+    }
+
+    /// Least conflicted unblocked row for `col`, ties broken at random.
+    fn best_row(&mut self, col: usize, upto: usize) -> usize {
+        let rows: Vec<usize> = (0..self.n).filter(|&r| !self.blocked[r][col]).collect();
+        let scores: Vec<usize> = rows.iter().map(|&r| self.conflicts(r, col, upto)).collect();
+        let min = *scores.iter().min().unwrap();
+        let best: Vec<usize> = rows
+            .iter()
+            .zip(&scores)
+            .filter(|&(_, &score)| score == min)
+            .map(|(&r, _)| r)
+            .collect();
+        best[self.rng.below(best.len())]
+    }
+
+    fn restart(&mut self) {
+        self.restarts += 1;
+        self.stale_restarts += 1;
+        if self.stale_restarts >= MAX_STALE_RESTARTS {
+            self.finished = true;
+            return;
+        }
+        self.randomize();
+    }
+}
+
+impl Solver for MinConflictsSolver {
+    fn name(&self) -> &'static str {
+        Algorithm::MinConflicts.label()
+    }
+
+    fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.stats.steps += 1;
+
+        if self.restart_pending {
+            self.restart_pending = false;
+            self.restart();
+            return false;
+        }
+
+        let conflicted: Vec<usize> = (0..self.n)
+            .filter(|&c| self.fixed[c].is_none())
+            .filter(|&c| self.conflicts(self.rows[c], c, self.n) > 0)
+            .collect();
+
+        if conflicted.is_empty() {
+            self.restart_pending = true;
+            let squares: Vec<(usize, usize)> = (0..self.n).map(|c| (self.rows[c], c)).collect();
+            if self.log.contains(&squares) {
+                return false;
+            }
+            self.stale_restarts = 0;
+            self.log.record(&self.board);
+            self.stats.note_solution();
+            return true;
+        }
+
+        if self.moves_since_restart >= MOVES_PER_COLUMN * self.n {
+            self.restart();
+            return false;
+        }
+
+        let col = conflicted[self.rng.below(conflicted.len())];
+        let row = self.best_row(col, self.n);
+        self.rows[col] = row;
+        self.sync_board();
+        self.moves_since_restart += 1;
+        self.last_moved = Some((row, col));
+        self.stats.placements += 1;
+        false
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn stats(&self) -> SolverStats {
+        let mut stats = self.stats.clone();
+        stats.extra.push(("Restarts", self.restarts));
+        stats
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        self.last_moved
+    }
+}
@@ -0,0 +1,1003 @@
+mod backtracking;
+mod bitboard;
+mod browser;
+mod cube;
+mod domination;
+mod forward_checking;
+mod min_conflicts;
+mod ordering;
+mod peaceable;
+mod piece;
+mod placement;
+mod replay;
+mod sat_search;
+mod topology;
+
+pub use backtracking::SolverWrapper;
+pub use bitboard::BitboardSolver;
+pub use browser::SolutionBrowser;
+pub use cube::{Axis, CubeSolver, CUBE_STEP_BUDGET};
+pub use domination::DominationSolver;
+pub use forward_checking::{Domains, ForwardCheckingSolver};
+pub use min_conflicts::MinConflictsSolver;
+pub use ordering::{ColumnOrder, Ordering, RowOrder};
+pub use peaceable::PeaceableSolver;
+pub use piece::Piece;
+pub use placement::PlacementSolver;
+pub use replay::{Event, Trace, TraceRecorder, TraceReplay, MAX_EVENTS};
+pub use sat_search::SatSearch;
+pub use topology::Topology;
+
+use crate::cnf::Encoding;
+use crate::sat::Learning;
+
+/// `board[row][col] == 1` where a queen stands; peaceable queens put the
+/// second (black) army on 2s.
+pub type Board = Vec<Vec<u8>>;
+
+/// Counters every solver reports; `extra` holds algorithm specific ones.
+#[derive(Clone, Default)]
+pub struct SolverStats {
+    pub steps: u64,
+    pub placements: u64,
+    pub backtracks: u64,
+    /// Placements made when the first solution was completed.
+    pub nodes_to_first: Option<u64>,
+    pub extra: Vec<(&'static str, u64)>,
+}
+
+impl SolverStats {
+    pub fn note_solution(&mut self) {
+        self.nodes_to_first.get_or_insert(self.placements);
+    }
+}
+
+/// What the GUI needs from a search algorithm to animate it.
+pub trait Solver {
+    /// Advance the search by one visible step.
+    /// Returns true when this step completed a solution.
+    fn step(&mut self) -> bool;
+    fn board(&self) -> &Board;
+    fn solutions(&self) -> &SolutionLog;
+    fn is_finished(&self) -> bool;
+    fn stats(&self) -> SolverStats;
+    /// The search that actually runs, which `SolverConfig::build` may pick
+    /// instead of the selected algorithm.
+    fn name(&self) -> &'static str;
+    /// Put the last solution back on the board once the search has run out.
+    fn restore_last_solution(&mut self);
+
+    /// The queen placed by the latest step, faded in by the renderer.
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        None
+    }
+
+    /// Rows each column may still take, for solvers that track domains.
+    fn domains(&self) -> Option<&Domains> {
+        None
+    }
+
+    /// Squares the latest step tested and found attacked or blocked, for
+    /// solvers that try them one at a time.
+    fn rejected(&self) -> &[(usize, usize)] {
+        &[]
+    }
+
+    /// The trace so far, when the search is being recorded.
+    fn recording(&self) -> Option<&TraceRecorder> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Algorithm {
+    Backtracking,
+    Bitboard,
+    ForwardChecking,
+    MinConflicts,
+    SatCdcl,
+    SatDpll,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 6] = [
+        Algorithm::Backtracking,
+        Algorithm::Bitboard,
+        Algorithm::ForwardChecking,
+        Algorithm::MinConflicts,
+        Algorithm::SatCdcl,
+        Algorithm::SatDpll,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Algorithm::Backtracking => "Backtracking",
+            Algorithm::Bitboard => "Bitboard DFS",
+            Algorithm::ForwardChecking => "Forward Checking (CSP)",
+            Algorithm::MinConflicts => "Min-Conflicts",
+            Algorithm::SatCdcl => "SAT: CDCL",
+            Algorithm::SatDpll => "SAT: DPLL",
+        }
+    }
+
+    /// Exhaustive searches; running out of steps means no (more) solutions.
+    pub fn is_complete(self) -> bool {
+        self != Algorithm::MinConflicts
+    }
+
+    /// Whether the algorithm can search for `piece`. All of them fill one
+    /// column at a time, so the piece has to attack along files; bitboards
+    /// only know rook and queen lines on top of that, while the others ask
+    /// the piece about every pair of squares.
+    pub fn supports(self, piece: &Piece) -> bool {
+        match self {
+            Algorithm::Bitboard => matches!(piece, Piece::Queen | Piece::Rook),
+            Algorithm::Backtracking
+            | Algorithm::ForwardChecking
+            | Algorithm::MinConflicts
+            | Algorithm::SatCdcl
+            | Algorithm::SatDpll => matches!(piece, Piece::Queen | Piece::Rook | Piece::Amazon),
+        }
+    }
+}
+
+/// How many pieces a solution holds, or what they have to achieve.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Goal {
+    /// As many as the shorter side of the board, like n queens on n x n.
+    #[default]
+    Fill,
+    /// Exactly k pieces.
+    Place(usize),
+    /// As many as fit; only placements of the largest size count.
+    Maximum,
+    /// As few as possible that attack or occupy every free square,
+    /// optionally without attacking each other.
+    Domination { independent: bool },
+    /// Two armies of k pieces each, neither attacking the other, for the
+    /// largest k.
+    Peaceable,
+}
+
+impl Goal {
+    pub const ALL: [Goal; 6] = [
+        Goal::Fill,
+        Goal::Place(0),
+        Goal::Maximum,
+        Goal::Domination { independent: false },
+        Goal::Domination { independent: true },
+        Goal::Peaceable,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Goal::Fill => "Fill the board",
+            Goal::Place(_) => "Place k",
+            Goal::Maximum => "Maximum",
+            Goal::Domination { independent: false } => "Dominate",
+            Goal::Domination { independent: true } => "Dominate (independent)",
+            Goal::Peaceable => "Peaceable armies",
+        }
+    }
+
+    /// Same kind of goal, whatever k is.
+    pub fn same_kind(self, other: Goal) -> bool {
+        matches!((self, other), (Goal::Place(_), Goal::Place(_))) || self == other
+    }
+}
+
+/// Rows and columns a board may have, in the GUI as in files.
+pub const BOARD_SIZES: std::ops::RangeInclusive<usize> = 4..=30;
+
+/// What a search must respect beyond the plain n-queens rules.
+#[derive(Clone, Default, PartialEq)]
+pub struct Constraints {
+    /// Queens placed by the user before the search, as (row, col).
+    /// Solutions are completions of this partial placement.
+    pub fixed: Vec<(usize, usize)>,
+    /// Squares no queen may stand on, as (row, col).
+    pub blocked: Vec<(usize, usize)>,
+    /// Whether diagonals wrap around the edges.
+    pub topology: Topology,
+    /// What stands on the board; queens unless a fairy piece was picked.
+    pub piece: Piece,
+    /// Number of columns when the board is not square; n is the number of rows.
+    pub width: Option<usize>,
+    pub goal: Goal,
+}
+
+impl Constraints {
+    /// Columns of a board with n rows.
+    pub fn cols(&self, n: usize) -> usize {
+        self.width.unwrap_or(n)
+    }
+
+    /// Pieces a solution must hold; `None` when the search finds the count.
+    pub fn pieces(&self, n: usize) -> Option<usize> {
+        match self.goal {
+            Goal::Fill => Some(n.min(self.cols(n))),
+            Goal::Place(k) => Some(k),
+            Goal::Maximum | Goal::Domination { .. } | Goal::Peaceable => None,
+        }
+    }
+
+    /// Whether two pieces of these colours (1 white, 2 black) may not
+    /// attack each other: plain domination allows it, peaceable armies only
+    /// forbid it between the two colours.
+    pub fn conflicts(&self, a: u8, b: u8) -> bool {
+        match self.goal {
+            Goal::Domination { independent } => independent,
+            Goal::Peaceable => a != b,
+            _ => true,
+        }
+    }
+
+    /// The classic shape of the problem: a square board with one rook-like
+    /// piece per column, which is what the column based solvers search.
+    pub fn fills_columns(&self, n: usize) -> bool {
+        self.piece.moves_like_rook() && self.cols(n) == n && self.pieces(n) == Some(n)
+    }
+
+    /// Pieces on squares `a` and `b` of a board with n rows attack each other.
+    pub fn attacks(&self, n: usize, a: (usize, usize), b: (usize, usize)) -> bool {
+        self.piece.attacks((n, self.cols(n)), self.topology, a, b)
+    }
+
+    /// No two fixed pieces attack each other.
+    pub fn fixed_consistent(&self, n: usize) -> bool {
+        self.fixed
+            .iter()
+            .enumerate()
+            .all(|(i, &a)| self.fixed[i + 1..].iter().all(|&b| !self.attacks(n, a, b)))
+    }
+
+    /// Set when it is known without searching that nothing fits.
+    pub fn no_solution_reason(&self, n: usize) -> Option<String> {
+        if self.piece == Piece::Queen && self.fills_columns(n) {
+            if let Some(reason) = self.topology.no_solution_reason(n) {
+                return Some(reason);
+            }
+        }
+        // Fixed pieces all belong to the white army
+        if self.conflicts(1, 1) && !self.fixed_consistent(n) {
+            return Some("Some fixed pieces attack each other".to_owned());
+        }
+        if let Some(k) = self.pieces(n).filter(|&k| self.fixed.len() > k) {
+            return Some(format!(
+                "{} pieces are fixed but a solution holds {}",
+                self.fixed.len(),
+                k
+            ));
+        }
+        if self.fills_columns(n) {
+            let blocked = self.blocked_grid(n);
+            if let Some(col) = (0..n).find(|&c| (0..n).all(|r| blocked[r][c])) {
+                return Some(format!(
+                    "Every square of column {} is blocked",
+                    (b'a' + col as u8) as char
+                ));
+            }
+        }
+        None
+    }
+
+    pub fn fixed_row(&self, col: usize) -> Option<usize> {
+        self.fixed.iter().find(|&&(_, c)| c == col).map(|&(r, _)| r)
+    }
+
+    /// True when no squares are fixed or blocked (the topology doesn't count).
+    pub fn is_empty(&self) -> bool {
+        self.fixed.is_empty() && self.blocked.is_empty()
+    }
+
+    /// `grid[row][col]` is true on blocked squares.
+    pub fn blocked_grid(&self, n: usize) -> Vec<Vec<bool>> {
+        let mut grid = vec![vec![false; self.cols(n)]; n];
+        for &(r, c) in &self.blocked {
+            grid[r][c] = true;
+        }
+        grid
+    }
+
+    /// Add a fixed queen on (row, col), or remove it if one is already there.
+    pub fn toggle_fixed(&mut self, row: usize, col: usize) {
+        self.blocked.retain(|&b| b != (row, col));
+        toggle(&mut self.fixed, (row, col));
+    }
+
+    /// Block (row, col), or unblock it if it already is.
+    pub fn toggle_blocked(&mut self, row: usize, col: usize) {
+        self.fixed.retain(|&q| q != (row, col));
+        toggle(&mut self.blocked, (row, col));
+    }
+
+    /// Drop anything that falls outside a board with n rows.
+    pub fn fit(&mut self, n: usize) {
+        if self.width == Some(n) {
+            self.width = None;
+        }
+        let cols = self.cols(n);
+        self.fixed.retain(|&(r, c)| r < n && c < cols);
+        self.blocked.retain(|&(r, c)| r < n && c < cols);
+    }
+
+    /// The board as a mask: one line per row, `.` free, `#` blocked and
+    /// `Q` a fixed queen.
+    pub fn to_mask(&self, n: usize) -> Vec<String> {
+        (0..n)
+            .map(|row| {
+                (0..self.cols(n))
+                    .map(|col| {
+                        if self.fixed.contains(&(row, col)) {
+                            'Q'
+                        } else if self.blocked.contains(&(row, col)) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Read a mask written by `to_mask`, rows separated by newlines or `/`.
+    /// Returns the number of rows along with the constraints; a mask with
+    /// rows of another length sets the board width.
+    pub fn from_mask(text: &str) -> Result<(usize, Self), String> {
+        let lines: Vec<&str> = text
+            .split(['\n', '/'])
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let n = lines.len();
+        if !BOARD_SIZES.contains(&n) {
+            return Err(format!("mask has {} rows, expected 4-30", n));
+        }
+        let cols = lines[0].chars().count();
+        if !BOARD_SIZES.contains(&cols) {
+            return Err(format!("mask rows have {} squares, expected 4-30", cols));
+        }
+        let mut constraints = Self {
+            width: (cols != n).then_some(cols),
+            ..Self::default()
+        };
+        for (row, line) in lines.iter().enumerate() {
+            if line.chars().count() != cols {
+                return Err(format!(
+                    "mask row {} has {} squares, expected {}",
+                    row + 1,
+                    line.chars().count(),
+                    cols
+                ));
+            }
+            for (col, square) in line.chars().enumerate() {
+                match square {
+                    '.' => {}
+                    '#' => constraints.blocked.push((row, col)),
+                    'Q' | 'q' => constraints.fixed.push((row, col)),
+                    other => {
+                        return Err(format!("unexpected \"{}\" in mask row {}", other, row + 1))
+                    }
+                }
+            }
+        }
+        Ok((n, constraints))
+    }
+}
+
+fn toggle(squares: &mut Vec<(usize, usize)>, square: (usize, usize)) {
+    match squares.iter().position(|&s| s == square) {
+        Some(i) => {
+            squares.remove(i);
+        }
+        None => squares.push(square),
+    }
+}
+
+/// Algorithm plus the knobs it understands; the app rebuilds its solver from this.
+#[derive(Clone, PartialEq)]
+pub struct SolverConfig {
+    pub algorithm: Algorithm,
+    /// Forward checking: also enforce arc consistency after each assignment.
+    pub arc_consistency: bool,
+    /// Backtracking and forward checking: column and row ordering heuristics.
+    pub ordering: Ordering,
+    /// SAT solvers and CNF export: how at-most-one constraints become clauses.
+    pub encoding: Encoding,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::Backtracking,
+            arc_consistency: false,
+            ordering: Ordering::default(),
+            encoding: Encoding::Pairwise,
+        }
+    }
+}
+
+impl SolverConfig {
+    pub fn uses_encoding(&self) -> bool {
+        matches!(self.algorithm, Algorithm::SatCdcl | Algorithm::SatDpll)
+    }
+
+    pub fn uses_ordering(&self) -> bool {
+        matches!(
+            self.algorithm,
+            Algorithm::Backtracking | Algorithm::ForwardChecking
+        )
+    }
+
+    pub fn build(&self, n: usize, constraints: &Constraints) -> Box<dyn Solver> {
+        if constraints.no_solution_reason(n).is_some() {
+            // Known to be unsolvable; nothing to search
+            return Box::new(SolutionBrowser::new(n, constraints, Vec::new()));
+        }
+        match constraints.goal {
+            Goal::Domination { independent } => {
+                return Box::new(DominationSolver::new(n, constraints, independent))
+            }
+            Goal::Peaceable => return Box::new(PeaceableSolver::new(n, constraints)),
+            _ => {}
+        }
+        if !constraints.fills_columns(n) {
+            // Columns may hold no piece or several, so search square by square
+            return Box::new(PlacementSolver::new(n, constraints));
+        }
+        if !self.algorithm.supports(&constraints.piece) {
+            return Box::new(SolverWrapper::new(n, self.ordering, constraints));
+        }
+        match self.algorithm {
+            Algorithm::Backtracking => Box::new(SolverWrapper::new(n, self.ordering, constraints)),
+            Algorithm::Bitboard => Box::new(BitboardSolver::new(n, constraints)),
+            Algorithm::ForwardChecking => Box::new(ForwardCheckingSolver::new(
+                n,
+                self.arc_consistency,
+                self.ordering,
+                constraints,
+            )),
+            Algorithm::MinConflicts => Box::new(MinConflictsSolver::new(n, constraints)),
+            Algorithm::SatCdcl => Box::new(SatSearch::new(
+                n,
+                self.encoding,
+                Learning::FirstUip,
+                constraints,
+            )),
+            Algorithm::SatDpll => Box::new(SatSearch::new(
+                n,
+                self.encoding,
+                Learning::Decisions,
+                constraints,
+            )),
+        }
+    }
+}
+
+/// Steps "Compare orderings" spends on one ordering before giving up.
+pub const COMPARE_STEP_BUDGET: u64 = 2_000_000;
+
+/// Nodes to the first solution per ordering; `None` means the step budget ran out.
+pub type OrderingReport = Vec<(Ordering, Option<u64>)>;
+
+/// Runs `config` with every column/row ordering to its first solution, one
+/// search step per `step()` so the GUI can spread it over frames.
+pub struct OrderingComparison {
+    config: SolverConfig,
+    n: usize,
+    constraints: Constraints,
+    // The ordering being run and the steps it has taken
+    current: Option<(Box<dyn Solver>, u64)>,
+    pub report: OrderingReport,
+}
+
+impl OrderingComparison {
+    /// Orderings compared: every column order with every row order.
+    pub const TOTAL: usize = ColumnOrder::ALL.len() * RowOrder::ALL.len();
+
+    pub fn new(config: &SolverConfig, n: usize, constraints: &Constraints) -> Self {
+        Self {
+            config: config.clone(),
+            n,
+            constraints: constraints.clone(),
+            current: None,
+            report: Vec::new(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.report.len() == Self::TOTAL
+    }
+
+    /// Steps run so far on the ordering being compared.
+    pub fn current_steps(&self) -> u64 {
+        self.current.as_ref().map_or(0, |(_, steps)| *steps)
+    }
+
+    pub fn step(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+        let ordering = self.ordering(self.report.len());
+        let (solver, steps) = self.current.get_or_insert_with(|| {
+            let solver = SolverConfig {
+                ordering,
+                ..self.config.clone()
+            }
+            .build(self.n, &self.constraints);
+            (solver, 0)
+        });
+        let solved = if solver.is_finished() || *steps >= COMPARE_STEP_BUDGET {
+            true
+        } else {
+            *steps += 1;
+            solver.step()
+        };
+        if solved || solver.is_finished() {
+            self.report.push((ordering, solver.stats().nodes_to_first));
+            self.current = None;
+        }
+    }
+
+    fn ordering(&self, i: usize) -> Ordering {
+        Ordering {
+            columns: ColumnOrder::ALL[i / RowOrder::ALL.len()],
+            rows: RowOrder::ALL[i % RowOrder::ALL.len()],
+            seed: self.config.ordering.seed,
+        }
+    }
+}
+
+/// Solutions in the order they were found.
+#[derive(Default)]
+pub struct SolutionLog {
+    /// Chess notation, prefixed with "(Sym)" when a symmetric variant was seen before.
+    pub solutions: Vec<String>,
+    /// Occupied squares of every solution as from `squares`, parallel to `solutions`.
+    pub placements: Vec<Vec<(usize, usize)>>,
+    /// The board value (1 white, 2 black) of each square in `placements`.
+    pub colours: Vec<Vec<u8>>,
+    pub last_board: Option<Board>,
+    // Decides which boards count as symmetric variants
+    topology: Topology,
+}
+
+impl SolutionLog {
+    pub fn new(topology: Topology) -> Self {
+        Self {
+            topology,
+            ..Self::default()
+        }
+    }
+
+    pub fn contains(&self, squares: &[(usize, usize)]) -> bool {
+        self.placements.iter().any(|p| p == squares)
+    }
+
+    pub fn record(&mut self, board: &Board) {
+        let squares = squares(board);
+        let colours: Vec<u8> = squares.iter().map(|&(r, c)| board[r][c]).collect();
+        let size = (board.len(), board[0].len());
+        let (sol_str, unique) = if colours.contains(&2) {
+            let (white, black) = armies(&squares, &colours);
+            (
+                army_notation(&squares, &colours),
+                self.is_new_unique_armies(&white, &black, size),
+            )
+        } else {
+            (notation(&squares), self.is_new_unique(&squares, size))
+        };
+        if unique {
+            self.solutions.push(sol_str);
+        } else {
+            // Already seen a variant of this
+            self.solutions.push(format!("(Sym) {}", sol_str));
+        }
+        self.placements.push(squares);
+        self.colours.push(colours);
+        self.last_board = Some(board.clone());
+    }
+
+    fn is_new_unique(&self, sol: &[(usize, usize)], size: (usize, usize)) -> bool {
+        get_variants(sol, size, self.topology)
+            .iter()
+            .all(|v| !self.contains(v))
+    }
+
+    /// Like `is_new_unique` for two armies; swapping the colours also
+    /// gives a variant.
+    fn is_new_unique_armies(
+        &self,
+        white: &[(usize, usize)],
+        black: &[(usize, usize)],
+        size: (usize, usize),
+    ) -> bool {
+        let whites = get_variants(white, size, self.topology);
+        let blacks = get_variants(black, size, self.topology);
+        self.placements
+            .iter()
+            .zip(&self.colours)
+            .all(|(squares, colours)| {
+                let seen = armies(squares, colours);
+                whites
+                    .iter()
+                    .zip(&blacks)
+                    .all(|(w, b)| (w, b) != (&seen.0, &seen.1) && (b, w) != (&seen.0, &seen.1))
+            })
+    }
+
+    /// The list shown in the history panel and written by the exporters.
+    pub fn display(&self, only_unique: bool) -> Vec<String> {
+        self.listed(only_unique)
+            .into_iter()
+            .map(|i| self.solutions[i].clone())
+            .collect()
+    }
+
+    /// Indices of the solutions `display` lists.
+    pub fn listed(&self, only_unique: bool) -> Vec<usize> {
+        (0..self.solutions.len())
+            .filter(|&i| !(only_unique && self.solutions[i].starts_with("(Sym)")))
+            .collect()
+    }
+
+    /// The listed solutions on boards of (rows, cols), each with its number
+    /// in the list and its notation, as figure captions.
+    pub fn captioned(&self, only_unique: bool, size: (usize, usize)) -> Vec<(String, Board)> {
+        self.listed(only_unique)
+            .into_iter()
+            .enumerate()
+            .map(|(k, i)| {
+                let notation = self.solutions[i].trim_start_matches("(Sym) ");
+                (format!("#{} {}", k + 1, notation), self.board(i, size))
+            })
+            .collect()
+    }
+
+    /// Solution `i` on a board of (rows, cols), in its colours.
+    fn board(&self, i: usize, size: (usize, usize)) -> Board {
+        let mut board = vec![vec![0; size.1]; size.0];
+        for (&(r, c), &colour) in self.placements[i].iter().zip(&self.colours[i]) {
+            board[r][c] = colour;
+        }
+        board
+    }
+}
+
+/// Occupied (row, col) squares, ordered by column and then row. For one
+/// piece per column this is the queen row of each column in turn.
+pub fn squares(board: &Board) -> Vec<(usize, usize)> {
+    let mut squares: Vec<(usize, usize)> = board
+        .iter()
+        .enumerate()
+        .flat_map(|(r, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|&(_, &cell)| cell != 0)
+                .map(move |(c, _)| (r, c))
+        })
+        .collect();
+    sort_squares(&mut squares);
+    squares
+}
+
+fn sort_squares(squares: &mut [(usize, usize)]) {
+    squares.sort_unstable_by_key(|&(r, c)| (c, r));
+}
+
+/// The squares of one colour of peaceable pieces.
+pub type Army = Vec<(usize, usize)>;
+
+/// Split squares into the white and the black army by their `colours`.
+pub fn armies(squares: &[(usize, usize)], colours: &[u8]) -> (Army, Army) {
+    let army = |black: bool| {
+        squares
+            .iter()
+            .zip(colours)
+            .filter(|&(_, &colour)| (colour == 2) == black)
+            .map(|(&square, _)| square)
+            .collect()
+    };
+    (army(false), army(true))
+}
+
+/// `notation` with each square prefixed by its army, e.g. "Wa1, Bc2".
+pub fn army_notation(squares: &[(usize, usize)], colours: &[u8]) -> String {
+    squares
+        .iter()
+        .zip(colours)
+        .map(|(&(r, c), &colour)| {
+            format!(
+                "{}{}{}",
+                if colour == 2 { 'B' } else { 'W' },
+                (b'a' + c as u8) as char,
+                r + 1
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// True when a piece other than one standing on (row, col) attacks it.
+/// Between peaceable armies only pieces of the other colour count.
+pub fn is_threatened(board: &Board, row: usize, col: usize, constraints: &Constraints) -> bool {
+    let n = board.len();
+    board.iter().enumerate().any(|(r, cells)| {
+        cells.iter().enumerate().any(|(c, &cell)| {
+            cell != 0
+                && (r, c) != (row, col)
+                && !(constraints.goal == Goal::Peaceable && cell == board[row][col])
+                && constraints.attacks(n, (r, c), (row, col))
+        })
+    })
+}
+
+pub fn notation(squares: &[(usize, usize)]) -> String {
+    squares
+        .iter()
+        .map(|&(r, c)| format!("{}{}", (b'a' + c as u8) as char, r + 1))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Pieces as (col, row, colour), from `class_key`.
+pub type ClassKey = Vec<(usize, usize, u8)>;
+
+/// Sort key for picking one board of a symmetry class: the pieces as
+/// (col, row, colour), column by column. The canonical board of a class is
+/// the variant with the smallest key; with one piece per column that is the
+/// variant with the lowest rows first, the one the column searches find
+/// first.
+pub fn class_key(squares: &[(usize, usize)], colours: &[u8]) -> ClassKey {
+    let mut key: ClassKey = squares
+        .iter()
+        .zip(colours)
+        .map(|(&(r, c), &colour)| (c, r, colour))
+        .collect();
+    key.sort_unstable();
+    key
+}
+
+/// The images of a solution on a (rows, cols) board under its symmetries:
+/// the 8 rotations and reflections of a square, only the 2 reflections and
+/// the half turn of a rectangle, on the torus also combined with every
+/// translation. Each comes back ordered like `squares`.
+pub fn get_variants(
+    sol: &[(usize, usize)],
+    size: (usize, usize),
+    topology: Topology,
+) -> Vec<Vec<(usize, usize)>> {
+    let (rows, cols) = size;
+    let mut variants = Vec::new();
+
+    // 1. Convert to (x, y) coordinates
+    let coords: Vec<(usize, usize)> = sol.iter().map(|&(y, x)| (x, y)).collect();
+
+    // Helper to convert back to ordered squares
+    let to_sol = |pts: &[(usize, usize)]| -> Vec<(usize, usize)> {
+        let mut v: Vec<(usize, usize)> = pts.iter().map(|&(x, y)| (y, x)).collect();
+        sort_squares(&mut v);
+        v
+    };
+
+    // All 8 transformations
+    // (x, y) ->
+    // 1. (x, y)
+    // 2. (y, n-1-x) - rotate 90
+    // 3. (n-1-x, n-1-y) - rotate 180
+    // 4. (n-1-y, x) - rotate 270
+    // 5. (n-1-x, y) - flip H
+    // 6. (x, n-1-y) - flip V
+    // 7. (y, x) - flip D1
+    // 8. (n-1-y, n-1-x) - flip D2
+
+    if rows == cols {
+        let n = rows;
+        let mut curr = coords.clone();
+        for _ in 0..4 {
+            // Rotate
+            variants.push(to_sol(&curr));
+            // Flip H
+            let flipped: Vec<(usize, usize)> = curr.iter().map(|&(x, y)| (n - 1 - x, y)).collect();
+            variants.push(to_sol(&flipped));
+
+            // Apply 90 rotation for next iteration
+            curr = curr.iter().map(|&(x, y)| (y, n - 1 - x)).collect();
+        }
+    } else {
+        // A rectangle has no quarter turns or diagonal flips: identity,
+        // flip H, flip V and rotate 180 only
+        for (flip_x, flip_y) in [(false, false), (true, false), (false, true), (true, true)] {
+            let image: Vec<(usize, usize)> = coords
+                .iter()
+                .map(|&(x, y)| {
+                    (
+                        if flip_x { cols - 1 - x } else { x },
+                        if flip_y { rows - 1 - y } else { y },
+                    )
+                })
+                .collect();
+            variants.push(to_sol(&image));
+        }
+    }
+
+    if topology == Topology::Toroidal {
+        variants = variants
+            .iter()
+            .flat_map(|v| {
+                (0..rows).flat_map(move |dr| (0..cols).map(move |dc| translate(v, size, dr, dc)))
+            })
+            .collect();
+    }
+    variants
+}
+
+/// Shift every square by (dr, dc), wrapping around the torus.
+fn translate(
+    sol: &[(usize, usize)],
+    (rows, cols): (usize, usize),
+    dr: usize,
+    dc: usize,
+) -> Vec<(usize, usize)> {
+    let mut moved: Vec<(usize, usize)> = sol
+        .iter()
+        .map(|&(r, c)| ((r + dr) % rows, (c + dc) % cols))
+        .collect();
+    sort_squares(&mut moved);
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(
+        algorithm: Algorithm,
+        n: usize,
+        constraints: &Constraints,
+    ) -> Vec<Vec<(usize, usize)>> {
+        let config = SolverConfig {
+            algorithm,
+            ..SolverConfig::default()
+        };
+        let mut solver = config.build(n, constraints);
+        while !solver.is_finished() {
+            solver.step();
+        }
+        let mut placements = solver.solutions().placements.clone();
+        placements.sort();
+        placements
+    }
+
+    const COMPLETE: [Algorithm; 5] = [
+        Algorithm::Backtracking,
+        Algorithm::Bitboard,
+        Algorithm::ForwardChecking,
+        Algorithm::SatCdcl,
+        Algorithm::SatDpll,
+    ];
+
+    #[test]
+    fn completions_keep_the_fixed_queens_and_avoid_blocked_squares() {
+        let all = solve(Algorithm::Bitboard, 8, &Constraints::default());
+        let cases = [
+            // Four of the 92 have a queen in the corner
+            (vec![(0, 0)], vec![], 4),
+            (vec![(0, 0), (4, 1)], vec![], 1),
+            (vec![], vec![(0, 0), (3, 3), (4, 4)], 72),
+            (vec![(3, 0)], vec![(1, 7)], 15),
+        ];
+        for (fixed, blocked, count) in cases {
+            let constraints = Constraints {
+                fixed: fixed.clone(),
+                blocked: blocked.clone(),
+                ..Constraints::default()
+            };
+            let expected: Vec<_> = all
+                .iter()
+                .filter(|p| fixed.iter().all(|q| p.contains(q)))
+                .filter(|p| blocked.iter().all(|b| !p.contains(b)))
+                .cloned()
+                .collect();
+            assert_eq!(expected.len(), count, "{:?} {:?}", fixed, blocked);
+            for algorithm in COMPLETE {
+                assert_eq!(
+                    solve(algorithm, 8, &constraints),
+                    expected,
+                    "{:?} with {:?} fixed, {:?} blocked",
+                    algorithm,
+                    fixed,
+                    blocked
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn supported_pieces_count_right() {
+        for algorithm in Algorithm::ALL {
+            let supported: Vec<&str> = Piece::PRESETS
+                .iter()
+                .filter(|piece| algorithm.supports(piece))
+                .map(Piece::key)
+                .collect();
+            let expected = match algorithm {
+                Algorithm::Bitboard => vec!["queen", "rook"],
+                _ => vec!["queen", "rook", "amazon"],
+            };
+            assert_eq!(supported, expected, "{:?}", algorithm);
+        }
+        // Rooks fill an n x n board in n! ways; amazons first fit on 10 x 10
+        let cases = [
+            (Piece::Queen, 6, 4),
+            (Piece::Rook, 5, 120),
+            (Piece::Amazon, 10, 4),
+        ];
+        for (piece, n, count) in cases {
+            let constraints = Constraints {
+                piece: piece.clone(),
+                ..Constraints::default()
+            };
+            for algorithm in COMPLETE.into_iter().filter(|a| a.supports(&piece)) {
+                assert_eq!(
+                    solve(algorithm, n, &constraints).len(),
+                    count,
+                    "{:?} {}",
+                    algorithm,
+                    piece.key()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn most_kings_and_knights() {
+        // Largest placements and the number of ways to make them
+        let cases = [
+            (Piece::King, 4, 4, 79),
+            (Piece::King, 5, 9, 1),
+            (Piece::Knight, 4, 8, 6),
+            (Piece::Knight, 5, 13, 1),
+            (Piece::Knight, 6, 18, 2),
+        ];
+        for (piece, n, most, ways) in cases {
+            let constraints = Constraints {
+                piece: piece.clone(),
+                goal: Goal::Maximum,
+                ..Constraints::default()
+            };
+            let found = solve(Algorithm::Backtracking, n, &constraints);
+            assert_eq!(found.len(), ways, "{} on {}x{}", piece.key(), n, n);
+            assert!(found.iter().all(|p| p.len() == most));
+        }
+    }
+
+    #[test]
+    fn impossible_layouts_have_a_reason() {
+        let with = |fixed: Vec<(usize, usize)>, blocked: Vec<(usize, usize)>| Constraints {
+            fixed,
+            blocked,
+            ..Constraints::default()
+        };
+        assert_eq!(with(vec![(0, 0)], vec![]).no_solution_reason(8), None);
+        assert!(with(vec![(0, 0), (2, 2)], vec![])
+            .no_solution_reason(8)
+            .is_some());
+        assert!(with(vec![], (0..8).map(|r| (r, 5)).collect())
+            .no_solution_reason(8)
+            .is_some_and(|reason| reason.contains("column f")));
+        assert!(with(vec![], vec![]).no_solution_reason(3).is_some());
+        // Plain domination lets fixed queens attack each other
+        let domination = Constraints {
+            goal: Goal::Domination { independent: false },
+            ..with(vec![(0, 0), (2, 2)], vec![])
+        };
+        assert_eq!(domination.no_solution_reason(8), None);
+        // The search has nothing to do
+        let config = SolverConfig::default();
+        let solver = config.build(8, &with(vec![(0, 0), (1, 1)], vec![]));
+        assert!(solver.is_finished() && solver.solutions().solutions.is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+use super::{Board, Constraints, SolutionLog, Solver, SolverStats, Topology};
+
+/// Whether the search is still raising k or listing the armies of the
+/// largest k.
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Probe,
+    Enumerate,
+}
+
+/// Peaceable queens: k white and k black pieces with no piece attacking
+/// one of the other colour, for the largest k. Only the white army is
+/// searched, square by square; the black one needs k squares no white
+/// piece attacks, and takes the first k of them. Fixed pieces join the
+/// white army.
+///
+/// Each k is probed up to its first solution; once a k fails, the armies
+/// of the k before it are listed in full.
+pub struct PeaceableSolver {
+    board: Board,
+    log: SolutionLog,
+    topology: Topology,
+
+    // Squares by index (col * n + row)
+    squares: Vec<(usize, usize)>,
+    // Squares each square attacks, itself included
+    covers: Vec<Vec<usize>>,
+    // White pieces covering each square, and whether pieces may stand there
+    coverage: Vec<usize>,
+    free: Vec<bool>,
+
+    // DFS State: the white squares placed, the next square to try and the
+    // black army of the solution on the board
+    stack: Vec<usize>,
+    fixed: usize,
+    cell: usize,
+    black: Vec<usize>,
+    // Pieces per army in the current round
+    size: usize,
+    phase: Phase,
+    // The probe just succeeded; the next step tries one piece more
+    raise: bool,
+
+    backtracking: bool,
+    finished: bool,
+    stats: SolverStats,
+}
+
+impl PeaceableSolver {
+    pub fn new(n: usize, constraints: &Constraints) -> Self {
+        let cols = constraints.cols(n);
+        let squares: Vec<(usize, usize)> = (0..cols)
+            .flat_map(|c| (0..n).map(move |r| (r, c)))
+            .collect();
+        let covers: Vec<Vec<usize>> = squares
+            .iter()
+            .map(|&a| {
+                (0..squares.len())
+                    .filter(|&j| constraints.attacks(n, a, squares[j]))
+                    .collect()
+            })
+            .collect();
+        let blocked = constraints.blocked_grid(n);
+        let mut solver = Self {
+            board: vec![vec![0; cols]; n],
+            log: SolutionLog::new(constraints.topology),
+            topology: constraints.topology,
+            free: squares.iter().map(|&(r, c)| !blocked[r][c]).collect(),
+            coverage: vec![0; squares.len()],
+            squares,
+            covers,
+            stack: Vec::new(),
+            fixed: constraints.fixed.len(),
+            cell: 0,
+            black: Vec::new(),
+            size: constraints.fixed.len().max(1),
+            phase: Phase::Probe,
+            raise: false,
+            backtracking: false,
+            finished: false,
+            stats: SolverStats::default(),
+        };
+        for &(r, c) in &constraints.fixed {
+            solver.put(c * n + r);
+        }
+        solver
+    }
+
+    fn put(&mut self, i: usize) {
+        let (r, c) = self.squares[i];
+        self.board[r][c] = 1;
+        for &j in &self.covers[i] {
+            self.coverage[j] += 1;
+        }
+    }
+
+    fn lift(&mut self, i: usize) {
+        let (r, c) = self.squares[i];
+        self.board[r][c] = 0;
+        for &j in &self.covers[i] {
+            self.coverage[j] -= 1;
+        }
+    }
+
+    /// Squares a black piece may take.
+    fn safe(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.squares.len()).filter(|&i| self.free[i] && self.coverage[i] == 0)
+    }
+
+    fn clear_black(&mut self) {
+        for i in self.black.drain(..) {
+            let (r, c) = self.squares[i];
+            self.board[r][c] = 0;
+        }
+    }
+
+    /// Take the white army back to the fixed pieces and search `size`.
+    fn start_round(&mut self, size: usize, phase: Phase) {
+        self.clear_black();
+        while let Some(i) = self.stack.pop() {
+            self.lift(i);
+        }
+        self.cell = 0;
+        self.size = size;
+        self.phase = phase;
+        self.backtracking = false;
+    }
+}
+
+impl Solver for PeaceableSolver {
+    fn name(&self) -> &'static str {
+        "Peaceable armies search"
+    }
+
+    fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.stats.steps += 1;
+
+        if self.raise {
+            self.raise = false;
+            self.start_round(self.size + 1, Phase::Probe);
+            return false;
+        }
+
+        if self.backtracking {
+            self.clear_black();
+            if let Some(i) = self.stack.pop() {
+                self.lift(i);
+                self.cell = i + 1;
+                self.backtracking = false;
+                self.stats.backtracks += 1;
+            } else if self.phase == Phase::Probe && self.size > self.fixed.max(1) {
+                // No armies of this size: list those one smaller
+                self.log = SolutionLog::new(self.topology);
+                self.start_round(self.size - 1, Phase::Enumerate);
+                return false;
+            } else {
+                self.finished = true;
+                return false;
+            }
+        }
+
+        let placed = self.stack.len() + self.fixed;
+        if placed >= self.size {
+            if self.safe().count() < self.size {
+                self.backtracking = true;
+                return false;
+            }
+            self.black = self.safe().take(self.size).collect();
+            for &i in &self.black {
+                let (r, c) = self.squares[i];
+                self.board[r][c] = 2;
+            }
+            if self.phase == Phase::Probe {
+                // Keep only the largest armies found so far
+                self.log = SolutionLog::new(self.topology);
+                self.log.record(&self.board);
+                self.stats.note_solution();
+                self.raise = true;
+                return true;
+            }
+            self.log.record(&self.board);
+            self.stats.note_solution();
+            self.backtracking = true;
+            return true;
+        }
+
+        // More white pieces only take safe squares away
+        let total = self.squares.len();
+        while self.cell < total
+            && placed + (total - self.cell) >= self.size
+            && self.safe().count() >= self.size
+        {
+            let i = self.cell;
+            self.cell += 1;
+            let (r, c) = self.squares[i];
+            if self.free[i] && self.board[r][c] == 0 {
+                self.put(i);
+                self.stack.push(i);
+                self.stats.placements += 1;
+                return false;
+            }
+        }
+
+        self.backtracking = true;
+        false
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        self.stack.last().map(|&i| self.squares[i])
+    }
+}
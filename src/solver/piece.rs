@@ -0,0 +1,153 @@
+use super::Topology;
+
+/// The piece placed on the board. Riders attack along whole lines (wrapping
+/// on the torus), leapers jump by fixed offsets.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum Piece {
+    #[default]
+    Queen,
+    Rook,
+    Bishop,
+    King,
+    Knight,
+    /// Queen + knight, also called superqueen.
+    Amazon,
+    /// Jumps by each (a, b) offset in all eight directions.
+    Leaper(Vec<(usize, usize)>),
+}
+
+impl Piece {
+    /// The built-in pieces; a custom leaper is made with `parse_leaper`.
+    pub const PRESETS: [Piece; 6] = [
+        Piece::Queen,
+        Piece::Rook,
+        Piece::Bishop,
+        Piece::King,
+        Piece::Knight,
+        Piece::Amazon,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Piece::Queen => "Queen",
+            Piece::Rook => "Rook",
+            Piece::Bishop => "Bishop",
+            Piece::King => "King",
+            Piece::Knight => "Knight",
+            Piece::Amazon => "Amazon (queen + knight)",
+            Piece::Leaper(_) => "Custom leaper",
+        }
+    }
+
+    /// Short name used on the command line and in exported headers.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Piece::Queen => "queen",
+            Piece::Rook => "rook",
+            Piece::Bishop => "bishop",
+            Piece::King => "king",
+            Piece::Knight => "knight",
+            Piece::Amazon => "amazon",
+            Piece::Leaper(_) => "leaper",
+        }
+    }
+
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Piece::Queen | Piece::Amazon => "♛",
+            Piece::Rook => "♜",
+            Piece::Bishop => "♝",
+            Piece::King => "♚",
+            Piece::Knight | Piece::Leaper(_) => "♞",
+        }
+    }
+
+    /// Small mark drawn next to the glyph of compound and custom pieces.
+    pub fn badge(&self) -> Option<&'static str> {
+        match self {
+            Piece::Amazon => Some("♞"),
+            Piece::Leaper(_) => Some("*"),
+            _ => None,
+        }
+    }
+
+    /// Attacks along ranks and files, so a solution has one piece per column.
+    pub fn moves_like_rook(&self) -> bool {
+        matches!(self, Piece::Queen | Piece::Rook | Piece::Amazon)
+    }
+
+    /// Attacks along diagonals.
+    pub fn moves_like_bishop(&self) -> bool {
+        matches!(self, Piece::Queen | Piece::Bishop | Piece::Amazon)
+    }
+
+    fn leaps(&self) -> &[(usize, usize)] {
+        match self {
+            Piece::King => &[(0, 1), (1, 1)],
+            Piece::Knight | Piece::Amazon => &[(1, 2)],
+            Piece::Leaper(offsets) => offsets,
+            _ => &[],
+        }
+    }
+
+    /// Pieces on (row, col) squares `a` and `b` of a board of `size`
+    /// (rows, cols) attack each other.
+    pub fn attacks(
+        &self,
+        size: (usize, usize),
+        topology: Topology,
+        a: (usize, usize),
+        b: (usize, usize),
+    ) -> bool {
+        if a == b {
+            return true;
+        }
+        // Row and column distances; on the torus both ways around count
+        let (rows, cols) = match topology {
+            Topology::Standard => (vec![a.0.abs_diff(b.0)], vec![a.1.abs_diff(b.1)]),
+            Topology::Toroidal => (wrapped(a.0, b.0, size.0), wrapped(a.1, b.1, size.1)),
+        };
+        rows.iter().any(|&dr| {
+            cols.iter().any(|&dc| {
+                (self.moves_like_rook() && (dr == 0 || dc == 0))
+                    || (self.moves_like_bishop() && dr == dc)
+                    || self
+                        .leaps()
+                        .iter()
+                        .any(|&(p, q)| (dr, dc) == (p, q) || (dr, dc) == (q, p))
+            })
+        })
+    }
+
+    /// Read leaper offsets such as "1,3" or "1,2; 2,2".
+    pub fn parse_leaper(text: &str) -> Result<Piece, String> {
+        let mut offsets = Vec::new();
+        for pair in text.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let parsed: Vec<usize> = pair
+                .trim_matches(|c| c == '(' || c == ')')
+                .split(',')
+                .map(|x| x.trim().parse::<usize>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("\"{}\" is not an offset like 1,2", pair))?;
+            match parsed[..] {
+                [0, 0] => return Err("the offset 0,0 does not move".to_owned()),
+                [a, b] => offsets.push((a, b)),
+                _ => return Err(format!("\"{}\" is not an offset like 1,2", pair)),
+            }
+        }
+        if offsets.is_empty() {
+            return Err("enter at least one offset, e.g. 1,3".to_owned());
+        }
+        Ok(Piece::Leaper(offsets))
+    }
+}
+
+/// Distances from `a` to `b` going either way around a cycle of length n.
+fn wrapped(a: usize, b: usize, n: usize) -> Vec<usize> {
+    let d = (a + n - b) % n;
+    if d == 0 {
+        vec![0]
+    } else {
+        vec![d, n - d]
+    }
+}
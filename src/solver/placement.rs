@@ -0,0 +1,262 @@
+use super::{Board, Constraints, Piece, SolutionLog, Solver, SolverStats, Topology};
+
+/// Depth-first search over squares instead of columns, for everything the
+/// column based solvers can't express: pieces that may share a column
+/// (bishops, kings, knights, leapers), rectangular boards, k pieces, and the
+/// maximum number of pieces. Squares are tried column by column, top to
+/// bottom, so each placement is found once.
+pub struct PlacementSolver {
+    n: usize,
+    cols: usize,
+    board: Board,
+    log: SolutionLog,
+
+    // DFS State
+    stack: Vec<(usize, usize)>,
+    // Pre-placed pieces count towards the pieces to place
+    fixed: Vec<(usize, usize)>,
+    blocked: Vec<Vec<bool>>,
+    piece: Piece,
+    topology: Topology,
+    // Pieces per solution, or `None` to look for the largest placements
+    target: Option<usize>,
+    // Size of the largest placement found so far when maximising
+    best: usize,
+
+    // Index (col * n + row) of the next square to try
+    cell: usize,
+
+    backtracking: bool,
+    finished: bool,
+    stats: SolverStats,
+}
+
+impl PlacementSolver {
+    pub fn new(n: usize, constraints: &Constraints) -> Self {
+        let cols = constraints.cols(n);
+        let fixed = constraints.fixed.clone();
+        let mut board = vec![vec![0; cols]; n];
+        for &(r, c) in &fixed {
+            board[r][c] = 1;
+        }
+        Self {
+            n,
+            cols,
+            board,
+            log: SolutionLog::new(constraints.topology),
+            stack: Vec::new(),
+            fixed,
+            blocked: constraints.blocked_grid(n),
+            piece: constraints.piece.clone(),
+            topology: constraints.topology,
+            target: constraints.pieces(n),
+            best: 0,
+            cell: 0,
+            backtracking: false,
+            finished: !constraints.fixed_consistent(n)
+                || constraints
+                    .pieces(n)
+                    .is_some_and(|k| constraints.fixed.len() > k),
+            stats: SolverStats::default(),
+        }
+    }
+
+    fn is_safe(&self, row: usize, col: usize) -> bool {
+        !self.blocked[row][col]
+            && self.board[row][col] == 0
+            && self.stack.iter().chain(&self.fixed).all(|&p| {
+                !self
+                    .piece
+                    .attacks((self.n, self.cols), self.topology, p, (row, col))
+            })
+    }
+
+    /// Most pieces the squares from `cell` on could still take.
+    fn capacity(&self) -> usize {
+        let cells = self.n * self.cols - self.cell;
+        if self.piece.moves_like_rook() {
+            // At most one more per column left
+            cells.min(self.cols - self.cell / self.n)
+        } else {
+            cells
+        }
+    }
+
+    fn record(&mut self) -> bool {
+        self.log.record(&self.board);
+        self.stats.note_solution();
+        self.backtracking = true;
+        true
+    }
+}
+
+impl Solver for PlacementSolver {
+    fn name(&self) -> &'static str {
+        "Square by square search"
+    }
+
+    fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.stats.steps += 1;
+
+        if self.backtracking {
+            if let Some((r, c)) = self.stack.pop() {
+                self.board[r][c] = 0;
+                self.cell = c * self.n + r + 1;
+                self.backtracking = false;
+                self.stats.backtracks += 1;
+            } else {
+                self.finished = true;
+                return false;
+            }
+        }
+
+        let placed = self.stack.len() + self.fixed.len();
+        if self.target == Some(placed) {
+            return self.record();
+        }
+
+        // Stop once the squares left can't reach the target or the best so far
+        let goal = self.target.unwrap_or(self.best);
+        while self.cell < self.n * self.cols && placed + self.capacity() >= goal {
+            let (row, col) = (self.cell % self.n, self.cell / self.n);
+            self.cell += 1;
+            if self.is_safe(row, col) {
+                self.board[row][col] = 1;
+                self.stack.push((row, col));
+                self.stats.placements += 1;
+                return false;
+            }
+        }
+
+        // Every square after the placed ones has been tried
+        if self.target.is_none() && self.cell == self.n * self.cols && placed >= self.best {
+            if placed > self.best {
+                // Smaller placements no longer count
+                self.best = placed;
+                self.log = SolutionLog::new(self.topology);
+            }
+            return self.record();
+        }
+
+        self.backtracking = true;
+        false
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        self.stack.last().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Goal;
+
+    /// Non-attacking sets of k queens, counted by brute force.
+    fn brute_force(n: usize, constraints: &Constraints, k: usize) -> usize {
+        fn extend(
+            n: usize,
+            constraints: &Constraints,
+            squares: &[(usize, usize)],
+            placed: &mut Vec<(usize, usize)>,
+            k: usize,
+        ) -> usize {
+            if placed.len() == k {
+                return 1;
+            }
+            let mut count = 0;
+            for (i, &square) in squares.iter().enumerate() {
+                if placed.iter().all(|&p| !constraints.attacks(n, p, square)) {
+                    placed.push(square);
+                    count += extend(n, constraints, &squares[i + 1..], placed, k);
+                    placed.pop();
+                }
+            }
+            count
+        }
+        let squares: Vec<(usize, usize)> = (0..n)
+            .flat_map(|r| (0..constraints.cols(n)).map(move |c| (r, c)))
+            .collect();
+        extend(n, constraints, &squares, &mut Vec::new(), k)
+    }
+
+    fn rectangle(goal: Goal) -> Constraints {
+        Constraints {
+            width: Some(6),
+            goal,
+            ..Constraints::default()
+        }
+    }
+
+    fn run(solver: &mut PlacementSolver) -> Vec<usize> {
+        let mut sizes = Vec::new();
+        while !solver.is_finished() {
+            solver.step();
+            sizes.push(solver.solutions().placements.len());
+        }
+        sizes
+    }
+
+    #[test]
+    fn k_queens_on_a_rectangle() {
+        for k in 1..=5 {
+            let constraints = rectangle(Goal::Place(k));
+            let mut solver = PlacementSolver::new(4, &constraints);
+            run(&mut solver);
+            let found = &solver.solutions().placements;
+            assert_eq!(found.len(), brute_force(4, &constraints, k), "k = {}", k);
+            assert!(found.iter().all(|p| p.len() == k));
+        }
+    }
+
+    #[test]
+    fn maximum_keeps_only_the_largest_placements() {
+        let constraints = rectangle(Goal::Maximum);
+        let mut solver = PlacementSolver::new(4, &constraints);
+        run(&mut solver);
+        // Four queens fit on 4 x 6, five don't
+        let found = &solver.solutions().placements;
+        assert_eq!(found.len(), brute_force(4, &constraints, 4));
+        assert_eq!(brute_force(4, &constraints, 5), 0);
+        assert!(found.iter().all(|p| p.len() == 4));
+
+        // The first knights found are not the most that fit, so the log
+        // starts over when a larger placement turns up
+        let knights = Constraints {
+            piece: Piece::Knight,
+            ..rectangle(Goal::Maximum)
+        };
+        let mut solver = PlacementSolver::new(4, &knights);
+        let sizes = run(&mut solver);
+        assert!(sizes.windows(2).any(|w| w[1] < w[0]));
+        let found = &solver.solutions().placements;
+        assert!(found.iter().all(|p| p.len() == 12));
+        assert_eq!(found.len(), brute_force(4, &knights, 12));
+        assert_eq!(brute_force(4, &knights, 13), 0);
+    }
+}
@@ -0,0 +1,273 @@
+use super::{Board, Domains, SolutionLog, Solver, SolverStats, Topology};
+
+/// Most events a recording keeps; steps after that are still searched but
+/// no longer logged.
+pub const MAX_EVENTS: usize = 2_000_000;
+
+/// What a search did to the board during one step.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    /// A square was considered for a piece...
+    Try((usize, usize)),
+    /// ...and ruled out, because it is attacked or blocked.
+    Reject((usize, usize)),
+    /// A piece of the given army (1 white, 2 black) was put down.
+    Place((usize, usize), u8),
+    /// A piece was taken back off the board.
+    Backtrack((usize, usize)),
+    /// The board now holds a solution.
+    Solution,
+    /// The solutions so far no longer count, because larger ones exist.
+    Discard,
+}
+
+/// A search as a list of (step, event). Step 0 places the pieces the board
+/// starts with; the calls to `step()` count from 1. Steps that changed
+/// nothing have no events.
+#[derive(Clone, Default)]
+pub struct Trace {
+    pub steps: u64,
+    pub events: Vec<(u64, Event)>,
+}
+
+/// Runs a solver and logs what each step did to the board. The squares
+/// tried and rejected come from `Solver::rejected`; everything else is read
+/// off the board between steps, so any solver can be recorded.
+pub struct TraceRecorder {
+    solver: Box<dyn Solver>,
+    trace: Trace,
+    truncated: bool,
+}
+
+impl TraceRecorder {
+    pub fn new(solver: Box<dyn Solver>) -> Self {
+        let board = solver.board();
+        let events = (0..board.len())
+            .flat_map(|r| (0..board[r].len()).map(move |c| (r, c)))
+            .filter(|&(r, c)| board[r][c] != 0)
+            .map(|(r, c)| (0, Event::Place((r, c), board[r][c])))
+            .collect();
+        Self {
+            solver,
+            trace: Trace { steps: 0, events },
+            truncated: false,
+        }
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// Whether steps were left out for going over `MAX_EVENTS`.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl Solver for TraceRecorder {
+    fn name(&self) -> &'static str {
+        self.solver.name()
+    }
+
+    fn step(&mut self) -> bool {
+        if self.truncated || self.solver.is_finished() {
+            return self.solver.step();
+        }
+        let before = self.solver.board().clone();
+        let found = self.solver.solutions().solutions.len();
+        let solved = self.solver.step();
+        let step = self.trace.steps + 1;
+        let after = self.solver.board();
+
+        let mut events = Vec::new();
+        let changed = || {
+            (0..before.len())
+                .flat_map(|r| (0..before[r].len()).map(move |c| (r, c)))
+                .filter(|&(r, c)| before[r][c] != after[r][c])
+        };
+        for (r, c) in changed().filter(|&(r, c)| before[r][c] != 0) {
+            events.push(Event::Backtrack((r, c)));
+        }
+        for &square in self.solver.rejected() {
+            events.push(Event::Try(square));
+            events.push(Event::Reject(square));
+        }
+        for (r, c) in changed().filter(|&(r, c)| after[r][c] != 0) {
+            events.push(Event::Try((r, c)));
+            events.push(Event::Place((r, c), after[r][c]));
+        }
+        // Solvers after the largest placements start their list over
+        if self.solver.solutions().solutions.len() < found + solved as usize {
+            events.push(Event::Discard);
+        }
+        if solved {
+            events.push(Event::Solution);
+        }
+
+        if self.trace.events.len() + events.len() > MAX_EVENTS {
+            self.truncated = true;
+        } else {
+            self.trace.steps = step;
+            self.trace
+                .events
+                .extend(events.into_iter().map(|e| (step, e)));
+        }
+        solved
+    }
+
+    fn board(&self) -> &Board {
+        self.solver.board()
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        self.solver.solutions()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.solver.is_finished()
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.solver.stats()
+    }
+
+    fn restore_last_solution(&mut self) {
+        self.solver.restore_last_solution();
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        self.solver.last_placed()
+    }
+
+    fn domains(&self) -> Option<&Domains> {
+        self.solver.domains()
+    }
+
+    fn rejected(&self) -> &[(usize, usize)] {
+        self.solver.rejected()
+    }
+
+    fn recording(&self) -> Option<&TraceRecorder> {
+        Some(self)
+    }
+}
+
+/// Plays a recorded trace back step by step, rebuilding the board from its
+/// events instead of searching. Its counters count the pieces put down and
+/// taken back, which some solvers count differently.
+pub struct TraceReplay {
+    trace: Trace,
+    topology: Topology,
+    // Index of the first event of the next step
+    next: usize,
+    step: u64,
+    board: Board,
+    log: SolutionLog,
+    stats: SolverStats,
+    last_placed: Option<(usize, usize)>,
+    rejected: Vec<(usize, usize)>,
+    rejections: u64,
+}
+
+impl TraceReplay {
+    /// Starts on an empty board of (rows, cols) with the pieces of step 0.
+    pub fn new(trace: Trace, size: (usize, usize), topology: Topology) -> Self {
+        let mut replay = Self {
+            trace,
+            topology,
+            next: 0,
+            step: 0,
+            board: vec![vec![0; size.1]; size.0],
+            log: SolutionLog::new(topology),
+            stats: SolverStats::default(),
+            last_placed: None,
+            rejected: Vec::new(),
+            rejections: 0,
+        };
+        replay.apply_step();
+        replay.stats = SolverStats::default();
+        replay
+    }
+
+    /// Play the events of the current step. Returns true when one of them
+    /// is a solution.
+    fn apply_step(&mut self) -> bool {
+        self.last_placed = None;
+        self.rejected.clear();
+        let mut solved = false;
+        while let Some(&(step, event)) = self.trace.events.get(self.next) {
+            if step != self.step {
+                break;
+            }
+            self.next += 1;
+            match event {
+                Event::Try(_) => {}
+                Event::Reject(square) => self.rejected.push(square),
+                Event::Place((r, c), colour) => {
+                    self.board[r][c] = colour;
+                    self.last_placed = Some((r, c));
+                    self.stats.placements += 1;
+                }
+                Event::Backtrack((r, c)) => {
+                    self.board[r][c] = 0;
+                    self.stats.backtracks += 1;
+                }
+                Event::Discard => self.log = SolutionLog::new(self.topology),
+                Event::Solution => {
+                    self.log.record(&self.board);
+                    self.stats.note_solution();
+                    solved = true;
+                }
+            }
+        }
+        self.rejections += self.rejected.len() as u64;
+        solved
+    }
+}
+
+impl Solver for TraceReplay {
+    fn name(&self) -> &'static str {
+        "Trace replay"
+    }
+
+    fn step(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        self.step += 1;
+        self.stats.steps = self.step;
+        let solved = self.apply_step();
+        self.stats.extra = vec![("Rejected squares", self.rejections)];
+        solved
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.step >= self.trace.steps
+    }
+
+    fn stats(&self) -> SolverStats {
+        self.stats.clone()
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        self.last_placed
+    }
+
+    fn rejected(&self) -> &[(usize, usize)] {
+        &self.rejected
+    }
+}
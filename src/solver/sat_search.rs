@@ -0,0 +1,121 @@
+use super::{Algorithm, Board, Constraints, SolutionLog, Solver, SolverStats};
+use crate::cnf::{self, Encoding};
+use crate::sat::{Cdcl, Learning, Outcome};
+
+/// Solves the DIMACS encoding of the board in-process. Each step is one
+/// decision or one conflict; after a model is found a clause blocking it is
+/// added so the search goes on to the next solution.
+pub struct SatSearch {
+    n: usize,
+    sat: Cdcl,
+    board: Board,
+    log: SolutionLog,
+    learning: Learning,
+    finished: bool,
+    stats: SolverStats,
+}
+
+impl SatSearch {
+    pub fn new(
+        n: usize,
+        encoding: Encoding,
+        learning: Learning,
+        constraints: &Constraints,
+    ) -> Self {
+        let cnf = cnf::encode(n, encoding, constraints);
+        Self {
+            n,
+            // Deciding a board variable true reads as "place a queen"
+            sat: Cdcl::new(cnf.num_vars, &cnf.clauses, learning, n * n),
+            board: vec![vec![0; n]; n],
+            log: SolutionLog::new(constraints.topology),
+            learning,
+            finished: false,
+            stats: SolverStats::default(),
+        }
+    }
+
+    fn sync_board(&mut self) {
+        for row in 0..self.n {
+            for col in 0..self.n {
+                let var = cnf::var(self.n, row, col) as usize;
+                self.board[row][col] = u8::from(self.sat.value(var) == Some(true));
+            }
+        }
+    }
+}
+
+impl Solver for SatSearch {
+    fn name(&self) -> &'static str {
+        match self.learning {
+            Learning::FirstUip => Algorithm::SatCdcl.label(),
+            Learning::Decisions => Algorithm::SatDpll.label(),
+        }
+    }
+
+    fn step(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.stats.steps += 1;
+
+        let outcome = self.sat.step();
+        self.sync_board();
+        match outcome {
+            Outcome::Sat => {
+                self.log.record(&self.board);
+                self.stats.placements = self.sat.decisions;
+                self.stats.note_solution();
+                let blocking = (0..self.n)
+                    .flat_map(|row| (0..self.n).map(move |col| (row, col)))
+                    .filter(|&(row, col)| self.board[row][col] == 1)
+                    .map(|(row, col)| -cnf::var(self.n, row, col))
+                    .collect();
+                self.sat.add_clause(blocking);
+                true
+            }
+            Outcome::Unsat => {
+                self.finished = true;
+                false
+            }
+            Outcome::Decided | Outcome::Conflict => false,
+        }
+    }
+
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn solutions(&self) -> &SolutionLog {
+        &self.log
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn stats(&self) -> SolverStats {
+        let mut stats = self.stats.clone();
+        stats.placements = self.sat.decisions;
+        stats.backtracks = self.sat.conflicts;
+        stats.extra = vec![
+            ("Decisions", self.sat.decisions),
+            ("Conflicts", self.sat.conflicts),
+            ("Propagations", self.sat.propagations),
+            ("Learned clauses", self.sat.learned),
+        ];
+        stats
+    }
+
+    fn restore_last_solution(&mut self) {
+        if let Some(board) = &self.log.last_board {
+            self.board = board.clone();
+        }
+    }
+
+    fn last_placed(&self) -> Option<(usize, usize)> {
+        let lit = self.sat.last_decision().filter(|&lit| lit > 0)?;
+        let var = lit as usize - 1;
+        (var < self.n * self.n).then_some((var / self.n, var % self.n))
+    }
+}
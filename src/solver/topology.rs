@@ -0,0 +1,47 @@
+/// How the board edges behave for the diagonals.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Topology {
+    #[default]
+    Standard,
+    /// Diagonals wrap around the edges (modular n-queens).
+    Toroidal,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 2] = [Topology::Standard, Topology::Toroidal];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Topology::Standard => "Standard",
+            Topology::Toroidal => "Toroidal",
+        }
+    }
+
+    /// Short name used in exported headers.
+    pub fn key(self) -> &'static str {
+        match self {
+            Topology::Standard => "standard",
+            Topology::Toroidal => "toroidal",
+        }
+    }
+
+    /// Whether n queens fit at all. On the torus that needs gcd(n, 6) = 1
+    /// (Pólya); the standard board has solutions for every n >= 4.
+    pub fn has_solutions(self, n: usize) -> bool {
+        match self {
+            Topology::Standard => n >= 4 || n == 1,
+            Topology::Toroidal => !n.is_multiple_of(2) && !n.is_multiple_of(3),
+        }
+    }
+
+    /// Why `has_solutions` is false, for the user.
+    pub fn no_solution_reason(self, n: usize) -> Option<String> {
+        (!self.has_solutions(n)).then(|| match self {
+            Topology::Standard => format!("No solution exists for n = {}", n),
+            Topology::Toroidal => format!(
+                "No toroidal solution exists for n = {}: it needs gcd(n, 6) = 1",
+                n
+            ),
+        })
+    }
+}
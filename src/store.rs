@@ -0,0 +1,380 @@
+//! Packed binary store for long solution lists, where every solution is a
+//! permutation (one piece per column of an n x n board).
+//!
+//! A 16 byte header
+//!
+//! | bytes | field                                                  |
+//! |-------|--------------------------------------------------------|
+//! | 0-3   | magic `NQPS`                                           |
+//! | 4     | format version, 1                                      |
+//! | 5     | flags: 1 unique only, 2 toroidal, 4 complete           |
+//! | 6     | n                                                      |
+//! | 7     | piece, as its index in `Piece::PRESETS`                |
+//! | 8-15  | number of solutions, little-endian                     |
+//!
+//! is followed by the solutions back to back, each in ⌈n·log2 n⌉ bits: the
+//! rows p0, p1, ... of the pieces in columns a, b, ... read as the number
+//! p0 + p1·n + p2·n² + ..., least significant bit first. Solution i starts
+//! at bit i·⌈n·log2 n⌉ of the body, so any one can be read without the rest.
+//! The count is filled in when writing finishes.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::solver::{class_key, get_variants, Constraints, Piece, Topology, BOARD_SIZES};
+
+const MAGIC: &[u8; 4] = b"NQPS";
+const VERSION: u8 = 1;
+const HEADER_LEN: u64 = 16;
+
+/// Only the first solution of each symmetry class was stored.
+pub const UNIQUE: u8 = 1;
+/// The solutions are for the torus.
+pub const TOROIDAL: u8 = 2;
+/// The enumeration ran to the end, so the store holds every solution.
+pub const COMPLETE: u8 = 4;
+
+/// Number of bits a permutation of n takes: the smallest b with 2^b >= n^n.
+pub fn bits_per_solution(n: usize) -> u64 {
+    let mut power = vec![1u32];
+    for _ in 0..n {
+        mul_add(&mut power, n as u32, 0);
+    }
+    // n^n - 1 is the largest value to store
+    let mut borrow = true;
+    for limb in power.iter_mut() {
+        let (value, under) = limb.overflowing_sub(borrow as u32);
+        *limb = value;
+        borrow = under;
+    }
+    let top = power.iter().rposition(|&limb| limb != 0);
+    top.map_or(0, |i| i as u64 * 32 + 32 - power[i].leading_zeros() as u64)
+}
+
+/// `value = value * factor + add`, on little-endian 32-bit limbs.
+fn mul_add(value: &mut Vec<u32>, factor: u32, add: u32) {
+    let mut carry = add as u64;
+    for limb in value.iter_mut() {
+        let product = *limb as u64 * factor as u64 + carry;
+        *limb = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        value.push(carry as u32);
+    }
+}
+
+/// `value /= divisor`, returning the remainder.
+fn div_rem(value: &mut [u32], divisor: u32) -> u32 {
+    let mut rem = 0u64;
+    for limb in value.iter_mut().rev() {
+        let current = rem << 32 | *limb as u64;
+        *limb = (current / divisor as u64) as u32;
+        rem = current % divisor as u64;
+    }
+    rem as u32
+}
+
+/// The board and the flags a store was written with.
+#[derive(Clone, Copy)]
+pub struct Header {
+    pub n: usize,
+    pub piece: usize,
+    pub flags: u8,
+    pub count: u64,
+}
+
+impl Header {
+    pub fn piece(&self) -> Piece {
+        Piece::PRESETS[self.piece].clone()
+    }
+
+    pub fn topology(&self) -> Topology {
+        if self.flags & TOROIDAL != 0 {
+            Topology::Toroidal
+        } else {
+            Topology::Standard
+        }
+    }
+
+    /// Piece and topology of the board; stores keep no mask.
+    pub fn constraints(&self) -> Constraints {
+        Constraints {
+            piece: self.piece(),
+            topology: self.topology(),
+            ..Constraints::default()
+        }
+    }
+
+    fn to_bytes(self) -> [u8; HEADER_LEN as usize] {
+        let mut bytes = [0; HEADER_LEN as usize];
+        bytes[..4].copy_from_slice(MAGIC);
+        bytes[4] = VERSION;
+        bytes[5] = self.flags;
+        bytes[6] = self.n as u8;
+        bytes[7] = self.piece as u8;
+        bytes[8..].copy_from_slice(&self.count.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_LEN as usize]) -> Result<Self, String> {
+        if &bytes[..4] != MAGIC {
+            return Err("not a packed solution store".to_owned());
+        }
+        if bytes[4] != VERSION {
+            return Err(format!("store version {} is not supported", bytes[4]));
+        }
+        let n = bytes[6] as usize;
+        if !BOARD_SIZES.contains(&n) {
+            return Err(format!("a {}x{} board is not supported", n, n));
+        }
+        let piece = bytes[7] as usize;
+        if piece >= Piece::PRESETS.len() || !Piece::PRESETS[piece].moves_like_rook() {
+            return Err(format!("piece {} can't fill one square per column", piece));
+        }
+        let mut count = [0; 8];
+        count.copy_from_slice(&bytes[8..]);
+        Ok(Self {
+            n,
+            piece,
+            flags: bytes[5],
+            count: u64::from_le_bytes(count),
+        })
+    }
+}
+
+/// The piece number to store for `constraints`, if its solutions are
+/// permutations that a store can hold.
+pub fn storable(n: usize, constraints: &Constraints) -> Result<usize, String> {
+    if !constraints.fills_columns(n) || n > 30 {
+        return Err("only n pieces on an n x n board, one per column, can be stored".to_owned());
+    }
+    Piece::PRESETS
+        .iter()
+        .position(|p| *p == constraints.piece)
+        .ok_or_else(|| "custom leapers can't be stored".to_owned())
+}
+
+/// Whether `rows` is the canonical board of its symmetry class (see
+/// `solver::class_key`), the first of the class the searches find.
+pub fn is_first_of_class(rows: &[usize], topology: Topology) -> bool {
+    let n = rows.len();
+    let squares: Vec<(usize, usize)> = rows.iter().enumerate().map(|(c, &r)| (r, c)).collect();
+    let ones = vec![1; n];
+    let key = class_key(&squares, &ones);
+    get_variants(&squares, (n, n), topology)
+        .iter()
+        .all(|variant| class_key(variant, &ones) >= key)
+}
+
+/// Writes solutions one at a time as they are found, then fills in the
+/// count.
+pub struct StoreWriter<W: Write + Seek> {
+    out: W,
+    header: Header,
+    bits: u64,
+    // Bits not yet making up a whole byte
+    pending: u8,
+    pending_bits: u32,
+}
+
+impl<W: Write + Seek> StoreWriter<W> {
+    pub fn new(mut out: W, n: usize, piece: usize, flags: u8) -> Result<Self, String> {
+        let header = Header {
+            n,
+            piece,
+            flags,
+            count: 0,
+        };
+        out.write_all(&header.to_bytes()).map_err(write_error)?;
+        Ok(Self {
+            out,
+            header,
+            bits: bits_per_solution(n),
+            pending: 0,
+            pending_bits: 0,
+        })
+    }
+
+    /// Append the solution with `rows[c]` the row of the piece in column c.
+    pub fn push(&mut self, rows: &[usize]) -> Result<(), String> {
+        let n = self.header.n;
+        if rows.len() != n || rows.iter().any(|&r| r >= n) {
+            return Err(format!("{:?} is not a solution of {}", rows, n));
+        }
+        let mut value = vec![0u32];
+        for &row in rows.iter().rev() {
+            mul_add(&mut value, n as u32, row as u32);
+        }
+        for bit in 0..self.bits {
+            let limb = value.get((bit / 32) as usize).copied().unwrap_or(0);
+            self.pending |= ((limb >> (bit % 32)) as u8 & 1) << self.pending_bits;
+            self.pending_bits += 1;
+            if self.pending_bits == 8 {
+                self.out.write_all(&[self.pending]).map_err(write_error)?;
+                self.pending = 0;
+                self.pending_bits = 0;
+            }
+        }
+        self.header.count += 1;
+        Ok(())
+    }
+
+    pub fn count(&self) -> u64 {
+        self.header.count
+    }
+
+    /// Write the last bits and the count, marking the store complete if
+    /// every solution was pushed.
+    pub fn finish(mut self, complete: bool) -> Result<W, String> {
+        if self.pending_bits > 0 {
+            self.out.write_all(&[self.pending]).map_err(write_error)?;
+        }
+        if complete {
+            self.header.flags |= COMPLETE;
+        }
+        self.out.seek(SeekFrom::Start(0)).map_err(write_error)?;
+        self.out
+            .write_all(&self.header.to_bytes())
+            .map_err(write_error)?;
+        self.out.flush().map_err(write_error)?;
+        Ok(self.out)
+    }
+}
+
+fn write_error(e: std::io::Error) -> String {
+    format!("could not write the store: {}", e)
+}
+
+/// Reads solutions by index without loading the file.
+pub struct StoreReader<R: Read + Seek> {
+    input: R,
+    pub header: Header,
+    bits: u64,
+}
+
+impl<R: Read + Seek> StoreReader<R> {
+    pub fn new(mut input: R) -> Result<Self, String> {
+        let mut bytes = [0; HEADER_LEN as usize];
+        input
+            .read_exact(&mut bytes)
+            .map_err(|_| "not a packed solution store".to_owned())?;
+        let header = Header::from_bytes(&bytes)?;
+        let bits = bits_per_solution(header.n);
+        // A short file means an interrupted write
+        let end = input.seek(SeekFrom::End(0)).map_err(read_error)?;
+        let needed = HEADER_LEN + (header.count * bits).div_ceil(8);
+        if end < needed {
+            return Err(format!(
+                "the store should hold {} solutions but is cut short",
+                header.count
+            ));
+        }
+        Ok(Self {
+            input,
+            header,
+            bits,
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.header.count
+    }
+
+    /// Rows of the pieces in each column of solution `i`.
+    pub fn get(&mut self, i: u64) -> Result<Vec<usize>, String> {
+        if i >= self.header.count {
+            return Err(format!(
+                "solution {} is past the end of {}",
+                i + 1,
+                self.header.count
+            ));
+        }
+        let n = self.header.n;
+        let start = i * self.bits;
+        let mut bytes = vec![0; ((start % 8 + self.bits).div_ceil(8)) as usize];
+        self.input
+            .seek(SeekFrom::Start(HEADER_LEN + start / 8))
+            .map_err(read_error)?;
+        self.input.read_exact(&mut bytes).map_err(read_error)?;
+
+        let mut value = vec![0u32; (self.bits as usize).div_ceil(32).max(1)];
+        for bit in 0..self.bits {
+            let at = start % 8 + bit;
+            let set = bytes[(at / 8) as usize] >> (at % 8) & 1;
+            value[(bit / 32) as usize] |= (set as u32) << (bit % 32);
+        }
+        let rows: Vec<usize> = (0..n)
+            .map(|_| div_rem(&mut value, n as u32) as usize)
+            .collect();
+        if value.iter().any(|&limb| limb != 0) {
+            return Err(format!("solution {} is corrupt", i + 1));
+        }
+        Ok(rows)
+    }
+}
+
+fn read_error(e: std::io::Error) -> String {
+    format!("could not read the store: {}", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+    use std::io::Cursor;
+
+    fn write(n: usize, solutions: &[Vec<usize>]) -> Vec<u8> {
+        let mut writer = StoreWriter::new(Cursor::new(Vec::new()), n, 0, 0).unwrap();
+        for rows in solutions {
+            writer.push(rows).unwrap();
+        }
+        writer.finish(true).unwrap().into_inner()
+    }
+
+    #[test]
+    fn solution_widths() {
+        assert_eq!(bits_per_solution(1), 0);
+        assert_eq!(bits_per_solution(2), 2);
+        assert_eq!(bits_per_solution(8), 24);
+        assert_eq!(bits_per_solution(30), 148);
+    }
+
+    #[test]
+    fn random_permutations_read_back_in_any_order() {
+        let mut rng = Rng::new(7);
+        for n in [5, 7, 13, 30] {
+            let solutions: Vec<Vec<usize>> = (0..40)
+                .map(|_| {
+                    let mut rows: Vec<usize> = (0..n).collect();
+                    for i in (1..n).rev() {
+                        rows.swap(i, rng.below(i + 1));
+                    }
+                    rows
+                })
+                .collect();
+            let mut reader = StoreReader::new(Cursor::new(write(n, &solutions))).unwrap();
+            assert_eq!(reader.len(), 40);
+            assert_ne!(COMPLETE & reader.header.flags, 0);
+            // Most of these start partway through a byte
+            for i in [39, 0, 17, 3, 22, 1, 38, 9] {
+                assert_eq!(reader.get(i).unwrap(), solutions[i as usize], "n = {}", n);
+            }
+            assert!(reader.get(40).is_err());
+        }
+    }
+
+    #[test]
+    fn damaged_stores_are_rejected() {
+        let mut bytes = write(8, &[vec![0; 8], vec![7; 8], vec![3; 8]]);
+        bytes.pop();
+        let short = StoreReader::new(Cursor::new(bytes)).err().unwrap();
+        assert!(short.contains("cut short"), "{}", short);
+
+        // 12 bits per solution of 5, all set: 4095 is past 5^5 - 1
+        let mut bytes = write(5, &[vec![4, 3, 2, 1, 0]]);
+        bytes[HEADER_LEN as usize] = 0xff;
+        bytes[HEADER_LEN as usize + 1] = 0x0f;
+        let mut reader = StoreReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.get(0), Err("solution 1 is corrupt".to_owned()));
+    }
+}
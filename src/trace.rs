@@ -0,0 +1,235 @@
+//! Search traces as text: a header describing the run, then one line per
+//! event, so two runs can be diffed and a run replayed without its solver.
+//!
+//! ```text
+//! trace 1
+//! n 5
+//! cols 5
+//! piece queen
+//! topology standard
+//! goal Fill the board
+//! algorithm Backtracking
+//! ordering Left to right / Ascending
+//! steps 108
+//! 1 t a1
+//! 1 p a1
+//! 2 t b1
+//! 2 r b1
+//! ...
+//! 6 s
+//! ```
+//!
+//! Events are `<step> <code> [square]` with the codes t (try), r (reject),
+//! p (place), b (backtrack) and s (solution), plus d (discard) where a
+//! search for the largest placements drops the smaller ones found before.
+//! Squares are in the app's notation, black army pieces prefixed with B.
+//! Step 0 holds the pieces the board starts with. A `pieces` line gives the
+//! count when the goal is to place a given number, and a `mask` line (rows
+//! joined by `/`) follows the header when squares are fixed or blocked.
+//! Lines starting with # are comments.
+
+use crate::import::{self, RunIn};
+use crate::solver::{self, Constraints, Event, Goal, SolverConfig, Trace};
+use crate::validate::Placement;
+
+const VERSION: &str = "1";
+
+/// A trace read back from a file, with the board it was recorded on.
+pub struct Loaded {
+    pub n: usize,
+    pub constraints: Constraints,
+    pub trace: Trace,
+    /// "Backtracking, Left to right / Ascending", as written by the recorder.
+    pub description: String,
+}
+
+pub fn to_text(
+    n: usize,
+    constraints: &Constraints,
+    config: &SolverConfig,
+    trace: &Trace,
+) -> String {
+    let mut out = format!(
+        "trace {}\nn {}\ncols {}\npiece {}\ntopology {}\ngoal {}\nalgorithm {}\n",
+        VERSION,
+        n,
+        constraints.cols(n),
+        constraints.piece.key(),
+        constraints.topology.key(),
+        constraints.goal.label(),
+        config.algorithm.label(),
+    );
+    if let Goal::Place(k) = constraints.goal {
+        out.push_str(&format!("pieces {}\n", k));
+    }
+    if config.uses_ordering() {
+        out.push_str(&format!(
+            "ordering {} / {}\n",
+            config.ordering.columns.label(),
+            config.ordering.rows.label()
+        ));
+    }
+    if !constraints.is_empty() {
+        out.push_str(&format!("mask {}\n", constraints.to_mask(n).join("/")));
+    }
+    out.push_str(&format!("steps {}\n", trace.steps));
+    for &(step, event) in &trace.events {
+        let line = match event {
+            Event::Try(square) => format!("{} t {}", step, name(square, 1)),
+            Event::Reject(square) => format!("{} r {}", step, name(square, 1)),
+            Event::Place(square, colour) => format!("{} p {}", step, name(square, colour)),
+            Event::Backtrack(square) => format!("{} b {}", step, name(square, 1)),
+            Event::Solution => format!("{} s", step),
+            Event::Discard => format!("{} d", step),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn name(square: (usize, usize), colour: u8) -> String {
+    if colour == 2 {
+        solver::army_notation(&[square], &[colour])
+    } else {
+        solver::notation(&[square])
+    }
+}
+
+/// Read a trace. Custom leapers are taken from `current`, as for JSON imports.
+pub fn parse(content: &str, current: &Constraints) -> Result<Loaded, String> {
+    let mut header: Vec<(&str, &str)> = Vec::new();
+    let mut events = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at = |e: String| format!("line {}: {}", i + 1, e);
+        if line.starts_with(|c: char| c.is_ascii_digit()) {
+            events.push(event(line).map_err(at)?);
+        } else {
+            header.push(line.split_once(' ').unwrap_or((line, "")));
+        }
+    }
+
+    let get = |key: &str| {
+        header
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.trim())
+            .ok_or(format!("the trace has no \"{}\" line", key))
+    };
+    let version = get("trace").map_err(|_| "not a search trace".to_owned())?;
+    if version != VERSION {
+        return Err(format!("trace version {} is not supported", version));
+    }
+    let number = |key: &str| -> Result<u64, String> {
+        get(key)?
+            .parse()
+            .map_err(|_| format!("\"{}\" needs a number", key))
+    };
+    let run = RunIn {
+        n: number("n")? as usize,
+        cols: number("cols")? as usize,
+        piece: get("piece")?.to_owned(),
+        topology: get("topology")?.to_owned(),
+        goal: get("goal")?.to_owned(),
+        mask: get("mask").ok().map(|rows| vec![rows.to_owned()]),
+    };
+    let (n, mut constraints) = import::run_constraints(&run, current)?;
+    if let Goal::Place(_) = constraints.goal {
+        constraints.goal = Goal::Place(number("pieces")? as usize);
+    }
+    let steps = number("steps")?;
+
+    let mut last = 0;
+    for &(step, event) in &events {
+        if step < last || step > steps {
+            return Err(format!(
+                "step {} is out of order (steps run from 0 to {})",
+                step, steps
+            ));
+        }
+        last = step;
+        let square = match event {
+            Event::Try(sq) | Event::Reject(sq) | Event::Place(sq, _) | Event::Backtrack(sq) => sq,
+            Event::Solution | Event::Discard => continue,
+        };
+        if square.0 >= n || square.1 >= run.cols {
+            return Err(format!(
+                "step {}: {} is off the {}x{} board",
+                step,
+                name(square, 1),
+                n,
+                run.cols
+            ));
+        }
+    }
+
+    let mut description = get("algorithm")?.to_owned();
+    if let Ok(ordering) = get("ordering") {
+        description = format!("{}, {}", description, ordering);
+    }
+    Ok(Loaded {
+        n,
+        constraints,
+        trace: Trace { steps, events },
+        description,
+    })
+}
+
+/// One "<step> <code> [square]" line.
+fn event(line: &str) -> Result<(u64, Event), String> {
+    let mut parts = line.split_whitespace();
+    let step = parts
+        .next()
+        .and_then(|s| s.parse::<u64>().ok())
+        .ok_or("expected a step number")?;
+    let code = parts.next().ok_or("expected an event after the step")?;
+    let square = || -> Result<((usize, usize), u8), String> {
+        let text = parts
+            .clone()
+            .next()
+            .ok_or(format!("\"{}\" needs a square", code))?;
+        match Placement::parse(text)?.squares() {
+            (squares, colours) if squares.len() == 1 => Ok((squares[0], colours[0])),
+            _ => Err(format!("\"{}\" is not a square like c5", text)),
+        }
+    };
+    let event = match code {
+        "t" => Event::Try(square()?.0),
+        "r" => Event::Reject(square()?.0),
+        "p" => {
+            let (square, colour) = square()?;
+            Event::Place(square, colour)
+        }
+        "b" => Event::Backtrack(square()?.0),
+        "s" => Event::Solution,
+        "d" => Event::Discard,
+        other => return Err(format!("unknown event \"{}\"", other)),
+    };
+    Ok((step, event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn place_goals_keep_their_count() {
+        let constraints = Constraints {
+            goal: Goal::Place(5),
+            ..Constraints::default()
+        };
+        let trace = Trace {
+            steps: 1,
+            events: vec![(1, Event::Try((0, 0))), (1, Event::Place((0, 0), 1))],
+        };
+        let text = to_text(8, &constraints, &SolverConfig::default(), &trace);
+        let loaded = parse(&text, &Constraints::default()).unwrap();
+        assert_eq!(loaded.n, 8);
+        assert_eq!(loaded.constraints.goal, Goal::Place(5));
+        assert_eq!(loaded.trace.events, trace.events);
+    }
+}
@@ -0,0 +1,503 @@
+//! Checking placements that come from outside the solvers (imported files,
+//! submissions on the command line) and saying exactly what is wrong with
+//! them, rather than only whether they solve the board.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::solver::{Board, Constraints, Goal, Topology};
+
+/// A placement in one of the forms it is usually written down in.
+pub enum Placement {
+    /// Row of the piece in each column, counting from 0.
+    Permutation(Vec<usize>),
+    /// (row, col) squares, possibly off the board; `colours` holds 1 or 2
+    /// for each square of peaceable armies and is otherwise all 1s.
+    Squares {
+        squares: Vec<(usize, usize)>,
+        colours: Vec<u8>,
+    },
+    /// Rows of cells, non-zero where a piece stands.
+    Board(Board),
+}
+
+impl Placement {
+    /// Read one line of a submission: chess notation ("a1, c5", "Wa1, Bc2"),
+    /// board rows separated by `/` ("Q..../...Q"), or the rank of the piece
+    /// in each file counting from 1 ("1 5 8 6 3 7 2 4", commas allowed).
+    pub fn parse(text: &str) -> Result<Placement, String> {
+        let text = text.trim().trim_start_matches("(Sym)").trim();
+        if text.contains('/') || text.chars().all(|c| ".QqWB#".contains(c)) {
+            let board = text
+                .split('/')
+                .filter(|row| !row.trim().is_empty())
+                .map(|row| {
+                    row.trim()
+                        .chars()
+                        .map(|c| match c {
+                            'Q' | 'q' | 'W' => Ok(1),
+                            'B' => Ok(2),
+                            '.' | '#' => Ok(0),
+                            other => Err(format!("unexpected \"{}\" in a board row", other)),
+                        })
+                        .collect()
+                })
+                .collect::<Result<_, _>>()?;
+            return Ok(Placement::Board(board));
+        }
+        if text
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ',' || c.is_whitespace())
+        {
+            let ranks = text
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|t| !t.is_empty())
+                .map(|t| match t.parse::<usize>() {
+                    Ok(rank) if rank > 0 => Ok(rank - 1),
+                    _ => Err(format!("\"{}\" is not a rank counting from 1", t)),
+                })
+                .collect::<Result<_, _>>()?;
+            return Ok(Placement::Permutation(ranks));
+        }
+        let mut squares = Vec::new();
+        let mut colours = Vec::new();
+        for token in text.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let (colour, square) = match token.as_bytes()[0] {
+                b'W' => (1, &token[1..]),
+                b'B' => (2, &token[1..]),
+                _ => (1, token),
+            };
+            let mut chars = square.chars();
+            let file = chars
+                .next()
+                .filter(|&f| f >= 'a')
+                .map(|f| f as usize - 'a' as usize);
+            let rank = chars.as_str().parse::<usize>().ok().filter(|&r| r > 0);
+            match (file, rank) {
+                (Some(col), Some(rank)) => {
+                    squares.push((rank - 1, col));
+                    colours.push(colour);
+                }
+                _ => return Err(format!("\"{}\" is not a square like c5", token)),
+            }
+        }
+        Ok(Placement::Squares { squares, colours })
+    }
+
+    /// The pieces on a board of n rows, dropping those that are off it.
+    pub fn to_board(&self, n: usize, constraints: &Constraints) -> Board {
+        let cols = constraints.cols(n);
+        let mut board = vec![vec![0; cols]; n];
+        let (squares, colours) = self.squares();
+        for (&(r, c), &colour) in squares.iter().zip(&colours) {
+            if r < n && c < cols {
+                board[r][c] = colour;
+            }
+        }
+        board
+    }
+
+    /// The squares, in the order given, and the colour of each.
+    pub fn squares(&self) -> (Vec<(usize, usize)>, Vec<u8>) {
+        match self {
+            Placement::Permutation(rows) => (
+                rows.iter().enumerate().map(|(c, &r)| (r, c)).collect(),
+                vec![1; rows.len()],
+            ),
+            Placement::Squares { squares, colours } => (
+                squares.clone(),
+                (0..squares.len())
+                    .map(|i| colours.get(i).copied().unwrap_or(1))
+                    .collect(),
+            ),
+            Placement::Board(board) => board
+                .iter()
+                .enumerate()
+                .flat_map(|(r, cells)| {
+                    cells
+                        .iter()
+                        .enumerate()
+                        .filter(|&(_, &cell)| cell != 0)
+                        .map(move |(c, &cell)| ((r, c), cell))
+                })
+                .unzip(),
+        }
+    }
+}
+
+/// A line pieces can share.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Line {
+    Row(usize),
+    Column(usize),
+    /// Squares with the same row - col (modulo n on the torus).
+    Diagonal,
+    /// Squares with the same row + col (modulo n on the torus).
+    AntiDiagonal,
+}
+
+/// One thing wrong with a placement.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Problem {
+    /// A board given as rows has the wrong number of rows or columns.
+    Shape {
+        rows: usize,
+        cols: usize,
+    },
+    OffBoard((usize, usize)),
+    Duplicate((usize, usize)),
+    Blocked((usize, usize)),
+    MissingFixed((usize, usize)),
+    Missing {
+        expected: usize,
+        found: usize,
+    },
+    Extra {
+        expected: usize,
+        found: usize,
+    },
+    /// Two or more pieces on one line the piece attacks along.
+    SharedLine {
+        line: Line,
+        squares: Vec<(usize, usize)>,
+    },
+    /// Two pieces attacking each other other than along a shared line, e.g.
+    /// by a knight's jump, or across the armies of peaceable queens.
+    Attack((usize, usize), (usize, usize)),
+    UnevenArmies {
+        white: usize,
+        black: usize,
+    },
+    Uncovered(Vec<(usize, usize)>),
+}
+
+/// Name of a square in the app's notation, or its coordinates when the
+/// column has no letter.
+fn name((r, c): (usize, usize)) -> String {
+    if c < 26 {
+        format!("{}{}", (b'a' + c as u8) as char, r + 1)
+    } else {
+        format!("(row {}, column {})", r + 1, c + 1)
+    }
+}
+
+fn names(squares: &[(usize, usize)]) -> String {
+    squares
+        .iter()
+        .map(|&sq| name(sq))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Shape { rows, cols } => {
+                write!(f, "the board is {} rows by {} columns", rows, cols)
+            }
+            Problem::OffBoard(sq) => write!(f, "{} is off the board", name(*sq)),
+            Problem::Duplicate(sq) => write!(f, "{} is taken twice", name(*sq)),
+            Problem::Blocked(sq) => write!(f, "{} is blocked", name(*sq)),
+            Problem::MissingFixed(sq) => write!(f, "the fixed piece on {} is missing", name(*sq)),
+            Problem::Missing { expected, found } => write!(
+                f,
+                "{} piece(s) missing: {} instead of {}",
+                expected - found,
+                found,
+                expected
+            ),
+            Problem::Extra { expected, found } => write!(
+                f,
+                "{} piece(s) too many: {} instead of {}",
+                found - expected,
+                found,
+                expected
+            ),
+            Problem::SharedLine { line, squares } => {
+                let line = match line {
+                    Line::Row(r) => format!("row {}", r + 1),
+                    Line::Column(c) if *c < 26 => format!("column {}", (b'a' + *c as u8) as char),
+                    Line::Column(c) => format!("column {}", c + 1),
+                    Line::Diagonal => "a diagonal".to_owned(),
+                    Line::AntiDiagonal => "an anti-diagonal".to_owned(),
+                };
+                write!(f, "{} share {}", names(squares), line)
+            }
+            Problem::Attack(a, b) => write!(f, "{} and {} attack each other", name(*a), name(*b)),
+            Problem::UnevenArmies { white, black } => {
+                write!(f, "{} white pieces but {} black ones", white, black)
+            }
+            Problem::Uncovered(squares) => write!(f, "not covered: {}", names(squares)),
+        }
+    }
+}
+
+/// Everything wrong with a placement; empty when it solves the board.
+#[derive(Default, Debug)]
+pub struct Report {
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check `placement` against the rules of `constraints` on a board of n
+/// rows. Largest and smallest sizes (maximum, domination) aren't checked,
+/// only that the pieces do what the goal asks.
+pub fn validate(n: usize, constraints: &Constraints, placement: &Placement) -> Report {
+    let cols = constraints.cols(n);
+    let mut problems = Vec::new();
+    if let Placement::Board(board) = placement {
+        let widest = board.iter().map(Vec::len).max().unwrap_or(0);
+        if board.len() != n || board.iter().any(|row| row.len() != cols) {
+            problems.push(Problem::Shape {
+                rows: board.len(),
+                cols: widest,
+            });
+        }
+    }
+
+    // Squares on the board, once each
+    let (all, all_colours) = placement.squares();
+    let mut squares: Vec<(usize, usize)> = Vec::new();
+    let mut colours = Vec::new();
+    for (&sq, &colour) in all.iter().zip(&all_colours) {
+        if sq.0 >= n || sq.1 >= cols {
+            problems.push(Problem::OffBoard(sq));
+        } else if squares.contains(&sq) {
+            problems.push(Problem::Duplicate(sq));
+        } else {
+            if constraints.blocked.contains(&sq) {
+                problems.push(Problem::Blocked(sq));
+            }
+            squares.push(sq);
+            colours.push(colour);
+        }
+    }
+    for &sq in &constraints.fixed {
+        if !squares.contains(&sq) {
+            problems.push(Problem::MissingFixed(sq));
+        }
+    }
+
+    let found = all.len();
+    match constraints.pieces(n) {
+        Some(expected) if found < expected => problems.push(Problem::Missing { expected, found }),
+        Some(expected) if found > expected => problems.push(Problem::Extra { expected, found }),
+        _ => {}
+    }
+
+    if constraints.goal == Goal::Peaceable {
+        let black = colours.iter().filter(|&&c| c == 2).count();
+        let white = colours.len() - black;
+        if white != black {
+            problems.push(Problem::UnevenArmies { white, black });
+        }
+        for (i, &a) in squares.iter().enumerate() {
+            for (j, &b) in squares.iter().enumerate().skip(i + 1) {
+                if constraints.conflicts(colours[i], colours[j]) && constraints.attacks(n, a, b) {
+                    problems.push(Problem::Attack(a, b));
+                }
+            }
+        }
+    } else if constraints.conflicts(1, 1) {
+        problems.extend(conflicts(n, constraints, &squares));
+    }
+
+    if matches!(constraints.goal, Goal::Domination { .. }) {
+        let uncovered: Vec<(usize, usize)> = (0..cols)
+            .flat_map(|c| (0..n).map(move |r| (r, c)))
+            .filter(|sq| {
+                !constraints.blocked.contains(sq)
+                    && !squares.iter().any(|&q| constraints.attacks(n, q, *sq))
+            })
+            .collect();
+        if !uncovered.is_empty() {
+            problems.push(Problem::Uncovered(uncovered));
+        }
+    }
+    Report { problems }
+}
+
+/// Pieces attacking each other, grouped by the line they share where the
+/// piece attacks along lines.
+fn conflicts(n: usize, constraints: &Constraints, squares: &[(usize, usize)]) -> Vec<Problem> {
+    let cols = constraints.cols(n);
+    let piece = &constraints.piece;
+    // Wrapped diagonals only line up as r - c (mod n) on a square torus
+    let diagonals =
+        piece.moves_like_bishop() && (constraints.topology == Topology::Standard || cols == n);
+    let mut lines: BTreeMap<(Line, usize), Vec<(usize, usize)>> = BTreeMap::new();
+    for &(r, c) in squares {
+        let mut add = |line, key| lines.entry((line, key)).or_default().push((r, c));
+        if piece.moves_like_rook() {
+            add(Line::Row(r), 0);
+            add(Line::Column(c), 0);
+        }
+        if diagonals {
+            let (diagonal, anti) = match constraints.topology {
+                Topology::Standard => (r + cols - c, r + c),
+                Topology::Toroidal => ((r + n - c) % n, (r + c) % n),
+            };
+            add(Line::Diagonal, diagonal);
+            add(Line::AntiDiagonal, anti);
+        }
+    }
+    let mut problems: Vec<Problem> = lines
+        .into_iter()
+        .filter(|(_, on_line)| on_line.len() > 1)
+        .map(|((line, _), squares)| Problem::SharedLine { line, squares })
+        .collect();
+
+    // Everything else: jumps, and diagonals the lines above don't cover
+    let on_shared_line = |a: (usize, usize), b: (usize, usize)| {
+        problems.iter().any(|p| match p {
+            Problem::SharedLine { squares, .. } => squares.contains(&a) && squares.contains(&b),
+            _ => false,
+        })
+    };
+    let mut attacks = Vec::new();
+    for (i, &a) in squares.iter().enumerate() {
+        for &b in &squares[i + 1..] {
+            if constraints.attacks(n, a, b) && !on_shared_line(a, b) {
+                attacks.push(Problem::Attack(a, b));
+            }
+        }
+    }
+    problems.extend(attacks);
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Piece;
+
+    #[test]
+    fn reports_each_kind_of_problem() {
+        let mask = |rows: &str| Constraints::from_mask(rows).unwrap().1;
+        let goal = |goal| Constraints {
+            goal,
+            ..Constraints::default()
+        };
+        let knights = Constraints {
+            piece: Piece::Knight,
+            goal: Goal::Place(2),
+            ..Constraints::default()
+        };
+        let cases: Vec<(usize, Constraints, &str, Vec<Problem>)> = vec![
+            (8, Constraints::default(), "1 5 8 6 3 7 2 4", vec![]),
+            (
+                4,
+                Constraints::default(),
+                "a1, b3, c2, d4",
+                vec![
+                    Problem::SharedLine {
+                        line: Line::Diagonal,
+                        squares: vec![(0, 0), (3, 3)],
+                    },
+                    Problem::SharedLine {
+                        line: Line::AntiDiagonal,
+                        squares: vec![(2, 1), (1, 2)],
+                    },
+                ],
+            ),
+            (
+                4,
+                Constraints::default(),
+                "a2, b4, c1",
+                vec![Problem::Missing {
+                    expected: 4,
+                    found: 3,
+                }],
+            ),
+            (
+                4,
+                Constraints::default(),
+                "a2, b4, c1, d3, a4",
+                vec![
+                    Problem::Extra {
+                        expected: 4,
+                        found: 5,
+                    },
+                    Problem::SharedLine {
+                        line: Line::Row(3),
+                        squares: vec![(3, 1), (3, 0)],
+                    },
+                    Problem::SharedLine {
+                        line: Line::Column(0),
+                        squares: vec![(1, 0), (3, 0)],
+                    },
+                ],
+            ),
+            (
+                4,
+                Constraints::default(),
+                "a1, a1, e9",
+                vec![
+                    Problem::Duplicate((0, 0)),
+                    Problem::OffBoard((8, 4)),
+                    Problem::Missing {
+                        expected: 4,
+                        found: 3,
+                    },
+                ],
+            ),
+            (
+                4,
+                mask("..../..../..../.#.."),
+                "a2, b4, c1, d3",
+                vec![Problem::Blocked((3, 1))],
+            ),
+            (
+                4,
+                mask("..../..../Q.../...."),
+                "a2, b4, c1, d3",
+                vec![Problem::MissingFixed((2, 0))],
+            ),
+            (4, knights, "a1, b3", vec![Problem::Attack((0, 0), (2, 1))]),
+            (
+                5,
+                goal(Goal::Peaceable),
+                "Wa1, Wa2, Bc5",
+                vec![Problem::UnevenArmies { white: 2, black: 1 }],
+            ),
+            (
+                5,
+                goal(Goal::Peaceable),
+                "Wa1, Bb2",
+                vec![Problem::Attack((0, 0), (1, 1))],
+            ),
+            (
+                4,
+                goal(Goal::Domination { independent: false }),
+                "a1",
+                vec![Problem::Uncovered(vec![
+                    (2, 1),
+                    (3, 1),
+                    (1, 2),
+                    (3, 2),
+                    (1, 3),
+                    (2, 3),
+                ])],
+            ),
+            (
+                4,
+                goal(Goal::Domination { independent: true }),
+                "b2, c2",
+                vec![Problem::SharedLine {
+                    line: Line::Row(1),
+                    squares: vec![(1, 1), (1, 2)],
+                }],
+            ),
+        ];
+        for (n, constraints, text, expected) in cases {
+            let placement = Placement::parse(text).unwrap();
+            let report = validate(n, &constraints, &placement);
+            assert_eq!(report.problems, expected, "{}", text);
+        }
+    }
+}